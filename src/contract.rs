@@ -1,26 +1,119 @@
-use core::panic;
-
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Addr, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Env, MessageInfo,
-    QueryRequest, Response, StdError, StdResult, Uint128, WasmQuery,
+    from_json, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Decimal256, Deps, DepsMut, Empty, Env, Event, MessageInfo,
+    Order, QueryRequest, Response, StdError, StdResult, Storage, Timestamp, Uint128, WasmQuery,
 };
 
-use cw2::set_contract_version;
-use cw20::TokenInfoResponse;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{Cw20ReceiveMsg, TokenInfoResponse};
+use cw_storage_plus::Bound;
 
 use crate::error::ContractError;
 
-use crate::msg::{AdminAddressesResponse, CW20DiraContractAddressResponse, CollateralPriceResponse, CollateralResponse, CollateralTokenDenomResponse, LiquidationHealthResponse, MintableHealthResponse, MintedDiraResponse, StablecoinHealthResponse};
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::msg::{AdminAddressesResponse, BadDebtResponse, PositionResponse, PositionsResponse, ConfigResponse, ConfigUpdate, OwnershipResponse, PauseStateResponse, ShutdownCollateralResponse, ShutdownResponse, QueuedChangeResponse, QueuedChangesResponse, RoleGrant, RolesResponse, AuctionConfigResponse, FeederPriceResponse, FeederPricesResponse, PriceFeedersResponse, AuctionResponse, AuctionsResponse, CW20DiraContractAddressResponse, CloseFactorResponse, CollateralGain, CollateralInfoResponse, CollateralPriceResponse, CollateralResponse, CollateralsResponse, LiquidationBonusResponse, LiquidationHealthResponse, LiquidationProtocolShareResponse, MintableHealthResponse, MintedDiraResponse, NextCollateralPriceResponse, PriceGuardConfigResponse, PriceHistoryEntry, PriceHistoryResponse, PsmAssetResponse, PsmAssetsResponse, PsmSwapSimulationResponse, RedemptionFeeResponse, RedemptionQueuePosition, RedemptionQueueResponse, SavingsResponse, SavingsVaultResponse, StabilityFeeResponse, StabilityPoolDepositResponse, StabilityPoolGainsResponse, StabilityPoolResponse, StablecoinHealthResponse};
+use crate::msg::{AssetInfo, CumulativePricesResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, OracleQueryMsg, OraclePriceResponse, PairQueryMsg, QueryMsg, ReceiveMsg};
 
-use crate::state::{ADMIN_ADDRESSES, COLLATERAL_TOKEN_DENOM, COLLATERAL_TOKEN_PRICE, CW20_DIRA_CONTRACT_ADDRESS, LIQUIDATION_HEALTH, LOCKED_COLLATERAL, MINTABLE_HEALTH, MINTED_DIRA, FeeTier, FEE_SWITCH, FeeConfig};
+use crate::state::{STATE_VERSION, Config, CONFIG, LEGACY_CW20_DIRA_CONTRACT_ADDRESS, LEGACY_FEE_SWITCH, LEGACY_CLOSE_FACTOR, LEGACY_LIQUIDATION_BONUS, LEGACY_LIQUIDATION_PROTOCOL_SHARE, LEGACY_SAVINGS_RATE, LEGACY_AUCTION_CONFIG, LEGACY_PRICE_GUARD_CONFIG, LEGACY_PRICE_FEED_CONFIG, LEGACY_TIMELOCK_DELAY, LEGACY_COLLATERAL_TOKEN_DENOM, LEGACY_COLLATERAL_TOKEN_PRICE, LEGACY_LIQUIDATION_HEALTH, LEGACY_LOCKED_COLLATERAL, LEGACY_MINTABLE_HEALTH, LEGACY_MINTED_DIRA, Auction, AuctionConfig, AUCTIONS, AUCTION_COUNT, CollateralAssetType, CollateralInfo, PendingOwnershipTransfer, PENDING_OWNERSHIP_TRANSFER, ParameterChange, PauseFlags, PauseState, PAUSE_STATE, Shutdown, ShutdownCollateral, SHUTDOWN, SHUTDOWN_COLLATERALS, QueuedChange, QUEUED_CHANGES, QUEUED_CHANGE_COUNT, Role, ROLES, PriceSource, NextCollateralPrice, NEXT_COLLATERAL_PRICES, COLLATERAL_PRICE_ACCEPTED_AT, PriceGuardConfig, FeederPrice, FEEDER_PRICES, TWAP_OBSERVATIONS, PRICE_HISTORY, PRICE_HISTORY_LENGTHS, PriceFeedConfig, PRICE_FEEDERS, ADMIN_ADDRESSES, COLLATERALS, LOCKED_COLLATERAL, MINTED_DIRA, PRINCIPAL_DIRA, TOTAL_MINTED_DIRA, StabilityFeeRate, STABILITY_FEE_RATES, UNMINTED_STABILITY_FEES, BAD_DEBT, PsmAsset, PSM_ASSETS, PSM_PRICE_ACCEPTED_AT, PSM_RESERVES, RedemptionBaseRate, REDEMPTION_BASE_RATE, REDEMPTION_QUEUE, REDEMPTION_QUEUE_KEYS, SavingsVault, SAVINGS_SHARES, SAVINGS_VAULT, FeeTier, FeeConfig, StabilityPoolDeposit, StabilityPoolState, STABILITY_POOL, STABILITY_POOL_DEPOSITS, STABILITY_POOL_SUMS, STABILITY_POOL_SUM_SNAPSHOTS};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cosmwasm-stable-dira";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// Number of decimals used by the CW20 Dira token
+const DIRA_DECIMALS: u32 = 6;
+
+// Number of decimals the collateral registered at instantiation uses
+const DEFAULT_COLLATERAL_DECIMALS: u32 = 6;
+
+// Share of a position's debt that can be repaid in a single liquidation, until changed by an admin
+const DEFAULT_CLOSE_FACTOR_PERCENT: u64 = 50;
+
+// Extra collateral a liquidator seizes on top of the repaid debt, until changed by an admin
+const DEFAULT_LIQUIDATION_BONUS_PERCENT: u64 = 5;
+
+// Share of the liquidation bonus that goes to the treasury, until changed by an admin
+const DEFAULT_LIQUIDATION_PROTOCOL_SHARE_PERCENT: u64 = 10;
+
+// Factor the stability pool's running product is scaled up by once it gets too small
+const STABILITY_POOL_SCALE_FACTOR: u128 = 1_000_000_000;
+
+// Seconds in a year, used to accrue the yearly stability fee second by second
+const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+// Auctions start 20% above the collateral price, take an hour to decay down to zero and
+// have to be reset after half an hour, until changed by an admin
+const DEFAULT_AUCTION_STARTING_PREMIUM_PERCENT: u64 = 120;
+const DEFAULT_AUCTION_PRICE_DECAY_SECONDS: u64 = 3600;
+const DEFAULT_AUCTION_RESET_AFTER_SECONDS: u64 = 1800;
+
+// By default a single fresh feeder price is enough, and feeder prices go stale after an hour
+const DEFAULT_PRICE_FEED_QUORUM: u32 = 1;
+const DEFAULT_MAX_PRICE_AGE_SECONDS: u64 = 3600;
+
+// Cumulative prices reported by DEX pairs carry 6 decimals of precision
+const TWAP_PRECISION: u32 = 6;
+
+// Price history entries kept per collateral type, and how many a history query returns
+const MAX_PRICE_HISTORY_ENTRIES: u32 = 1000;
+const DEFAULT_PRICE_HISTORY_LIMIT: u32 = 30;
+const MAX_PRICE_HISTORY_LIMIT: u32 = 100;
+
+// Number of positions looked at per page of the position listing
+const DEFAULT_POSITIONS_LIMIT: u32 = 30;
+const MAX_POSITIONS_LIMIT: u32 = 100;
+
+// Admin set prices are neither limited nor delayed by default, until the guard is configured
+const DEFAULT_MAX_PRICE_DEVIATION_PERCENT: u64 = 10;
+
+// Redemptions pay a fee of the base rate plus this floor. The base rate halves every 12 hours,
+// decaying minute by minute by 0.5^(1/720), written out here with 18 decimals
+const REDEMPTION_FEE_FLOOR_PERMILLE: u64 = 5;
+const REDEMPTION_BASE_RATE_MINUTE_DECAY_FACTOR: u128 = 999_037_758_833_783_000;
+
+// Most positions a single redemption walks through, to keep its gas use bounded
+const MAX_REDEMPTION_POSITIONS: usize = 50;
+// Most positions a redemption looks at, including those below the liquidation health it skips
+const MAX_REDEMPTION_SCAN_POSITIONS: usize = 500;
+
+// Parameter changes wait two days in the queue by default, and have to be applied within
+// two weeks after that. The delay can be set between an hour and thirty days
+const DEFAULT_TIMELOCK_DELAY_SECONDS: u64 = 2 * 24 * 60 * 60;
+const MIN_TIMELOCK_DELAY_SECONDS: u64 = 60 * 60;
+const MAX_TIMELOCK_DELAY_SECONDS: u64 = 30 * 24 * 60 * 60;
+const TIMELOCK_GRACE_PERIOD_SECONDS: u64 = 14 * 24 * 60 * 60;
+
+// Ownership transfers have to be accepted within thirty days of being proposed
+const MAX_OWNERSHIP_TRANSFER_EXPIRY_SECONDS: u64 = 30 * 24 * 60 * 60;
+
+// Pauses last at most a week, and the contract cannot be paused again for two days after a
+// pause ends, so that users always get the chance to exit
+const MAX_PAUSE_DURATION_SECONDS: u64 = 7 * 24 * 60 * 60;
+const PAUSE_COOLDOWN_SECONDS: u64 = 2 * 24 * 60 * 60;
+
+// State migrations, in order. Each one upgrades the state by one version, and migrate runs
+// every one after the stored state version. They have to be safe to run on state that is
+// already partly upgraded, since instances from before the state version was tracked start
+// from the first one
+type StateMigration = fn(&mut dyn Storage, &Env) -> Result<(), ContractError>;
+
+const STATE_MIGRATIONS: &[StateMigration] = &[migrate_state_to_collateral_registry, migrate_state_to_config];
+
+// Time given to settle positions after shutdown before DIRA holders can cash out, unless
+// every position has been settled before that
+const SHUTDOWN_SETTLEMENT_PERIOD_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+// Roles every admin holds. The owner holds the Owner role on top of these
+const ADMIN_ROLES: [Role; 4] = [
+    Role::PriceFeeder,
+    Role::RiskManager,
+    Role::FeeManager,
+    Role::Pauser,
+];
+
 /****
  * THIS IS THE SECTION FOR MATCHING EXECUTE AND QUERY MESSAGES
  * FROM msg.rs IN HERE. THE ACTUAL FUNCTION IMPLEMENTATIONS ARE DONE IN THE SECTION
@@ -30,7 +123,7 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
@@ -52,26 +145,40 @@ pub fn instantiate(
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     ADMIN_ADDRESSES.save(deps.storage, &vec![info.sender.clone()])?;
-    LIQUIDATION_HEALTH.save(deps.storage, &msg.liquidation_health)?;
-    MINTABLE_HEALTH.save(deps.storage, &msg.mintable_health)?;
-    COLLATERAL_TOKEN_DENOM.save(deps.storage, &msg.collateral_token_denom)?;
 
-    let default_fee_config = FeeConfig{
-        enabled: true,
-        tier: FeeTier::Low,
+    for role in Role::ALL.iter() {
+        ROLES.save(deps.storage, (role.as_str(), &info.sender), &Empty {})?;
+    }
+
+    let initial_collateral_info = CollateralInfo {
+        asset_type: CollateralAssetType::Native,
+        collateral_price_in_dirham: None,
+        price_source: PriceSource::Manual,
+        liquidation_health: msg.liquidation_health,
+        mintable_health: msg.mintable_health,
+        debt_ceiling: None,
+        decimals: DEFAULT_COLLATERAL_DECIMALS,
+        stability_fee: Decimal::zero(),
     };
 
-    FEE_SWITCH.save(deps.storage, &default_fee_config)?;
+    COLLATERALS.save(
+        deps.storage,
+        &msg.collateral_token_denom,
+        &initial_collateral_info,
+    )?;
 
-    match msg.cw20_dira_contract_address {
-        Some(contract_address) => {
-            if helper_is_cw20_contract(deps.as_ref(), &contract_address) {
-                CW20_DIRA_CONTRACT_ADDRESS.save(deps.storage, &contract_address)?;
-            } else {
-                return Err(ContractError::InvalidCW20ContractAddress {});
-            }
+    helper_save_missing_config(deps.storage, env.block.time)?;
+    STATE_VERSION.save(deps.storage, &(STATE_MIGRATIONS.len() as u32))?;
+
+    if let Some(contract_address) = msg.cw20_dira_contract_address {
+        if helper_is_cw20_contract(deps.as_ref(), &contract_address) {
+            CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+                config.cw20_dira_contract_address = Some(contract_address);
+                Ok(config)
+            })?;
+        } else {
+            return Err(ContractError::InvalidCW20ContractAddress {});
         }
-        _ => {}
     }
 
     Ok(Response::new()
@@ -79,10 +186,48 @@ pub fn instantiate(
         .add_attribute("admin", info.sender))
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored_contract_version = get_contract_version(deps.storage)?;
+
+    if stored_contract_version.contract != CONTRACT_NAME {
+        return Err(ContractError::InvalidMigrationContract {
+            contract_name: stored_contract_version.contract,
+        });
+    }
+
+    if helper_parse_contract_version(&stored_contract_version.version)?
+        > helper_parse_contract_version(CONTRACT_VERSION)?
+    {
+        return Err(ContractError::CannotMigrateToOlderVersion {
+            stored_version: stored_contract_version.version,
+            version: CONTRACT_VERSION.to_string(),
+        });
+    }
+
+    let from_state_version = STATE_VERSION.may_load(deps.storage)?.unwrap_or_default();
+
+    for state_migration in STATE_MIGRATIONS.iter().skip(from_state_version as usize) {
+        state_migration(deps.storage, &env)?;
+    }
+
+    let to_state_version = STATE_MIGRATIONS.len() as u32;
+
+    STATE_VERSION.save(deps.storage, &to_state_version.max(from_state_version))?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", stored_contract_version.version)
+        .add_attribute("to_version", CONTRACT_VERSION)
+        .add_attribute("from_state_version", from_state_version.to_string())
+        .add_attribute("to_state_version", to_state_version.to_string()))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
@@ -90,60 +235,434 @@ pub fn execute(
     deps.api.debug(&format!("Received message: {:?}", &msg));
 
     match msg {
-        ExecuteMsg::LockCollateral {} => execute_lock_collateral(deps, info),
+        ExecuteMsg::LockCollateral { collateral_id } => {
+            execute_lock_collateral(deps, info, collateral_id)
+        }
+
+        ExecuteMsg::Receive(cw20_receive_msg) => {
+            execute_receive(deps, env, info, cw20_receive_msg)
+        }
 
         ExecuteMsg::UnlockCollateral {
+            collateral_id,
+            collateral_amount_to_unlock,
+        } => execute_unlock_collateral(
+            deps,
+            env,
+            info,
+            collateral_id,
             collateral_amount_to_unlock,
-        } => execute_unlock_collateral(deps, info, collateral_amount_to_unlock),
+        ),
 
-        ExecuteMsg::MintDira { dira_to_mint } => execute_mint_dira(deps, info, dira_to_mint),
-        ExecuteMsg::BurnDira { dira_to_burn } => execute_burn_dira(deps, info, dira_to_burn),
+        ExecuteMsg::MintDira {
+            collateral_id,
+            dira_to_mint,
+        } => execute_mint_dira(deps, env, info, collateral_id, dira_to_mint),
 
         ExecuteMsg::LiquidateStablecoins {
             wallet_address_to_liquidate,
-        } => execute_liquidate_stablecoin_minter(deps, info, wallet_address_to_liquidate),
+            collateral_id,
+            debt_to_repay,
+        } => {
+            // Let the stability pool absorb the liquidation whenever it can cover the debt,
+            // otherwise auction the seized collateral off
+            if STABILITY_POOL.load(deps.storage)?.total_deposits >= debt_to_repay {
+                execute_liquidate_stablecoin_minter(
+                    deps,
+                    env,
+                    info.sender,
+                    wallet_address_to_liquidate,
+                    collateral_id,
+                    debt_to_repay,
+                    LiquidatorPayment::StabilityPool,
+                )
+            } else {
+                execute_start_auction(
+                    deps,
+                    env,
+                    info,
+                    wallet_address_to_liquidate,
+                    collateral_id,
+                    debt_to_repay,
+                )
+            }
+        }
+
+        ExecuteMsg::ResetAuction { auction_id } => {
+            execute_reset_auction(deps, env, info, auction_id)
+        }
+
+        ExecuteMsg::AddAdmin { admin_address } => execute_add_admin(deps, info, admin_address),
+
+        ExecuteMsg::RemoveAdmin { admin_address } => {
+            execute_remove_admin(deps, info, admin_address)
+        }
+
+        ExecuteMsg::ProposeOwnershipTransfer {
+            new_owner,
+            expires_in_seconds,
+        } => execute_propose_ownership_transfer(deps, env, info, new_owner, expires_in_seconds),
+
+        ExecuteMsg::AcceptOwnershipTransfer {} => execute_accept_ownership_transfer(deps, env, info),
+
+        ExecuteMsg::CancelOwnershipTransfer {} => execute_cancel_ownership_transfer(deps, info),
+
+        ExecuteMsg::GrantRole { role, address } => execute_grant_role(deps, info, role, address),
+
+        ExecuteMsg::RevokeRole { role, address } => execute_revoke_role(deps, info, role, address),
+
+        ExecuteMsg::RegisterCollateral {
+            collateral_id,
+            asset_type,
+            liquidation_health,
+            mintable_health,
+            debt_ceiling,
+            decimals,
+        } => execute_register_collateral(
+            deps,
+            info,
+            collateral_id,
+            CollateralInfo {
+                asset_type,
+                collateral_price_in_dirham: None,
+                price_source: PriceSource::Manual,
+                liquidation_health,
+                mintable_health,
+                debt_ceiling,
+                decimals,
+                stability_fee: Decimal::zero(),
+            },
+        ),
 
         ExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id,
+            collateral_price_in_dirham,
+        } => execute_set_collateral_price_in_dirham(
+            deps,
+            env,
+            info,
+            collateral_id,
             collateral_price_in_dirham,
-        } => execute_set_collateral_price_in_dirham(deps, info, collateral_price_in_dirham),
+        ),
+
+        ExecuteMsg::SetPriceGuardConfig {
+            max_price_deviation,
+            deviation_window_seconds,
+            price_delay_seconds,
+        } => execute_set_price_guard_config(
+            deps,
+            info,
+            PriceGuardConfig {
+                max_price_deviation,
+                deviation_window_seconds,
+                price_delay_seconds,
+            },
+        ),
+
+        ExecuteMsg::SetCollateralPriceSource {
+            collateral_id,
+            price_source,
+        } => execute_set_collateral_price_source(deps, env, info, collateral_id, price_source),
+
+        ExecuteMsg::RecordPriceObservation { collateral_id } => {
+            execute_record_price_observation(deps, env, collateral_id)
+        }
+
+        ExecuteMsg::SubmitPrice {
+            collateral_id,
+            price,
+        } => execute_submit_price(deps, env, info, collateral_id, price),
 
-        ExecuteMsg::SetLiquidationHealth { liquidation_health } => {
-            execute_set_liquidation_health(deps, info, liquidation_health)
+        ExecuteMsg::AddPriceFeeder { feeder_address } => {
+            execute_add_price_feeder(deps, info, feeder_address)
         }
 
-        ExecuteMsg::SetMintableHealth { mintable_health } => {
-            execute_set_mintable_health(deps, info, mintable_health)
+        ExecuteMsg::RemovePriceFeeder { feeder_address } => {
+            execute_remove_price_feeder(deps, info, feeder_address)
         }
 
+        ExecuteMsg::SetPriceFeedConfig {
+            quorum,
+            max_price_age_seconds,
+        } => execute_set_price_feed_config(
+            deps,
+            info,
+            PriceFeedConfig {
+                quorum,
+                max_price_age_seconds,
+            },
+        ),
+
+        ExecuteMsg::SetLiquidationHealth {
+            collateral_id,
+            liquidation_health,
+        } => execute_set_liquidation_health(deps, env, info, collateral_id, liquidation_health),
+
+        ExecuteMsg::SetMintableHealth {
+            collateral_id,
+            mintable_health,
+        } => execute_set_mintable_health(deps, env, info, collateral_id, mintable_health),
+
+        ExecuteMsg::SetTimelockDelay {
+            timelock_delay_seconds,
+        } => execute_set_timelock_delay(deps, env, info, timelock_delay_seconds),
+
+        ExecuteMsg::ExecuteQueued { id } => execute_execute_queued(deps, env, info, id),
+
+        ExecuteMsg::CancelQueued { id } => execute_cancel_queued(deps, info, id),
+
+        ExecuteMsg::Pause {
+            minting,
+            unlocking,
+            liquidations,
+            price_updates,
+            duration_seconds,
+        } => execute_pause(
+            deps,
+            env,
+            info,
+            PauseFlags {
+                minting,
+                unlocking,
+                liquidations,
+                price_updates,
+            },
+            duration_seconds,
+        ),
+
+        ExecuteMsg::Unpause {} => execute_unpause(deps, env, info),
+
+        ExecuteMsg::Shutdown {} => execute_shutdown(deps, env, info),
+
+        ExecuteMsg::SettlePosition {
+            wallet_address,
+            collateral_id,
+        } => execute_settle_position(deps, env, info, wallet_address, collateral_id),
+
+        ExecuteMsg::SetDebtCeiling {
+            collateral_id,
+            debt_ceiling,
+        } => execute_set_debt_ceiling(deps, info, collateral_id, debt_ceiling),
+
+        ExecuteMsg::SetStabilityFee {
+            collateral_id,
+            stability_fee,
+        } => execute_set_stability_fee(deps, env, info, collateral_id, stability_fee),
+
+        ExecuteMsg::SetCloseFactor { close_factor } => {
+            execute_set_close_factor(deps, info, close_factor)
+        }
+
+        ExecuteMsg::SetLiquidationBonus { liquidation_bonus } => {
+            execute_set_liquidation_bonus(deps, info, liquidation_bonus)
+        }
+
+        ExecuteMsg::SetLiquidationProtocolShare {
+            liquidation_protocol_share,
+        } => execute_set_liquidation_protocol_share(deps, info, liquidation_protocol_share),
+
+        ExecuteMsg::SetAuctionConfig {
+            starting_premium,
+            price_decay_seconds,
+            reset_after_seconds,
+        } => execute_set_auction_config(
+            deps,
+            info,
+            AuctionConfig {
+                starting_premium,
+                price_decay_seconds,
+                reset_after_seconds,
+            },
+        ),
+
         ExecuteMsg::SetCW20DiraContractAddress {
             cw20_dira_contract_address,
-        } => execute_set_cw20_dira_contact_address(deps, cw20_dira_contract_address,info),
+        } => execute_set_cw20_dira_contact_address(deps, env, cw20_dira_contract_address,info),
 
         ExecuteMsg::EnableFeeSwitch {}   => execute_enable_fee_switch_state(deps, info),
 
         ExecuteMsg::DisableFeeSwitch {} => execute_disable_fee_switch_state(deps, info),
+
+        ExecuteMsg::UpdateConfig(config_update) => {
+            execute_update_config(deps, env, info, config_update)
+        }
+
+        ExecuteMsg::WithdrawFromStabilityPool { dira_to_withdraw } => {
+            execute_withdraw_from_stability_pool(deps, info, dira_to_withdraw)
+        }
+
+        ExecuteMsg::ClaimStabilityPoolGains {} => execute_claim_stability_pool_gains(deps, info),
+
+        ExecuteMsg::Drip {} => execute_drip(deps, env),
+
+        ExecuteMsg::WithdrawSavings { dira_to_withdraw } => {
+            execute_withdraw_savings(deps, env, info, dira_to_withdraw)
+        }
+
+        ExecuteMsg::SetSavingsRate { savings_rate } => {
+            execute_set_savings_rate(deps, env, info, savings_rate)
+        }
+
+        ExecuteMsg::SwapStablecoinForDira { asset_id } => {
+            let psm_asset = helper_load_psm_asset(deps.storage, &asset_id)?;
+
+            if psm_asset.asset_type != CollateralAssetType::Native {
+                return Err(ContractError::CollateralAssetTypeMismatch {
+                    collateral_id: asset_id,
+                });
+            }
+
+            let sent_funds = info
+                .funds
+                .iter()
+                .find(|coin| coin.denom == asset_id && !coin.amount.is_zero())
+                .ok_or(ContractError::InsufficientFundsSent {})?;
+
+            let stablecoin_amount = helper_amount_to_decimal(sent_funds.amount, psm_asset.decimals)?;
+
+            execute_swap_stablecoin_for_dira(deps, env, info.sender, asset_id, psm_asset, stablecoin_amount)
+        }
+
+        ExecuteMsg::RegisterPsmAsset {
+            asset_id,
+            asset_type,
+            decimals,
+            price_in_dirham,
+            fee_in,
+            fee_out,
+            reserve_cap,
+        } => execute_register_psm_asset(
+            deps,
+            env,
+            info,
+            asset_id,
+            PsmAsset {
+                asset_type,
+                decimals,
+                price_in_dirham,
+                fee_in,
+                fee_out,
+                reserve_cap,
+            },
+        ),
+
+        ExecuteMsg::SetPsmAssetPrice {
+            asset_id,
+            price_in_dirham,
+        } => execute_set_psm_asset_price(deps, env, info, asset_id, price_in_dirham),
+
+        ExecuteMsg::SetPsmFees {
+            asset_id,
+            fee_in,
+            fee_out,
+        } => execute_set_psm_fees(deps, info, asset_id, fee_in, fee_out),
+
+        ExecuteMsg::SetPsmReserveCap {
+            asset_id,
+            reserve_cap,
+        } => execute_set_psm_reserve_cap(deps, info, asset_id, reserve_cap),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::QueryLockedCollateral {
             wallet_address_to_query,
-        } => query_locked_collateral(deps, wallet_address_to_query),
+            collateral_id,
+        } => query_locked_collateral(deps, wallet_address_to_query, collateral_id),
         QueryMsg::QueryMintedDira {
             wallet_address_to_query,
-        } => query_minted_dira(deps, wallet_address_to_query),
+            collateral_id,
+        } => query_minted_dira(deps, env, wallet_address_to_query, collateral_id),
         QueryMsg::QueryStablecoinHealth {
             stablecoin_minter_address_to_query,
-        } => query_stablecoin_health(deps, stablecoin_minter_address_to_query),
-        QueryMsg::QueryCollateralPrice {} => query_collateral_price(deps),
-        QueryMsg::QueryLiquidationHealth {} => query_liquidation_health(deps),
-        QueryMsg::QueryMintableHealth {} => query_mintable_health(deps),
+            collateral_id,
+        } => query_stablecoin_health(
+            deps,
+            env,
+            stablecoin_minter_address_to_query,
+            collateral_id,
+        ),
+        QueryMsg::QueryCollateralPrice { collateral_id } => {
+            query_collateral_price(deps, env, collateral_id)
+        }
+        QueryMsg::QueryNextCollateralPrice { collateral_id } => {
+            query_next_collateral_price(deps, env, collateral_id)
+        }
+        QueryMsg::QueryPriceGuardConfig {} => query_price_guard_config(deps),
+        QueryMsg::QueryPriceHistory {
+            collateral_id,
+            start_after,
+            limit,
+        } => query_price_history(deps, env, collateral_id, start_after, limit),
+        QueryMsg::QueryPriceAt {
+            collateral_id,
+            time,
+        } => query_price_at(deps, env, collateral_id, time),
+        QueryMsg::QueryLiquidationHealth { collateral_id } => {
+            query_liquidation_health(deps, collateral_id)
+        }
+        QueryMsg::QueryMintableHealth { collateral_id } => {
+            query_mintable_health(deps, collateral_id)
+        }
+        QueryMsg::QueryCloseFactor {} => query_close_factor(deps),
+        QueryMsg::QueryLiquidationBonus {} => query_liquidation_bonus(deps),
+        QueryMsg::QueryLiquidationProtocolShare {} => query_liquidation_protocol_share(deps),
         QueryMsg::QueryAdminAddresses {} => query_admin_addresses(deps),
-        QueryMsg::QueryCollateralTokenDenom {} => query_collateral_token_denom(deps),
+        QueryMsg::QueryOwnership {} => query_ownership(deps, env),
+        QueryMsg::QueryRoles { address } => query_roles(deps, address),
+        QueryMsg::QueryPriceFeeders {} => query_price_feeders(deps),
+        QueryMsg::QueryFeederPrices { collateral_id } => {
+            query_feeder_prices(deps, env, collateral_id)
+        }
+        QueryMsg::QueryCollateralInfo { collateral_id } => {
+            query_collateral_info(deps, env, collateral_id)
+        }
+        QueryMsg::QueryCollaterals {} => query_collaterals(deps, env),
+        QueryMsg::QueryConfig {} => query_config(deps, env),
         QueryMsg::QueryCW20DiraContractAddress {} => query_cw20_dira_contract_address(deps),
-        QueryMsg::QueryGetFeeConfig {} => query_fee_config_state(deps)
+        QueryMsg::QueryGetFeeConfig {} => query_fee_config_state(deps),
+        QueryMsg::QueryStabilityPool {} => query_stability_pool(deps),
+        QueryMsg::QueryStabilityPoolDeposit { depositor } => {
+            query_stability_pool_deposit(deps, depositor)
+        }
+        QueryMsg::QueryStabilityPoolGains { depositor } => {
+            query_stability_pool_gains(deps, depositor)
+        }
+        QueryMsg::QueryStabilityFee { collateral_id } => {
+            query_stability_fee(deps, env, collateral_id)
+        }
+        QueryMsg::QueryBadDebt {} => query_bad_debt(deps),
+        QueryMsg::QuerySavingsVault {} => query_savings_vault(deps, env),
+        QueryMsg::QuerySavings { depositor } => query_savings(deps, env, depositor),
+        QueryMsg::QueryPsmAsset { asset_id } => query_psm_asset(deps, asset_id),
+        QueryMsg::QueryPsmAssets {} => query_psm_assets(deps),
+        QueryMsg::QuerySimulateSwapStablecoinForDira {
+            asset_id,
+            stablecoin_amount,
+        } => query_simulate_swap_stablecoin_for_dira(deps, asset_id, stablecoin_amount),
+        QueryMsg::QuerySimulateSwapDiraForStablecoin {
+            asset_id,
+            dira_amount,
+        } => query_simulate_swap_dira_for_stablecoin(deps, asset_id, dira_amount),
+        QueryMsg::QueryRedemptionFee {} => query_redemption_fee(deps, env),
+        QueryMsg::QueryRedemptionQueue {
+            collateral_id,
+            limit,
+        } => query_redemption_queue(deps, env, collateral_id, limit),
+        QueryMsg::QueryAllPositions {
+            start_after,
+            limit,
+            below_health,
+        } => query_all_positions(deps, env, start_after, limit, below_health),
+        QueryMsg::QueryAuctionConfig {} => query_auction_config(deps),
+        QueryMsg::QueryAuction { auction_id } => query_auction(deps, env, auction_id),
+        QueryMsg::QueryActiveAuctions {} => query_active_auctions(deps, env),
+        QueryMsg::QueryQueuedChange { id } => query_queued_change(deps, id),
+        QueryMsg::QueryQueuedChanges {} => query_queued_changes(deps),
+        QueryMsg::QueryPauseState {} => query_pause_state(deps, env),
+        QueryMsg::QueryShutdown {} => query_shutdown(deps),
     }
 }
 
@@ -169,7 +688,7 @@ fn helper_calculate_stablecoin_health(
         // }
     }
 
-    return locked_collateral_value_in_dirham / minted_dira;
+    locked_collateral_value_in_dirham / minted_dira
 }
 
 // Function to calculate how much Dira the user can mint
@@ -181,9 +700,7 @@ fn helper_calculate_max_mintable_dira(
     collateral_price_in_dirham: Decimal,
     mintable_health: Decimal,
 ) -> Decimal {
-    let max_mintable_dira = (locked_collateral * collateral_price_in_dirham) / mintable_health;
-
-    max_mintable_dira
+    (locked_collateral * collateral_price_in_dirham) / mintable_health
 }
 
 // Function to calculate how much collateral can be unlocked
@@ -197,9 +714,7 @@ fn helper_calculate_max_unlockable_collateral(
 ) -> Decimal {
     let required_collateral_for_minted_dira =
         (minted_dira * mintable_health) / collateral_price_in_dirham;
-    let unlockable_collateral = locked_collateral - required_collateral_for_minted_dira;
-
-    unlockable_collateral
+    locked_collateral - required_collateral_for_minted_dira
 }
 
 fn helper_is_cw20_contract(deps: Deps, contract_addr: &Addr) -> bool {
@@ -217,8 +732,9 @@ fn helper_is_cw20_contract(deps: Deps, contract_addr: &Addr) -> bool {
 
 // This function helps to calculate the tier amount that should go to treasury
 fn helper_calculate_fee_tier_amount(amount: Decimal, fee_config: &FeeConfig) -> Result<Decimal, ContractError> {
-    if !fee_config.enabled{
-        return Err(ContractError::FeeSwitchDisabled {})
+    // No fee is charged while the fee switch is disabled
+    if !fee_config.enabled {
+        return Ok(Decimal::zero());
     }
 
     let tier = FeeTier::from_amount(amount);
@@ -229,190 +745,957 @@ fn helper_calculate_fee_tier_amount(amount: Decimal, fee_config: &FeeConfig) ->
 
 }
 
-// Function to lock collateral
-fn execute_lock_collateral(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
-    let collateral_token_denom = COLLATERAL_TOKEN_DENOM
-        .load(deps.storage)
-        .map_err(|_| ContractError::MissingCollateralTokenDenom {})?;
+// Function to load a collateral type from the registry, failing if it was never registered
+fn helper_load_collateral_info(
+    storage: &dyn Storage,
+    collateral_id: &str,
+) -> Result<CollateralInfo, ContractError> {
+    COLLATERALS
+        .may_load(storage, collateral_id)?
+        .ok_or(ContractError::UnknownCollateral {
+            collateral_id: collateral_id.to_string(),
+        })
+}
 
-    let message_sender = info.sender;
+// Function to get the dirham price of a collateral type from its price source, failing if
+// the admin never set it, the oracle does not return one or too few feeders are fresh
+fn helper_collateral_price(
+    deps: Deps,
+    now: Timestamp,
+    collateral_id: &str,
+    collateral_info: &CollateralInfo,
+) -> Result<Decimal, ContractError> {
+    // Prices are frozen once the contract is shut down. Collateral types that were never priced
+    // are frozen at zero
+    if let Some(shutdown_collateral) = SHUTDOWN_COLLATERALS.may_load(deps.storage, collateral_id)? {
+        if shutdown_collateral.price.is_zero() {
+            return Err(ContractError::CollateralPriceNotSet {});
+        }
+        return Ok(shutdown_collateral.price);
+    }
 
-    // Check if the user has sent enough funds along with the transaction
-    let sent_funds = info
-        .funds
-        .iter()
-        .find(|coin| coin.denom == collateral_token_denom)
-        .ok_or(ContractError::InsufficientFundsSent {})
-        .unwrap();
+    match &collateral_info.price_source {
+        PriceSource::Manual => {
+            helper_manual_collateral_price(deps.storage, now, collateral_id, collateral_info)?
+                .ok_or(ContractError::CollateralPriceNotSet {})
+        }
 
-    let sent_amount = Decimal::from_atomics(sent_funds.amount, 6).unwrap();
+        PriceSource::Oracle { oracle_address } => {
+            let oracle_price: OraclePriceResponse = deps
+                .querier
+                .query_wasm_smart(
+                    oracle_address,
+                    &OracleQueryMsg::Price {
+                        denom: collateral_id.to_string(),
+                    },
+                )
+                .map_err(|_| ContractError::OraclePriceUnavailable {
+                    collateral_id: collateral_id.to_string(),
+                })?;
+
+            if oracle_price.price.is_zero() {
+                return Err(ContractError::OraclePriceUnavailable {
+                    collateral_id: collateral_id.to_string(),
+                });
+            }
 
-    match LOCKED_COLLATERAL.update(
-        deps.storage,
-        message_sender.clone(),
-        |balance: Option<Decimal>| -> Result<Decimal, ContractError> {
-            Ok(balance.unwrap_or_default() + sent_amount)
-        },
-    ) {
-        Ok(_result) => {}
-        Err(error) => {
-            dbg!("Error in updating LOCKED_COLLATERAL storage item");
-            return Err(error);
+            Ok(oracle_price.price)
         }
+
+        PriceSource::Feeders => helper_median_feeder_price(deps.storage, now, collateral_id),
+
+        PriceSource::Twap {
+            pair_address,
+            window_seconds,
+        } => helper_twap_price(
+            deps,
+            now,
+            collateral_id,
+            collateral_info,
+            pair_address,
+            *window_seconds,
+        ),
+    }
+}
+
+// Function to query the cumulative price of a collateral type from a DEX pair
+fn helper_query_cumulative_price(
+    deps: Deps,
+    collateral_id: &str,
+    collateral_info: &CollateralInfo,
+    pair_address: &Addr,
+) -> Result<Uint128, ContractError> {
+    let collateral_asset_info = match collateral_info.asset_type {
+        CollateralAssetType::Native => AssetInfo::NativeToken {
+            denom: collateral_id.to_string(),
+        },
+        CollateralAssetType::Cw20 => AssetInfo::Token {
+            contract_addr: Addr::unchecked(collateral_id),
+        },
     };
 
-    // Send the lock collateral messages and return the Ok response
-    Ok(Response::new()
-        .add_attribute("action", "lock_collateral")
-        .add_attribute("sender", message_sender.clone())
-        .add_attribute(
-            "total_funds_locked_by_user",
-            LOCKED_COLLATERAL
-                .load(deps.storage, message_sender)
-                .unwrap_or_default()
-                .to_string(),
-        ))
+    let cumulative_prices: CumulativePricesResponse = deps
+        .querier
+        .query_wasm_smart(pair_address, &PairQueryMsg::CumulativePrices {})
+        .map_err(|_| ContractError::OraclePriceUnavailable {
+            collateral_id: collateral_id.to_string(),
+        })?;
+
+    cumulative_prices
+        .cumulative_prices
+        .into_iter()
+        .find(|(asset_info, _, _)| *asset_info == collateral_asset_info)
+        .map(|(_, _, cumulative_price)| cumulative_price)
+        .ok_or_else(|| ContractError::OraclePriceUnavailable {
+            collateral_id: collateral_id.to_string(),
+        })
 }
 
-// Function to unlock collateral
-fn execute_unlock_collateral(
-    deps: DepsMut,
-    info: MessageInfo,
-    collateral_amount: Decimal,
-) -> Result<Response, ContractError> {
-    let collateral_token_denom = COLLATERAL_TOKEN_DENOM
-        .load(deps.storage)
-        .map_err(|_| ContractError::MissingCollateralTokenDenom {})?;
+// Function to compute the time weighted average price of a collateral type from the newest
+// observation that is at least a window old. Observations older than two windows are too
+// stale to be used
+fn helper_twap_price(
+    deps: Deps,
+    now: Timestamp,
+    collateral_id: &str,
+    collateral_info: &CollateralInfo,
+    pair_address: &Addr,
+    window_seconds: u64,
+) -> Result<Decimal, ContractError> {
+    let (observed_at, observed_cumulative_price) = TWAP_OBSERVATIONS
+        .prefix(collateral_id)
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive(now.seconds().saturating_sub(window_seconds))),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?
+        .filter(|(observed_at, _)| {
+            now.seconds().saturating_sub(*observed_at) <= window_seconds.saturating_mul(2)
+        })
+        .ok_or_else(|| ContractError::TwapUnavailable {
+            collateral_id: collateral_id.to_string(),
+        })?;
+
+    let cumulative_price =
+        helper_query_cumulative_price(deps, collateral_id, collateral_info, pair_address)?;
+    let elapsed_seconds = now.seconds() - observed_at;
+
+    // Cumulative prices are allowed to wrap around, like the pairs reporting them
+    let twap = cumulative_price.wrapping_sub(observed_cumulative_price)
+        / Uint128::from(elapsed_seconds);
+
+    if twap.is_zero() {
+        return Err(ContractError::OraclePriceUnavailable {
+            collateral_id: collateral_id.to_string(),
+        });
+    }
 
-    let message_sender = info.sender;
+    helper_amount_to_decimal(twap, TWAP_PRECISION)
+}
 
-    let locked_collateral = LOCKED_COLLATERAL
-        .load(deps.storage, message_sender.clone())
-        .unwrap_or_default();
+// Function to get the admin set price of a collateral type in use at the given time, which is
+// the next price once it has waited out the price delay
+fn helper_manual_collateral_price(
+    storage: &dyn Storage,
+    now: Timestamp,
+    collateral_id: &str,
+    collateral_info: &CollateralInfo,
+) -> Result<Option<Decimal>, ContractError> {
+    match NEXT_COLLATERAL_PRICES.may_load(storage, collateral_id)? {
+        Some(next_price) if next_price.effective_at <= now => Ok(Some(next_price.price)),
+        _ => Ok(collateral_info.collateral_price_in_dirham),
+    }
+}
 
-    let minted_dira = MINTED_DIRA
-        .load(deps.storage, message_sender.clone())
+// Function to add a collateral price to its price history, dropping the oldest entry once the
+// history is full. Prices recorded within the same second replace each other
+fn helper_record_collateral_price(
+    storage: &mut dyn Storage,
+    collateral_id: &str,
+    time: Timestamp,
+    price: Decimal,
+) -> StdResult<()> {
+    let mut history_length = PRICE_HISTORY_LENGTHS
+        .may_load(storage, collateral_id)?
         .unwrap_or_default();
 
-    let mintable_health = MINTABLE_HEALTH.load(deps.storage)?;
+    if !PRICE_HISTORY.has(storage, (collateral_id, time.seconds())) {
+        history_length += 1;
+    }
 
-    let collateral_price_in_dirham = COLLATERAL_TOKEN_PRICE
-        .may_load(deps.storage)?
-        .ok_or(ContractError::CollateralPriceNotSet {})
-        .unwrap();
+    PRICE_HISTORY.save(storage, (collateral_id, time.seconds()), &price)?;
 
-    let max_unlockable_collateral = helper_calculate_max_unlockable_collateral(
-        locked_collateral,
-        collateral_price_in_dirham,
-        minted_dira,
-        mintable_health,
-    );
+    if history_length > MAX_PRICE_HISTORY_ENTRIES {
+        let oldest_entry = PRICE_HISTORY
+            .prefix(collateral_id)
+            .keys(storage, None, None, Order::Ascending)
+            .next()
+            .transpose()?;
 
-    if collateral_amount > max_unlockable_collateral {
-        return Err(ContractError::UnlockAmountTooHigh {
-            max_unlockable: max_unlockable_collateral,
+        if let Some(oldest_entry) = oldest_entry {
+            PRICE_HISTORY.remove(storage, (collateral_id, oldest_entry));
+            history_length -= 1;
+        }
+    }
+
+    PRICE_HISTORY_LENGTHS.save(storage, collateral_id, &history_length)
+}
+
+// Function to drop a price from the price history, for next prices replaced before they took effect
+fn helper_forget_collateral_price(
+    storage: &mut dyn Storage,
+    collateral_id: &str,
+    time: Timestamp,
+) -> StdResult<()> {
+    if PRICE_HISTORY.has(storage, (collateral_id, time.seconds())) {
+        PRICE_HISTORY.remove(storage, (collateral_id, time.seconds()));
+        PRICE_HISTORY_LENGTHS.update(storage, collateral_id, |history_length| -> StdResult<u32> {
+            Ok(history_length.unwrap_or_default().saturating_sub(1))
+        })?;
+    }
+
+    Ok(())
+}
+
+// Function to compute the median of the fresh prices submitted by the current price feeders,
+// failing with StalePrice when fewer of them than the quorum are fresh
+fn helper_median_feeder_price(
+    storage: &dyn Storage,
+    now: Timestamp,
+    collateral_id: &str,
+) -> Result<Decimal, ContractError> {
+    let price_feed_config = CONFIG.load(storage)?.price_feed_config;
+    let price_feeders = PRICE_FEEDERS.load(storage)?;
+
+    let mut fresh_prices = vec![];
+
+    for feeder_address in price_feeders.iter() {
+        if let Some(feeder_price) = FEEDER_PRICES.may_load(storage, (collateral_id, feeder_address))? {
+            if !helper_is_feeder_price_stale(&feeder_price, &price_feed_config, now) {
+                fresh_prices.push(feeder_price.price);
+            }
+        }
+    }
+
+    if fresh_prices.is_empty() || fresh_prices.len() < price_feed_config.quorum as usize {
+        return Err(ContractError::StalePrice {
+            collateral_id: collateral_id.to_string(),
+            quorum: price_feed_config.quorum,
         });
     }
 
-    LOCKED_COLLATERAL.save(
+    fresh_prices.sort();
+
+    let middle = fresh_prices.len() / 2;
+
+    if fresh_prices.len() % 2 == 0 {
+        Ok((fresh_prices[middle - 1] + fresh_prices[middle]) / Decimal::from_ratio(2u128, 1u128))
+    } else {
+        Ok(fresh_prices[middle])
+    }
+}
+
+// Function to check whether a feeder price is too old to be used
+fn helper_is_feeder_price_stale(
+    feeder_price: &FeederPrice,
+    price_feed_config: &PriceFeedConfig,
+    now: Timestamp,
+) -> bool {
+    now.seconds().saturating_sub(feeder_price.submitted_at.seconds())
+        > price_feed_config.max_price_age_seconds
+}
+
+// Function to check the risk parameters of a collateral type before saving them
+fn helper_validate_collateral_info(collateral_info: &CollateralInfo) -> Result<(), ContractError> {
+    if collateral_info.liquidation_health.is_zero() || collateral_info.mintable_health.is_zero() {
+        return Err(ContractError::HealthCannotBeZero {});
+    }
+
+    if collateral_info.mintable_health < collateral_info.liquidation_health {
+        return Err(ContractError::MintableHealthLowerThanLiquidationHealth {});
+    }
+
+    if collateral_info.decimals > Decimal::DECIMAL_PLACES {
+        return Err(ContractError::InvalidCollateralDecimals {});
+    }
+
+    Ok(())
+}
+
+// Function to convert an on chain token amount into a Decimal, based on
+// how many decimals the token uses
+fn helper_amount_to_decimal(amount: Uint128, decimals: u32) -> Result<Decimal, ContractError> {
+    Decimal::from_atomics(amount, decimals)
+        .map_err(|error| ContractError::Std(StdError::generic_err(error.to_string())))
+}
+
+// Function to convert a Decimal back into an on chain token amount, based on
+// how many decimals the token uses
+fn helper_decimal_to_amount(value: Decimal, decimals: u32) -> Uint128 {
+    value.atomics() / Uint128::from(10u128.pow(Decimal::DECIMAL_PLACES - decimals))
+}
+
+// Function to load the CW20 Dira contract address, failing if it was never set
+fn helper_load_cw20_dira_contract_address(storage: &dyn Storage) -> Result<Addr, ContractError> {
+    CONFIG
+        .load(storage)?
+        .cw20_dira_contract_address
+        .ok_or(ContractError::CW20DiraContractAddressNotSet {})
+}
+
+// Function to add collateral to a wallet's locked balance once the tokens
+// have been received by the contract
+fn helper_lock_collateral(
+    deps: DepsMut,
+    wallet_address: Addr,
+    collateral_id: String,
+    collateral_amount: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_not_shut_down(deps.storage)?;
+
+    let total_funds_locked_by_user = LOCKED_COLLATERAL.update(
         deps.storage,
-        message_sender.clone(),
-        &(locked_collateral - collateral_amount),
+        (&wallet_address, &collateral_id),
+        |balance: Option<Decimal>| -> Result<Decimal, ContractError> {
+            Ok(balance.unwrap_or_default() + collateral_amount)
+        },
     )?;
 
-    let return_collateral_to_user_message = BankMsg::Send {
-        to_address: message_sender.to_string(),
-        amount: vec![Coin {
-            denom: collateral_token_denom,
-            amount: collateral_amount.atomics() / Uint128::from(u128::pow(10, 12)),
-        }],
-    };
+    helper_update_redemption_queue(deps.storage, &wallet_address, &collateral_id)?;
 
+    // Send the lock collateral messages and return the Ok response
     Ok(Response::new()
-        .add_message(return_collateral_to_user_message)
-        .add_attribute("action", "unlock_collateral")
-        .add_attribute("sender", message_sender.clone())
+        .add_attribute("action", "lock_collateral")
+        .add_attribute("sender", wallet_address)
+        .add_attribute("collateral_id", collateral_id)
         .add_attribute(
             "total_funds_locked_by_user",
-            LOCKED_COLLATERAL
-                .load(deps.storage, message_sender)
-                .unwrap_or_default()
-                .to_string(),
+            total_funds_locked_by_user.to_string(),
         ))
 }
 
-// Function to mint dira
-fn execute_mint_dira(
-    deps: DepsMut,
-    info: MessageInfo,
-    dira_to_mint: Decimal,
-) -> Result<Response, ContractError> {
-    // First calculate how much dira this user can mint based on current collateral price
-    // and how much collateral they have locked
+// Function to build the message that sends collateral out of the contract,
+// through the bank module or the CW20 contract depending on the collateral type
+fn helper_send_collateral_message(
+    collateral_id: &str,
+    collateral_info: &CollateralInfo,
+    recipient: &Addr,
+    collateral_amount: Decimal,
+) -> Result<CosmosMsg, ContractError> {
+    helper_send_asset_message(
+        collateral_id,
+        &collateral_info.asset_type,
+        collateral_info.decimals,
+        recipient,
+        collateral_amount,
+    )
+}
 
-    // To do this, first load all the variables from the blockchain
-    let collateral_locked_by_user =
-        match LOCKED_COLLATERAL.may_load(deps.storage, info.sender.clone()) {
-            Ok(Some(locked_collateral)) => locked_collateral,
-            _ => return Err(ContractError::InsufficientCollateral {}),
-        };
+// Function to build the message sending a native or CW20 token out of the contract
+fn helper_send_asset_message(
+    asset_id: &str,
+    asset_type: &CollateralAssetType,
+    decimals: u32,
+    recipient: &Addr,
+    asset_amount: Decimal,
+) -> Result<CosmosMsg, ContractError> {
+    let amount = helper_decimal_to_amount(asset_amount, decimals);
+
+    match asset_type {
+        CollateralAssetType::Native => Ok(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom: asset_id.to_string(),
+                amount,
+            }],
+        }
+        .into()),
+        CollateralAssetType::Cw20 => Ok(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: asset_id.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into()),
+    }
+}
 
-    let previously_minted_dira = match MINTED_DIRA.may_load(deps.storage, info.sender.clone()) {
-        Ok(Some(minted_dira)) => minted_dira,
-        _ => Decimal::zero(),
-    };
+// Function to load the treasury address that protocol fees are routed to,
+// which is the first admin address
+fn helper_load_treasury_address(storage: &dyn Storage) -> Result<Addr, ContractError> {
+    let admins = ADMIN_ADDRESSES.load(storage)?;
 
-    let collateral_price_in_dirham = match COLLATERAL_TOKEN_PRICE.may_load(deps.storage) {
-        Ok(Some(collateral_price)) => collateral_price,
-        _ => return Err(ContractError::CollateralPriceNotSet {}),
-    };
+    admins
+        .first()
+        .cloned()
+        .ok_or(ContractError::NoAdminAddressesSet {})
+}
 
-    let mintable_health = MINTABLE_HEALTH.load(deps.storage).unwrap();
+// Function to fail once the contract has been shut down
+fn helper_check_not_shut_down(storage: &dyn Storage) -> Result<(), ContractError> {
+    if SHUTDOWN.may_load(storage)?.is_some() {
+        return Err(ContractError::ShutDown {});
+    }
 
-    // Finally use the helper function to calculate max mintable dira by this user
-    let max_mintable_dira = helper_calculate_max_mintable_dira(
-        collateral_locked_by_user,
-        collateral_price_in_dirham,
-        mintable_health,
-    );
+    Ok(())
+}
 
-    if dira_to_mint + previously_minted_dira > max_mintable_dira {
-        return Err(ContractError::InsufficientCollateral {});
+// Function to get the operations that are paused at the given time
+fn helper_active_pause_flags(storage: &dyn Storage, now: Timestamp) -> StdResult<PauseFlags> {
+    Ok(PAUSE_STATE
+        .may_load(storage)?
+        .filter(|pause_state| now < pause_state.paused_until)
+        .map(|pause_state| pause_state.flags)
+        .unwrap_or_default())
+}
+
+// Function to check that an address was granted a role
+fn helper_check_role(storage: &dyn Storage, address: &Addr, role: Role) -> Result<(), ContractError> {
+    if !ROLES.has(storage, (role.as_str(), address)) {
+        return Err(ContractError::MissingRole {
+            role: role.as_str().to_string(),
+        });
     }
 
-    //Implementation of fee switch mechanism
-    let fee_config = FEE_SWITCH.load(deps.storage)?;
-    let fee_amount = helper_calculate_fee_tier_amount(dira_to_mint, &fee_config)?;
+    Ok(())
+}
 
-    let dira_to_mint_after_fee_deduction = dira_to_mint - fee_amount ;
+// Function to compute the stability fee rate of a collateral type at the given time, without saving it
+fn helper_stability_fee_rate(
+    storage: &dyn Storage,
+    collateral_id: &str,
+    now: Timestamp,
+) -> Result<StabilityFeeRate, ContractError> {
+    let collateral_info = helper_load_collateral_info(storage, collateral_id)?;
+
+    // Stability fees stop accruing once the contract is shut down
+    let now = match SHUTDOWN.may_load(storage)? {
+        Some(shutdown) => now.min(shutdown.shutdown_at),
+        None => now,
+    };
 
+    let stability_fee_rate = STABILITY_FEE_RATES
+        .may_load(storage, collateral_id)?
+        .unwrap_or(StabilityFeeRate {
+            rate: Decimal::one(),
+            last_updated: now,
+        });
 
-    // Else, mint dira and transfer it to user, add that message to the response
-    MINTED_DIRA.save(
-        deps.storage,
-        info.sender.clone(),
-        &(dira_to_mint_after_fee_deduction + previously_minted_dira),
-    )?;
+    let elapsed_seconds = now
+        .seconds()
+        .saturating_sub(stability_fee_rate.last_updated.seconds());
 
+    // The fee compounds every time the rate is brought up to date
+    let rate = stability_fee_rate.rate
+        * (Decimal::one()
+            + collateral_info.stability_fee
+                * Decimal::from_ratio(elapsed_seconds, SECONDS_PER_YEAR));
 
-    // Get the CW20 contract address
-    let cw20_dira_contract_address = match CW20_DIRA_CONTRACT_ADDRESS.may_load(deps.storage) {
-        Ok(Some(contract_address)) => contract_address,
-        _ => return Err(ContractError::CW20DiraContractAddressNotSet {}),
-    };
+    Ok(StabilityFeeRate {
+        rate,
+        last_updated: now,
+    })
+}
 
+// Function to compute the stability fees a collateral type's debt has accrued since its rate
+// was last updated, along with the up to date rate, without saving either
+fn helper_stability_fees_accrued(
+    storage: &dyn Storage,
+    collateral_id: &str,
+    now: Timestamp,
+) -> Result<(StabilityFeeRate, Decimal), ContractError> {
+    let previous_rate = STABILITY_FEE_RATES
+        .may_load(storage, collateral_id)?
+        .map_or(Decimal::one(), |stability_fee_rate| stability_fee_rate.rate);
+
+    let stability_fee_rate = helper_stability_fee_rate(storage, collateral_id, now)?;
+
+    let total_normalized_debt = TOTAL_MINTED_DIRA
+        .may_load(storage, collateral_id)?
+        .unwrap_or_default();
+    let accrued_stability_fees = total_normalized_debt * (stability_fee_rate.rate - previous_rate);
 
-    //Admin address that routes to treasury
-    let admins = ADMIN_ADDRESSES.load(deps.storage)?;
-    let treasury_address =
-             admins.first()
-            .ok_or(ContractError::NoAdminAddressesSet {})?;
+    Ok((stability_fee_rate, accrued_stability_fees))
+}
 
+// Function to compute the stability fees accrued across all collateral types that have not
+// been minted yet, as they would be if every rate was brought up to date now
+fn helper_unminted_stability_fees(
+    storage: &dyn Storage,
+    now: Timestamp,
+) -> Result<Decimal, ContractError> {
+    let mut unminted_stability_fees = UNMINTED_STABILITY_FEES
+        .may_load(storage)?
+        .unwrap_or_default();
 
-    // Mint CW20 tokens , to treasury according to fee tiers
-    let mint_msg_for_treasury = cw20::Cw20ExecuteMsg::Mint {
+    for collateral_id in COLLATERALS.keys(storage, None, None, Order::Ascending) {
+        let (_, accrued_stability_fees) =
+            helper_stability_fees_accrued(storage, &collateral_id?, now)?;
+        unminted_stability_fees += accrued_stability_fees;
+    }
+
+    Ok(unminted_stability_fees)
+}
+
+// Function to bring the stability fee rate of a collateral type up to date before its debt
+// changes. The fees this adds to the collateral's debt are set aside to be minted by Drip.
+// Returns the updated rate
+fn helper_accrue_stability_fee(
+    storage: &mut dyn Storage,
+    collateral_id: &str,
+    now: Timestamp,
+) -> Result<Decimal, ContractError> {
+    let (stability_fee_rate, accrued_stability_fees) =
+        helper_stability_fees_accrued(storage, collateral_id, now)?;
+
+    if !accrued_stability_fees.is_zero() {
+        let unminted_stability_fees = UNMINTED_STABILITY_FEES
+            .may_load(storage)?
+            .unwrap_or_default();
+        UNMINTED_STABILITY_FEES.save(storage, &(unminted_stability_fees + accrued_stability_fees))?;
+    }
+
+    STABILITY_FEE_RATES.save(storage, collateral_id, &stability_fee_rate)?;
+
+    Ok(stability_fee_rate.rate)
+}
+
+// Function to load the dira a wallet owes against a collateral type, accrued stability fees included
+fn helper_load_dira_owed(
+    storage: &dyn Storage,
+    wallet_address: &Addr,
+    collateral_id: &str,
+    rate: Decimal,
+) -> StdResult<Decimal> {
+    Ok(MINTED_DIRA
+        .may_load(storage, (wallet_address, collateral_id))?
+        .unwrap_or_default()
+        * rate)
+}
+
+// Function to load the dira a wallet minted against a collateral type and has not repaid yet,
+// without the stability fees accrued on it
+fn helper_load_principal_dira(
+    storage: &dyn Storage,
+    wallet_address: &Addr,
+    collateral_id: &str,
+) -> StdResult<Decimal> {
+    match PRINCIPAL_DIRA.may_load(storage, (wallet_address, collateral_id))? {
+        Some(principal_dira) => Ok(principal_dira),
+        // Debt minted before stability fees were charged has no principal saved, all of it is principal
+        None => Ok(MINTED_DIRA
+            .may_load(storage, (wallet_address, collateral_id))?
+            .unwrap_or_default()),
+    }
+}
+
+// Function to add newly minted dira to a wallet's debt against a collateral type
+fn helper_increase_debt(
+    storage: &mut dyn Storage,
+    wallet_address: &Addr,
+    collateral_id: &str,
+    rate: Decimal,
+    dira_minted: Decimal,
+) -> Result<(), ContractError> {
+    let normalized_dira_minted = dira_minted / rate;
+    let principal_dira = helper_load_principal_dira(storage, wallet_address, collateral_id)?;
+
+    MINTED_DIRA.update(
+        storage,
+        (wallet_address, collateral_id),
+        |normalized_debt| -> StdResult<Decimal> {
+            Ok(normalized_debt.unwrap_or_default() + normalized_dira_minted)
+        },
+    )?;
+
+    PRINCIPAL_DIRA.save(
+        storage,
+        (wallet_address, collateral_id),
+        &(principal_dira + dira_minted),
+    )?;
+
+    TOTAL_MINTED_DIRA.update(
+        storage,
+        collateral_id,
+        |total_normalized_debt| -> StdResult<Decimal> {
+            Ok(total_normalized_debt.unwrap_or_default() + normalized_dira_minted)
+        },
+    )?;
+
+    helper_update_redemption_queue(storage, wallet_address, collateral_id)?;
+
+    Ok(())
+}
+
+// Function to take repaid dira off a wallet's debt against a collateral type. Accrued
+// stability fees are paid off first, then the principal. Returns the dira still owed
+fn helper_decrease_debt(
+    storage: &mut dyn Storage,
+    wallet_address: &Addr,
+    collateral_id: &str,
+    rate: Decimal,
+    dira_repaid: Decimal,
+) -> Result<Decimal, ContractError> {
+    let normalized_debt = MINTED_DIRA
+        .may_load(storage, (wallet_address, collateral_id))?
+        .unwrap_or_default();
+    let principal_dira = helper_load_principal_dira(storage, wallet_address, collateral_id)?;
+
+    // Repaying everything that is owed clears the debt without leaving rounding dust behind
+    let normalized_dira_repaid = if dira_repaid >= normalized_debt * rate {
+        normalized_debt
+    } else {
+        (dira_repaid / rate).min(normalized_debt)
+    };
+
+    let remaining_normalized_debt = normalized_debt - normalized_dira_repaid;
+    let remaining_dira_owed = remaining_normalized_debt * rate;
+
+    MINTED_DIRA.save(
+        storage,
+        (wallet_address, collateral_id),
+        &remaining_normalized_debt,
+    )?;
+
+    PRINCIPAL_DIRA.save(
+        storage,
+        (wallet_address, collateral_id),
+        &principal_dira.min(remaining_dira_owed),
+    )?;
+
+    TOTAL_MINTED_DIRA.update(
+        storage,
+        collateral_id,
+        |total_normalized_debt| -> StdResult<Decimal> {
+            Ok(total_normalized_debt
+                .unwrap_or_default()
+                .saturating_sub(normalized_dira_repaid))
+        },
+    )?;
+
+    helper_update_redemption_queue(storage, wallet_address, collateral_id)?;
+
+    Ok(remaining_dira_owed)
+}
+
+// Function to lock native collateral
+fn execute_lock_collateral(
+    deps: DepsMut,
+    info: MessageInfo,
+    collateral_id: String,
+) -> Result<Response, ContractError> {
+    let collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+
+    if collateral_info.asset_type != CollateralAssetType::Native {
+        return Err(ContractError::CollateralAssetTypeMismatch { collateral_id });
+    }
+
+    // Check if the user has sent enough funds along with the transaction
+    let sent_funds = info
+        .funds
+        .iter()
+        .find(|coin| coin.denom == collateral_id && !coin.amount.is_zero())
+        .ok_or(ContractError::InsufficientFundsSent {})?;
+
+    let sent_amount = helper_amount_to_decimal(sent_funds.amount, collateral_info.decimals)?;
+
+    helper_lock_collateral(deps, info.sender, collateral_id, sent_amount)
+}
+
+// Function to check that the CW20 tokens received by the contract are DIRA
+fn helper_assert_dira_token(
+    storage: &dyn Storage,
+    cw20_contract_address: &Addr,
+) -> Result<(), ContractError> {
+    if helper_load_cw20_dira_contract_address(storage)? != *cw20_contract_address {
+        return Err(ContractError::NotDiraToken {});
+    }
+
+    Ok(())
+}
+
+// Function to handle CW20 tokens sent to the contract, dispatching on the hook message
+fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_receive_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    // The CW20 contract calling this hook is the token that was received
+    let cw20_contract_address = info.sender;
+    let sender = deps.api.addr_validate(&cw20_receive_msg.sender)?;
+
+    match from_json(&cw20_receive_msg.msg)? {
+        ReceiveMsg::Repay { collateral_id } => {
+            helper_assert_dira_token(deps.storage, &cw20_contract_address)?;
+            execute_repay_dira(deps, env, sender, collateral_id, cw20_receive_msg.amount)
+        }
+
+        ReceiveMsg::DepositStabilityPool {} => {
+            helper_assert_dira_token(deps.storage, &cw20_contract_address)?;
+            execute_deposit_stability_pool(deps, sender, cw20_receive_msg.amount)
+        }
+
+        ReceiveMsg::DepositSavings {} => {
+            helper_assert_dira_token(deps.storage, &cw20_contract_address)?;
+            execute_deposit_savings(deps, env, sender, cw20_receive_msg.amount)
+        }
+
+        ReceiveMsg::SwapStablecoinForDira {} => {
+            let asset_id = cw20_contract_address.to_string();
+            let psm_asset = helper_load_psm_asset(deps.storage, &asset_id)?;
+
+            if psm_asset.asset_type != CollateralAssetType::Cw20 {
+                return Err(ContractError::CollateralAssetTypeMismatch {
+                    collateral_id: asset_id,
+                });
+            }
+
+            if cw20_receive_msg.amount.is_zero() {
+                return Err(ContractError::InsufficientFundsSent {});
+            }
+
+            let stablecoin_amount =
+                helper_amount_to_decimal(cw20_receive_msg.amount, psm_asset.decimals)?;
+
+            execute_swap_stablecoin_for_dira(deps, env, sender, asset_id, psm_asset, stablecoin_amount)
+        }
+
+        ReceiveMsg::SwapDiraForStablecoin { asset_id } => {
+            helper_assert_dira_token(deps.storage, &cw20_contract_address)?;
+            execute_swap_dira_for_stablecoin(deps, sender, asset_id, cw20_receive_msg.amount)
+        }
+
+        ReceiveMsg::Redeem { collateral_id } => {
+            helper_assert_dira_token(deps.storage, &cw20_contract_address)?;
+            execute_redeem(deps, env, sender, collateral_id, cw20_receive_msg.amount)
+        }
+
+        ReceiveMsg::BidAuction {
+            auction_id,
+            max_collateral,
+        } => {
+            helper_assert_dira_token(deps.storage, &cw20_contract_address)?;
+            execute_bid_auction(
+                deps,
+                env,
+                sender,
+                auction_id,
+                max_collateral,
+                cw20_receive_msg.amount,
+            )
+        }
+
+        ReceiveMsg::Liquidate {
+            wallet_address_to_liquidate,
+            collateral_id,
+        } => {
+            helper_assert_dira_token(deps.storage, &cw20_contract_address)?;
+
+            // The DIRA sent along is exactly the debt being repaid
+            let debt_to_repay = helper_amount_to_decimal(cw20_receive_msg.amount, DIRA_DECIMALS)?;
+
+            execute_liquidate_stablecoin_minter(
+                deps,
+                env,
+                sender,
+                wallet_address_to_liquidate,
+                collateral_id,
+                debt_to_repay,
+                LiquidatorPayment::Received,
+            )
+        }
+
+        ReceiveMsg::CashOut {} => {
+            helper_assert_dira_token(deps.storage, &cw20_contract_address)?;
+            execute_cash_out(deps, env, sender, cw20_receive_msg.amount)
+        }
+
+        ReceiveMsg::LockCollateral {} => {
+            let collateral_id = cw20_contract_address.to_string();
+            let collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+
+            if collateral_info.asset_type != CollateralAssetType::Cw20 {
+                return Err(ContractError::CollateralAssetTypeMismatch { collateral_id });
+            }
+
+            if cw20_receive_msg.amount.is_zero() {
+                return Err(ContractError::InsufficientFundsSent {});
+            }
+
+            let received_amount =
+                helper_amount_to_decimal(cw20_receive_msg.amount, collateral_info.decimals)?;
+
+            helper_lock_collateral(deps, sender, collateral_id, received_amount)
+        }
+    }
+}
+
+// Function to unlock collateral
+fn execute_unlock_collateral(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collateral_id: String,
+    collateral_amount: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_not_shut_down(deps.storage)?;
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.unlocking {
+        return Err(ContractError::Paused {
+            operation: "unlocking".to_string(),
+        });
+    }
+
+    let collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+
+    let message_sender = info.sender;
+
+    let locked_collateral = LOCKED_COLLATERAL
+        .may_load(deps.storage, (&message_sender, &collateral_id))?
+        .unwrap_or_default();
+
+    let rate = helper_stability_fee_rate(deps.storage, &collateral_id, env.block.time)?.rate;
+    let minted_dira = helper_load_dira_owed(deps.storage, &message_sender, &collateral_id, rate)?;
+
+    let collateral_price_in_dirham =
+        helper_collateral_price(deps.as_ref(), env.block.time, &collateral_id, &collateral_info)?;
+
+    let max_unlockable_collateral = helper_calculate_max_unlockable_collateral(
+        locked_collateral,
+        collateral_price_in_dirham,
+        minted_dira,
+        collateral_info.mintable_health,
+    );
+
+    if collateral_amount > max_unlockable_collateral {
+        return Err(ContractError::UnlockAmountTooHigh {
+            max_unlockable: max_unlockable_collateral,
+        });
+    }
+
+    let total_funds_locked_by_user = locked_collateral - collateral_amount;
+
+    LOCKED_COLLATERAL.save(
+        deps.storage,
+        (&message_sender, &collateral_id),
+        &total_funds_locked_by_user,
+    )?;
+
+    helper_update_redemption_queue(deps.storage, &message_sender, &collateral_id)?;
+
+    let return_collateral_to_user_message = helper_send_collateral_message(
+        &collateral_id,
+        &collateral_info,
+        &message_sender,
+        collateral_amount,
+    )?;
+
+    Ok(Response::new()
+        .add_message(return_collateral_to_user_message)
+        .add_attribute("action", "unlock_collateral")
+        .add_attribute("sender", message_sender)
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute(
+            "total_funds_locked_by_user",
+            total_funds_locked_by_user.to_string(),
+        ))
+}
+
+// Function to mint dira
+fn execute_mint_dira(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collateral_id: String,
+    dira_to_mint: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_not_shut_down(deps.storage)?;
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.minting {
+        return Err(ContractError::Paused {
+            operation: "minting".to_string(),
+        });
+    }
+
+    // First calculate how much dira this user can mint based on current collateral price
+    // and how much collateral they have locked
+
+    // To do this, first load all the variables from the blockchain
+    let collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+
+    let collateral_locked_by_user =
+        match LOCKED_COLLATERAL.may_load(deps.storage, (&info.sender, &collateral_id)) {
+            Ok(Some(locked_collateral)) => locked_collateral,
+            _ => return Err(ContractError::InsufficientCollateral {}),
+        };
+
+    let rate = helper_accrue_stability_fee(deps.storage, &collateral_id, env.block.time)?;
+    let previously_minted_dira =
+        helper_load_dira_owed(deps.storage, &info.sender, &collateral_id, rate)?;
+
+    let collateral_price_in_dirham =
+        helper_collateral_price(deps.as_ref(), env.block.time, &collateral_id, &collateral_info)?;
+
+    // Finally use the helper function to calculate max mintable dira by this user
+    let max_mintable_dira = helper_calculate_max_mintable_dira(
+        collateral_locked_by_user,
+        collateral_price_in_dirham,
+        collateral_info.mintable_health,
+    );
+
+    if dira_to_mint + previously_minted_dira > max_mintable_dira {
+        return Err(ContractError::InsufficientCollateral {});
+    }
+
+    //Implementation of fee switch mechanism
+    let fee_config = CONFIG.load(deps.storage)?.fee_switch;
+    let fee_amount = helper_calculate_fee_tier_amount(dira_to_mint, &fee_config)?;
+
+    // Work in CW20 units so that the DIRA minted to the user and the fee add up to the new debt
+    let fee_amount_in_cw20 = helper_decimal_to_amount(fee_amount, DIRA_DECIMALS);
+    let dira_to_mint_after_fee_deduction =
+        helper_decimal_to_amount(dira_to_mint, DIRA_DECIMALS) - fee_amount_in_cw20;
+
+    // Make sure this mint does not push the collateral type over its debt ceiling
+    let total_minted_dira = TOTAL_MINTED_DIRA
+        .may_load(deps.storage, &collateral_id)?
+        .unwrap_or_default()
+        * rate
+        + dira_to_mint;
+
+    if let Some(debt_ceiling) = collateral_info.debt_ceiling {
+        if total_minted_dira > debt_ceiling {
+            return Err(ContractError::DebtCeilingExceeded { debt_ceiling });
+        }
+    }
+
+    // Else, mint dira and transfer it to user, add that message to the response. The fee
+    // minted to the treasury is owed like the rest, so the DIRA supply matches the debt
+    helper_increase_debt(
+        deps.storage,
+        &info.sender,
+        &collateral_id,
+        rate,
+        dira_to_mint,
+    )?;
+
+    // Get the CW20 contract address
+    let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
+
+    //Admin address that routes to treasury
+    let treasury_address = helper_load_treasury_address(deps.storage)?;
+
+
+    // Mint CW20 tokens , to treasury according to fee tiers
+    let mint_msg_for_treasury = cw20::Cw20ExecuteMsg::Mint {
         recipient : treasury_address.to_string() ,
-        amount: fee_amount.atomics() / Uint128::from(u128::pow(10, 12)),
+        amount: fee_amount_in_cw20,
     } ;
 
     let mint_treasury_charges = cosmwasm_std::WasmMsg::Execute {
@@ -424,7 +1707,7 @@ fn execute_mint_dira(
     // Mint CW20 tokens to user
     let mint_msg = cw20::Cw20ExecuteMsg::Mint {
         recipient: info.sender.to_string(),
-        amount:dira_to_mint_after_fee_deduction.atomics() / Uint128::from(u128::pow(10, 12)),
+        amount: dira_to_mint_after_fee_deduction,
     };
 
     let mint_cw20_message = cosmwasm_std::WasmMsg::Execute {
@@ -440,312 +1723,4087 @@ fn execute_mint_dira(
         .add_message(mint_treasury_charges)
         .add_attribute("action", "mint_dira")
         .add_attribute("sender", info.sender.to_string())
+        .add_attribute("collateral_id", collateral_id)
         .add_attribute(
             "total_dira_minted_by_sender",
-            (dira_to_mint_after_fee_deduction + previously_minted_dira).to_string(),
+            (dira_to_mint + previously_minted_dira).to_string(),
         ))
 }
 
-// Function to burn dira for the original collateral
-fn execute_burn_dira(
+// Function to repay dira debt with DIRA sent to the contract through the CW20 Receive hook.
+// The received DIRA is burned, except for the fee which is routed to the treasury. The fee is
+// paid on top of the debt repaid, so only the burned DIRA comes off the debt
+fn execute_repay_dira(
     deps: DepsMut,
-    info: MessageInfo,
-    dira_to_return: Decimal,
+    env: Env,
+    sender: Addr,
+    collateral_id: String,
+    dira_received: Uint128,
 ) -> Result<Response, ContractError> {
-    let previously_minted_dira = match MINTED_DIRA.may_load(deps.storage, info.sender.clone()) {
-        Ok(Some(minted_dira)) => minted_dira,
-        _ => Decimal::zero(),
-    };
+    // After shutdown debt is only settled against collateral, so the DIRA supply stays
+    // as snapshotted until it is cashed out
+    helper_check_not_shut_down(deps.storage)?;
 
-    if dira_to_return > previously_minted_dira {
-        return Err(ContractError::ReturningMoreDiraThanMinted {});
+    let rate = helper_accrue_stability_fee(deps.storage, &collateral_id, env.block.time)?;
+
+    let dira_to_return = helper_amount_to_decimal(dira_received, DIRA_DECIMALS)?;
+
+    let previously_minted_dira = helper_load_dira_owed(deps.storage, &sender, &collateral_id, rate)?;
+
+    if dira_to_return.is_zero() {
+        return Err(ContractError::InsufficientFundsSent {});
     }
 
-    let fee_config = FEE_SWITCH.load(deps.storage)?;
+    let fee_config = CONFIG.load(deps.storage)?.fee_switch;
     let fee_amount = helper_calculate_fee_tier_amount(dira_to_return, &fee_config)?;
-    let dira_to_burn_after_fee_deduction = dira_to_return - fee_amount ;
 
+    // Work in CW20 units so that the burned DIRA and the fee add up to exactly what was received
+    let fee_amount_in_cw20 = helper_decimal_to_amount(fee_amount, DIRA_DECIMALS);
+    let dira_to_burn_after_fee_deduction = dira_received - fee_amount_in_cw20;
+    let dira_repaid = helper_amount_to_decimal(dira_to_burn_after_fee_deduction, DIRA_DECIMALS)?;
 
-    MINTED_DIRA.save(
+    if dira_repaid > previously_minted_dira {
+        return Err(ContractError::ReturningMoreDiraThanMinted {});
+    }
+
+    let remaining_dira = helper_decrease_debt(
         deps.storage,
-        info.sender.clone(),
-        &(previously_minted_dira - dira_to_return),
+        &sender,
+        &collateral_id,
+        rate,
+        dira_repaid,
     )?;
 
     // Get the CW20 contract address
-    let cw20_dira_contract_address = match CW20_DIRA_CONTRACT_ADDRESS.may_load(deps.storage) {
-        Ok(Some(contract_address)) => contract_address,
-        _ => return Err(ContractError::CW20DiraContractAddressNotSet {}),
-    };
-
-     // Burn CW20 tokens
-    let burn_msg = cw20::Cw20ExecuteMsg::BurnFrom {
-        owner: info.sender.to_string(),
-        amount: dira_to_burn_after_fee_deduction.atomics() / Uint128::from(u128::pow(10, 12))
-    };
+    let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
 
+    // Burn the CW20 tokens now held by this contract
     let burn_cw20_message = cosmwasm_std::WasmMsg::Execute {
         contract_addr: cw20_dira_contract_address.to_string(),
-        msg: to_json_binary(&burn_msg)?,
+        msg: to_json_binary(&cw20::Cw20ExecuteMsg::Burn {
+            amount: dira_to_burn_after_fee_deduction,
+        })?,
         funds: vec![],
     };
 
+    let mut response = Response::new().add_message(burn_cw20_message);
+
     // Treasury charges
-    let admins = ADMIN_ADDRESSES.load(deps.storage)?;
-    let treasury_address =
-        admins.first()
-            .ok_or(ContractError::NoAdminAddressesSet {})?;
-
-    let transfer_fee_cw20 = cw20::Cw20ExecuteMsg::Transfer {
-        recipient: treasury_address.to_string(),
-        amount: fee_amount.atomics() / Uint128::from(u128::pow(10, 12)),
-    };
+    if !fee_amount_in_cw20.is_zero() {
+        let treasury_address = helper_load_treasury_address(deps.storage)?;
+
+        let transfer_fee_cw20_msg = cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Transfer {
+                recipient: treasury_address.to_string(),
+                amount: fee_amount_in_cw20,
+            })?,
+            funds: vec![],
+        };
 
+        response = response.add_message(transfer_fee_cw20_msg);
+    }
 
-    let transfer_fee_cw20_msg = cosmwasm_std::WasmMsg::Execute {
-        contract_addr: cw20_dira_contract_address.to_string(),
-        msg: to_json_binary(&transfer_fee_cw20)?,
-        funds: vec![],
-    };
+    Ok(response
+        .add_attribute("action", "repay_dira")
+        .add_attribute("sender", sender.to_string())
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute("dira_burned", dira_to_burn_after_fee_deduction.to_string())
+        .add_attribute("fee_paid", fee_amount_in_cw20.to_string())
+        .add_attribute("total_dira_remaining_by_sender", remaining_dira.to_string()))
+}
 
-    Ok(Response::new()
-        .add_message(burn_cw20_message)
-        // .add_message(transfer_fee_cw20_msg)
-        .add_attribute("action", "burn_dira")
-        .add_attribute("sender", info.sender.to_string())
-        .add_attribute(
-            "total_dira_remaining_by_sender",
-            (previously_minted_dira - dira_to_return).to_string(),
-        ))
+// How the DIRA repaid by a liquidator reaches the contract to be burned
+enum LiquidatorPayment {
+    // The liquidator already sent the DIRA to this contract through the CW20 Receive hook
+    Received,
+    // The DIRA deposited in the stability pool repays the debt, and the pool's
+    // depositors receive the seized collateral instead of the liquidator
+    StabilityPool,
 }
 
-// Function to liquidate stablecoins
-fn execute_liquidate_stablecoin_minter(
-    deps: DepsMut,
-    info: MessageInfo,
-    wallet_address_to_liquidate: Addr,
-) -> Result<Response, ContractError> {
+// A position that can be liquidated, as loaded by helper_load_liquidatable_position
+struct LiquidatablePosition {
+    collateral_info: CollateralInfo,
+    collateral_price_in_dirham: Decimal,
+    collateral_locked: Decimal,
+}
+
+// Function to load a position and check that it can be liquidated for the given amount of debt
+fn helper_load_liquidatable_position(
+    deps: Deps,
+    wallet_address_to_liquidate: &Addr,
+    collateral_id: &str,
+    debt_to_repay: Decimal,
+    rate: Decimal,
+    now: Timestamp,
+) -> Result<LiquidatablePosition, ContractError> {
     // Validate the wallet address
     deps.api
         .addr_validate(wallet_address_to_liquidate.as_str())
         .map_err(|_| ContractError::InvalidWalletAddress {})?;
 
+    if debt_to_repay.is_zero() {
+        return Err(ContractError::AmountCannotBeZero {});
+    }
+
     // Load relevant data for liquidation
-    let dira_minted_by_wallet_to_liquidate = MINTED_DIRA
-        .load(deps.storage, wallet_address_to_liquidate.clone())
-        .unwrap_or_default();
+    let collateral_info = helper_load_collateral_info(deps.storage, collateral_id)?;
 
-    let collateral_price_in_dirham = COLLATERAL_TOKEN_PRICE
-        .load(deps.storage)
-        .map_err(|_| ContractError::CollateralPriceNotSet {})?;
+    let dira_minted =
+        helper_load_dira_owed(deps.storage, wallet_address_to_liquidate, collateral_id, rate)?;
 
-    let collateral_locked_by_user_to_liquidate = LOCKED_COLLATERAL
-        .load(deps.storage, wallet_address_to_liquidate.clone())
-        .unwrap_or_default();
+    let collateral_price_in_dirham =
+        helper_collateral_price(deps, now, collateral_id, &collateral_info)?;
 
-    let liquidation_health = LIQUIDATION_HEALTH.load(deps.storage)?;
+    let collateral_locked = LOCKED_COLLATERAL
+        .may_load(deps.storage, (wallet_address_to_liquidate, collateral_id))?
+        .unwrap_or_default();
 
     // Calculate health
     let user_health = helper_calculate_stablecoin_health(
-        dira_minted_by_wallet_to_liquidate,
-        collateral_locked_by_user_to_liquidate,
+        dira_minted,
+        collateral_locked,
         collateral_price_in_dirham,
     );
 
     // Check if the user is liquidatable
-    if user_health >= liquidation_health {
+    if user_health >= collateral_info.liquidation_health {
         return Err(ContractError::TooHealthyToLiquidate {
-            wallet_address: wallet_address_to_liquidate,
+            wallet_address: wallet_address_to_liquidate.clone(),
         });
     }
 
-    // Liquidate: Reset the collateral to zero
-    LOCKED_COLLATERAL.save(
-        deps.storage,
-        wallet_address_to_liquidate.clone(),
-        &Decimal::zero(),
-    )?;
+    // A single liquidation can only repay up to the close factor of the debt
+    let close_factor = CONFIG.load(deps.storage)?.close_factor;
+    let max_repayable_dira = dira_minted * close_factor;
 
-    let mut liquidated_dira = Decimal::zero();
-    MINTED_DIRA.update(
-        deps.storage,
-        wallet_address_to_liquidate.clone(),
-        |minted_dira| {
-            liquidated_dira = minted_dira.unwrap_or_default();
-            Ok::<Decimal, StdError>(Decimal::zero())
-        },
-    )?;
+    if debt_to_repay > max_repayable_dira {
+        return Err(ContractError::LiquidationAmountTooHigh {
+            max_repayable: max_repayable_dira,
+        });
+    }
 
-    // Return a successful response
-    Ok(Response::new()
-        .add_attribute("action", "liquidate_stablecoins")
-        .add_attribute("liquidated_wallet", wallet_address_to_liquidate.to_string())
-        .add_attribute(
-            "liquidated_collateral",
-            collateral_locked_by_user_to_liquidate.to_string(),
-        )
-        .add_attribute("liquidated_dira", liquidated_dira.to_string())
-        .add_attribute("initiator", info.sender.to_string())
-        .add_attribute("liquidator_reward_paid", "0"))
-    // TODO: Update liquidator reward logic here
+    Ok(LiquidatablePosition {
+        collateral_info,
+        collateral_price_in_dirham,
+        collateral_locked,
+    })
 }
 
-// Function to set collateral prices in dirham
-fn execute_set_collateral_price_in_dirham(
-    deps: DepsMut,
+// Function to liquidate stablecoins
+fn execute_liquidate_stablecoin_minter(
+    deps: DepsMut,
+    env: Env,
+    liquidator: Addr,
+    wallet_address_to_liquidate: Addr,
+    collateral_id: String,
+    debt_to_repay: Decimal,
+    liquidator_payment: LiquidatorPayment,
+) -> Result<Response, ContractError> {
+    helper_check_not_shut_down(deps.storage)?;
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.liquidations {
+        return Err(ContractError::Paused {
+            operation: "liquidations".to_string(),
+        });
+    }
+
+    let rate = helper_accrue_stability_fee(deps.storage, &collateral_id, env.block.time)?;
+
+    let LiquidatablePosition {
+        collateral_info,
+        collateral_price_in_dirham,
+        collateral_locked: collateral_locked_by_user_to_liquidate,
+    } = helper_load_liquidatable_position(
+        deps.as_ref(),
+        &wallet_address_to_liquidate,
+        &collateral_id,
+        debt_to_repay,
+        rate,
+        env.block.time,
+    )?;
+
+    // Keep the price the position was liquidated at on record, whatever its price source
+    helper_record_collateral_price(
+        deps.storage,
+        &collateral_id,
+        env.block.time,
+        collateral_price_in_dirham,
+    )?;
+
+    // Seize collateral worth the repaid debt plus the liquidation bonus, capped at
+    // whatever the wallet has left if the position is underwater
+    let config = CONFIG.load(deps.storage)?;
+    let liquidation_bonus = config.liquidation_bonus;
+    let repaid_collateral = debt_to_repay / collateral_price_in_dirham;
+    let liquidated_collateral = (debt_to_repay * (Decimal::one() + liquidation_bonus)
+        / collateral_price_in_dirham)
+        .min(collateral_locked_by_user_to_liquidate);
+
+    // The bonus is the penalty paid by the liquidated wallet, a share of it goes to the treasury
+    let liquidation_penalty = liquidated_collateral.saturating_sub(repaid_collateral);
+    let protocol_share = config.liquidation_protocol_share;
+    let protocol_penalty = liquidation_penalty * protocol_share;
+    let liquidator_reward = liquidation_penalty - protocol_penalty;
+    let collateral_to_liquidator = liquidated_collateral - protocol_penalty;
+
+    let remaining_collateral = collateral_locked_by_user_to_liquidate - liquidated_collateral;
+
+    LOCKED_COLLATERAL.save(
+        deps.storage,
+        (&wallet_address_to_liquidate, &collateral_id),
+        &remaining_collateral,
+    )?;
+
+    let remaining_dira = helper_decrease_debt(
+        deps.storage,
+        &wallet_address_to_liquidate,
+        &collateral_id,
+        rate,
+        debt_to_repay,
+    )?;
+
+    // The liquidator pays off the debt with their own DIRA, which is burned so that the
+    // DIRA supply goes down by exactly the debt that was cancelled
+    let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
+    let dira_to_burn = helper_decimal_to_amount(debt_to_repay, DIRA_DECIMALS);
+
+    let burn_cw20_message = cosmwasm_std::WasmMsg::Execute {
+        contract_addr: cw20_dira_contract_address.to_string(),
+        msg: to_json_binary(&cw20::Cw20ExecuteMsg::Burn {
+            amount: dira_to_burn,
+        })?,
+        funds: vec![],
+    };
+
+    let mut response = Response::new().add_message(burn_cw20_message);
+
+    // In exchange the liquidator receives the seized collateral minus the protocol's cut
+    if let LiquidatorPayment::StabilityPool = liquidator_payment {
+        helper_stability_pool_offset(
+            deps.storage,
+            &collateral_id,
+            debt_to_repay,
+            collateral_to_liquidator,
+        )?;
+    } else if !helper_decimal_to_amount(collateral_to_liquidator, collateral_info.decimals)
+        .is_zero()
+    {
+        response = response.add_message(helper_send_collateral_message(
+            &collateral_id,
+            &collateral_info,
+            &liquidator,
+            collateral_to_liquidator,
+        )?);
+    }
+
+    if !helper_decimal_to_amount(protocol_penalty, collateral_info.decimals).is_zero() {
+        let treasury_address = helper_load_treasury_address(deps.storage)?;
+
+        response = response.add_message(helper_send_collateral_message(
+            &collateral_id,
+            &collateral_info,
+            &treasury_address,
+            protocol_penalty,
+        )?);
+    }
+
+    // Return a successful response
+    Ok(response
+        .add_attribute("action", "liquidate_stablecoins")
+        .add_attribute("liquidated_wallet", wallet_address_to_liquidate.to_string())
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute("liquidated_collateral", liquidated_collateral.to_string())
+        .add_attribute("liquidated_dira", debt_to_repay.to_string())
+        .add_attribute("remaining_collateral", remaining_collateral.to_string())
+        .add_attribute("remaining_dira", remaining_dira.to_string())
+        .add_attribute("initiator", liquidator.to_string())
+        .add_attribute("liquidator_reward_paid", liquidator_reward.to_string())
+        .add_attribute("protocol_penalty_paid", protocol_penalty.to_string()))
+}
+
+// Function to add an admin
+fn execute_add_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    admin_address: Addr,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::Owner)?;
+
+    let mut admins = ADMIN_ADDRESSES.load(deps.storage)?;
+
+    let admin_address = deps
+        .api
+        .addr_validate(admin_address.as_str())
+        .map_err(|_| ContractError::InvalidWalletAddress {})?;
+
+    if admins.contains(&admin_address) {
+        return Err(ContractError::AdminAlreadyAdded { admin_address });
+    }
+
+    admins.push(admin_address.clone());
+    ADMIN_ADDRESSES.save(deps.storage, &admins)?;
+
+    for role in ADMIN_ROLES.iter() {
+        ROLES.save(deps.storage, (role.as_str(), &admin_address), &Empty {})?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "add_admin")
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("admin_address", admin_address.clone())
+        .add_event(
+            Event::new("admin_added")
+                .add_attribute("sender", info.sender)
+                .add_attribute("admin_address", admin_address),
+        ))
+}
+
+// Function to remove an admin along with all of their roles. The owner and the last admin
+// cannot be removed
+fn execute_remove_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    admin_address: Addr,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::Owner)?;
+
+    let mut admins = ADMIN_ADDRESSES.load(deps.storage)?;
+
+    if !admins.contains(&admin_address) {
+        return Err(ContractError::UnknownAdmin { admin_address });
+    }
+
+    if admins.len() == 1 {
+        return Err(ContractError::CannotRemoveLastAdmin {});
+    }
+
+    if admins.first() == Some(&admin_address) {
+        return Err(ContractError::CannotRemoveOwner {});
+    }
+
+    admins.retain(|admin| *admin != admin_address);
+    ADMIN_ADDRESSES.save(deps.storage, &admins)?;
+
+    for role in Role::ALL.iter() {
+        ROLES.remove(deps.storage, (role.as_str(), &admin_address));
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_admin")
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("admin_address", admin_address.clone())
+        .add_event(
+            Event::new("admin_removed")
+                .add_attribute("sender", info.sender)
+                .add_attribute("admin_address", admin_address),
+        ))
+}
+
+// Function for the owner to propose handing ownership over to another wallet, replacing any
+// earlier proposal
+fn execute_propose_ownership_transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_owner: Addr,
+    expires_in_seconds: u64,
+) -> Result<Response, ContractError> {
+    let owner = helper_load_treasury_address(deps.storage)?;
+
+    if info.sender != owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    if expires_in_seconds == 0 || expires_in_seconds > MAX_OWNERSHIP_TRANSFER_EXPIRY_SECONDS {
+        return Err(ContractError::InvalidOwnershipTransferExpiry {
+            max_expiry_seconds: MAX_OWNERSHIP_TRANSFER_EXPIRY_SECONDS,
+        });
+    }
+
+    let new_owner = deps
+        .api
+        .addr_validate(new_owner.as_str())
+        .map_err(|_| ContractError::InvalidWalletAddress {})?;
+
+    let expires_at = env.block.time.plus_seconds(expires_in_seconds);
+
+    PENDING_OWNERSHIP_TRANSFER.save(
+        deps.storage,
+        &PendingOwnershipTransfer {
+            new_owner: new_owner.clone(),
+            expires_at,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_ownership_transfer")
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("new_owner", new_owner.clone())
+        .add_event(
+            Event::new("ownership_transfer_proposed")
+                .add_attribute("owner", info.sender)
+                .add_attribute("new_owner", new_owner)
+                .add_attribute("expires_at", expires_at.to_string()),
+        ))
+}
+
+// Function for the proposed owner to take over ownership, becoming the first admin
+fn execute_accept_ownership_transfer(
+    deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    collateral_price_in_dirham: Decimal,
 ) -> Result<Response, ContractError> {
-    let admins = ADMIN_ADDRESSES.load(deps.storage)?;
+    let pending_ownership_transfer = PENDING_OWNERSHIP_TRANSFER
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingOwnershipTransfer {})?;
 
-    if !admins.contains(&info.sender) {
+    if info.sender != pending_ownership_transfer.new_owner {
         return Err(ContractError::UnauthorizedUser {});
     }
 
-    match COLLATERAL_TOKEN_PRICE.save(deps.storage, &collateral_price_in_dirham) {
-        Ok(_result) => {}
-        Err(error) => {
-            dbg!(&error);
-            panic!("Error in updating COLLATERAL_TOKEN_PRICE storage item");
+    if env.block.time >= pending_ownership_transfer.expires_at {
+        return Err(ContractError::OwnershipTransferExpired {
+            expires_at: pending_ownership_transfer.expires_at,
+        });
+    }
+
+    let previous_owner = helper_load_treasury_address(deps.storage)?;
+
+    let mut admins = ADMIN_ADDRESSES.load(deps.storage)?;
+    admins.retain(|admin| *admin != info.sender);
+    admins.insert(0, info.sender.clone());
+    ADMIN_ADDRESSES.save(deps.storage, &admins)?;
+
+    // The Owner role moves over to the new owner, who holds every role like the owner before
+    ROLES.remove(deps.storage, (Role::Owner.as_str(), &previous_owner));
+
+    for role in Role::ALL.iter() {
+        ROLES.save(deps.storage, (role.as_str(), &info.sender), &Empty {})?;
+    }
+
+    PENDING_OWNERSHIP_TRANSFER.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_ownership_transfer")
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("previous_owner", previous_owner.clone())
+        .add_event(
+            Event::new("ownership_transferred")
+                .add_attribute("previous_owner", previous_owner)
+                .add_attribute("new_owner", info.sender),
+        ))
+}
+
+// Function for the owner to withdraw an ownership transfer that was not accepted yet
+fn execute_cancel_ownership_transfer(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let owner = helper_load_treasury_address(deps.storage)?;
+
+    if info.sender != owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    let pending_ownership_transfer = PENDING_OWNERSHIP_TRANSFER
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingOwnershipTransfer {})?;
+
+    PENDING_OWNERSHIP_TRANSFER.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_ownership_transfer")
+        .add_attribute("sender", info.sender.clone())
+        .add_event(
+            Event::new("ownership_transfer_cancelled")
+                .add_attribute("owner", info.sender)
+                .add_attribute("new_owner", pending_ownership_transfer.new_owner),
+        ))
+}
+
+// Function for the owner to grant a role to an address
+fn execute_grant_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    address: Addr,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::Owner)?;
+
+    let address = deps
+        .api
+        .addr_validate(address.as_str())
+        .map_err(|_| ContractError::InvalidWalletAddress {})?;
+
+    if ROLES.has(deps.storage, (role.as_str(), &address)) {
+        return Err(ContractError::RoleAlreadyGranted {
+            role: role.as_str().to_string(),
+            address,
+        });
+    }
+
+    ROLES.save(deps.storage, (role.as_str(), &address), &Empty {})?;
+
+    Ok(Response::new()
+        .add_attribute("action", "grant_role")
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", address.clone())
+        .add_event(
+            Event::new("role_granted")
+                .add_attribute("sender", info.sender)
+                .add_attribute("role", role.as_str())
+                .add_attribute("address", address),
+        ))
+}
+
+// Function for the owner to revoke a role from an address
+fn execute_revoke_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    address: Addr,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::Owner)?;
+
+    if !ROLES.has(deps.storage, (role.as_str(), &address)) {
+        return Err(ContractError::RoleNotGranted {
+            role: role.as_str().to_string(),
+            address,
+        });
+    }
+
+    if role == Role::Owner && address == helper_load_treasury_address(deps.storage)? {
+        return Err(ContractError::CannotRevokeOwnerRole {});
+    }
+
+    ROLES.remove(deps.storage, (role.as_str(), &address));
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke_role")
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", address.clone())
+        .add_event(
+            Event::new("role_revoked")
+                .add_attribute("sender", info.sender)
+                .add_attribute("role", role.as_str())
+                .add_attribute("address", address),
+        ))
+}
+
+// Function for the guardian to pause operations until a deadline
+fn execute_pause(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    flags: PauseFlags,
+    duration_seconds: u64,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::Pauser)?;
+
+    if duration_seconds == 0 || duration_seconds > MAX_PAUSE_DURATION_SECONDS {
+        return Err(ContractError::InvalidPauseDuration {
+            max_pause_duration_seconds: MAX_PAUSE_DURATION_SECONDS,
+        });
+    }
+
+    let pause_state = match PAUSE_STATE.may_load(deps.storage)? {
+        // Operations paused while a pause is running share its deadline
+        Some(pause_state) if env.block.time < pause_state.paused_until => PauseState {
+            flags: PauseFlags {
+                minting: pause_state.flags.minting || flags.minting,
+                unlocking: pause_state.flags.unlocking || flags.unlocking,
+                liquidations: pause_state.flags.liquidations || flags.liquidations,
+                price_updates: pause_state.flags.price_updates || flags.price_updates,
+            },
+            paused_until: pause_state.paused_until,
+        },
+
+        Some(pause_state)
+            if env.block.time < pause_state.paused_until.plus_seconds(PAUSE_COOLDOWN_SECONDS) =>
+        {
+            return Err(ContractError::PauseCooldown {
+                available_at: pause_state.paused_until.plus_seconds(PAUSE_COOLDOWN_SECONDS),
+            });
+        }
+
+        _ => PauseState {
+            flags,
+            paused_until: env.block.time.plus_seconds(duration_seconds),
+        },
+    };
+
+    PAUSE_STATE.save(deps.storage, &pause_state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "pause")
+        .add_attribute("sender", info.sender)
+        .add_attribute("minting", pause_state.flags.minting.to_string())
+        .add_attribute("unlocking", pause_state.flags.unlocking.to_string())
+        .add_attribute("liquidations", pause_state.flags.liquidations.to_string())
+        .add_attribute("price_updates", pause_state.flags.price_updates.to_string())
+        .add_attribute("paused_until", pause_state.paused_until.to_string()))
+}
+
+// Function for the guardian to lift a pause before its deadline. The pause cooldown runs from the
+// moment it is lifted, so pausing and unpausing cannot be chained to keep the contract frozen
+fn execute_unpause(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::Pauser)?;
+
+    if let Some(pause_state) = PAUSE_STATE.may_load(deps.storage)? {
+        if env.block.time < pause_state.paused_until {
+            PAUSE_STATE.save(
+                deps.storage,
+                &PauseState {
+                    flags: PauseFlags::default(),
+                    paused_until: env.block.time,
+                },
+            )?;
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "unpause")
+        .add_attribute("sender", info.sender))
+}
+
+// Function to register a new collateral type with its risk parameters
+fn execute_register_collateral(
+    deps: DepsMut,
+    info: MessageInfo,
+    collateral_id: String,
+    collateral_info: CollateralInfo,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+
+    if collateral_id.is_empty() {
+        return Err(ContractError::MissingCollateralTokenDenom {});
+    }
+
+    if COLLATERALS.has(deps.storage, &collateral_id) {
+        return Err(ContractError::CollateralAlreadyRegistered { collateral_id });
+    }
+
+    // CW20 collateral is identified by the address of its token contract
+    if collateral_info.asset_type == CollateralAssetType::Cw20 {
+        let cw20_contract_address = deps
+            .api
+            .addr_validate(&collateral_id)
+            .map_err(|_| ContractError::InvalidCW20ContractAddress {})?;
+
+        if !helper_is_cw20_contract(deps.as_ref(), &cw20_contract_address) {
+            return Err(ContractError::InvalidCW20ContractAddress {});
+        }
+    }
+
+    helper_validate_collateral_info(&collateral_info)?;
+
+    COLLATERALS.save(deps.storage, &collateral_id, &collateral_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_collateral")
+        .add_attribute("sender", info.sender)
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute(
+            "liquidation_health",
+            collateral_info.liquidation_health.to_string(),
+        )
+        .add_attribute(
+            "mintable_health",
+            collateral_info.mintable_health.to_string(),
+        ))
+}
+
+// Function to set collateral prices in dirham
+// Prices moving too far from the last accepted price within the deviation window are rejected,
+// and with a price delay configured the new price waits as the next price before it is used
+fn execute_set_collateral_price_in_dirham(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collateral_id: String,
+    collateral_price_in_dirham: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::PriceFeeder)?;
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.price_updates {
+        return Err(ContractError::Paused {
+            operation: "price_updates".to_string(),
+        });
+    }
+
+    if collateral_price_in_dirham.is_zero() {
+        return Err(ContractError::PriceCannotBeZero {});
+    }
+
+    let mut collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+    let price_guard_config = CONFIG.load(deps.storage)?.price_guard_config;
+
+    // A next price that has waited out the delay becomes the current price
+    let next_price = NEXT_COLLATERAL_PRICES.may_load(deps.storage, &collateral_id)?;
+
+    if let Some(next_price) = &next_price {
+        if next_price.effective_at <= env.block.time {
+            collateral_info.collateral_price_in_dirham = Some(next_price.price);
+            NEXT_COLLATERAL_PRICES.remove(deps.storage, &collateral_id);
         }
     }
 
+    let pending_next_price_at = next_price
+        .as_ref()
+        .filter(|next_price| next_price.effective_at > env.block.time)
+        .map(|next_price| next_price.effective_at);
+
+    let last_accepted_price = next_price
+        .filter(|next_price| next_price.effective_at > env.block.time)
+        .map(|next_price| next_price.price)
+        .or(collateral_info.collateral_price_in_dirham);
+
+    if let (Some(last_accepted_price), Some(accepted_at)) = (
+        last_accepted_price,
+        COLLATERAL_PRICE_ACCEPTED_AT.may_load(deps.storage, &collateral_id)?,
+    ) {
+        helper_check_price_deviation(
+            &price_guard_config,
+            env.block.time,
+            last_accepted_price,
+            accepted_at,
+            collateral_price_in_dirham,
+        )?;
+    }
+
+    // A next price replaced before it took effect was never used
+    if let Some(pending_next_price_at) = pending_next_price_at {
+        helper_forget_collateral_price(deps.storage, &collateral_id, pending_next_price_at)?;
+    }
+
+    // The very first price is used at once, as there is nothing to protect yet
+    let price_delayed = price_guard_config.price_delay_seconds > 0
+        && collateral_info.collateral_price_in_dirham.is_some();
+
+    let effective_at = if price_delayed {
+        let effective_at = env
+            .block
+            .time
+            .plus_seconds(price_guard_config.price_delay_seconds);
+
+        NEXT_COLLATERAL_PRICES.save(
+            deps.storage,
+            &collateral_id,
+            &NextCollateralPrice {
+                price: collateral_price_in_dirham,
+                effective_at,
+            },
+        )?;
+
+        effective_at
+    } else {
+        NEXT_COLLATERAL_PRICES.remove(deps.storage, &collateral_id);
+        collateral_info.collateral_price_in_dirham = Some(collateral_price_in_dirham);
+
+        env.block.time
+    };
+
+    if matches!(collateral_info.price_source, PriceSource::Manual) {
+        helper_record_collateral_price(
+            deps.storage,
+            &collateral_id,
+            effective_at,
+            collateral_price_in_dirham,
+        )?;
+    }
+
+    COLLATERALS.save(deps.storage, &collateral_id, &collateral_info)?;
+    COLLATERAL_PRICE_ACCEPTED_AT.save(deps.storage, &collateral_id, &env.block.time)?;
+
     Ok(Response::new()
         .add_attribute("action", "set_collateral_price_in_dirham")
         .add_attribute("sender", info.sender)
+        .add_attribute("collateral_id", collateral_id)
         .add_attribute(
             "new_collateral_price",
             collateral_price_in_dirham.to_string(),
-        ))
+        )
+        .add_attribute("price_delayed", price_delayed.to_string()))
 }
 
-// Function to set liquidation health
-fn execute_set_liquidation_health(
+// Function to reject an admin set price that moves too far from the last accepted price
+// while still within the deviation window
+fn helper_check_price_deviation(
+    price_guard_config: &PriceGuardConfig,
+    now: Timestamp,
+    last_accepted_price: Decimal,
+    accepted_at: Timestamp,
+    price: Decimal,
+) -> Result<(), ContractError> {
+    let within_deviation_window = now.seconds().saturating_sub(accepted_at.seconds())
+        < price_guard_config.deviation_window_seconds;
+    let price_deviation = price
+        .abs_diff(last_accepted_price)
+        .checked_div(last_accepted_price)
+        .unwrap_or(Decimal::MAX);
+
+    if within_deviation_window && price_deviation > price_guard_config.max_price_deviation {
+        return Err(ContractError::PriceDeviationTooHigh {
+            max_price_deviation: price_guard_config.max_price_deviation,
+        });
+    }
+
+    Ok(())
+}
+
+// Function to set how far admin set prices can move at once and how long they are delayed
+fn execute_set_price_guard_config(
     deps: DepsMut,
     info: MessageInfo,
-    liquidation_health: Decimal,
+    price_guard_config: PriceGuardConfig,
 ) -> Result<Response, ContractError> {
-    let admins = ADMIN_ADDRESSES.load(deps.storage)?;
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+    helper_validate_price_guard_config(&price_guard_config)?;
+
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        config.price_guard_config = price_guard_config.clone();
+        Ok(config)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_price_guard_config")
+        .add_attribute("sender", info.sender)
+        .add_attribute(
+            "max_price_deviation",
+            price_guard_config.max_price_deviation.to_string(),
+        )
+        .add_attribute(
+            "deviation_window_seconds",
+            price_guard_config.deviation_window_seconds.to_string(),
+        )
+        .add_attribute(
+            "price_delay_seconds",
+            price_guard_config.price_delay_seconds.to_string(),
+        ))
+}
+
+// Function to switch a collateral type between the admin set price and an oracle contract
+fn execute_set_collateral_price_source(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collateral_id: String,
+    price_source: PriceSource,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+
+    let mut collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+    collateral_info.price_source = price_source;
+
+    let price_source_attribute = match &collateral_info.price_source {
+        PriceSource::Manual => "manual".to_string(),
+        PriceSource::Feeders => "feeders".to_string(),
+        PriceSource::Twap {
+            pair_address,
+            window_seconds,
+        } => {
+            deps.api
+                .addr_validate(pair_address.as_str())
+                .map_err(|_| ContractError::InvalidWalletAddress {})?;
+
+            if *window_seconds == 0 {
+                return Err(ContractError::InvalidTwapWindow {});
+            }
+
+            pair_address.to_string()
+        }
+        PriceSource::Oracle { oracle_address } => {
+            deps.api
+                .addr_validate(oracle_address.as_str())
+                .map_err(|_| ContractError::InvalidWalletAddress {})?;
+
+            oracle_address.to_string()
+        }
+    };
+
+    match &collateral_info.price_source {
+        PriceSource::Manual => {}
+
+        // A TWAP cannot price the collateral until a window has passed, so record the
+        // first observation right away, which also checks that the pair quotes it
+        PriceSource::Twap { pair_address, .. } => {
+            let cumulative_price = helper_query_cumulative_price(
+                deps.as_ref(),
+                &collateral_id,
+                &collateral_info,
+                pair_address,
+            )?;
+
+            TWAP_OBSERVATIONS.save(
+                deps.storage,
+                (&collateral_id, env.block.time.seconds()),
+                &cumulative_price,
+            )?;
+        }
+
+        // Make sure the oracle or the feeders can price the collateral before relying on them
+        _ => {
+            helper_collateral_price(deps.as_ref(), env.block.time, &collateral_id, &collateral_info)?;
+        }
+    }
+
+    COLLATERALS.save(deps.storage, &collateral_id, &collateral_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_collateral_price_source")
+        .add_attribute("sender", info.sender)
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute("price_source", price_source_attribute))
+}
+
+// Function to record the cumulative price of a collateral type priced by a TWAP, dropping the
+// observations that are too old to ever be used again
+fn execute_record_price_observation(
+    deps: DepsMut,
+    env: Env,
+    collateral_id: String,
+) -> Result<Response, ContractError> {
+    if helper_active_pause_flags(deps.storage, env.block.time)?.price_updates {
+        return Err(ContractError::Paused {
+            operation: "price_updates".to_string(),
+        });
+    }
+
+    let collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+
+    let (pair_address, window_seconds) = match &collateral_info.price_source {
+        PriceSource::Twap {
+            pair_address,
+            window_seconds,
+        } => (pair_address, *window_seconds),
+        _ => return Err(ContractError::NotTwapPriced { collateral_id }),
+    };
+
+    let cumulative_price =
+        helper_query_cumulative_price(deps.as_ref(), &collateral_id, &collateral_info, pair_address)?;
+
+    // The newest observation at least a window old has to stay around
+    let now = env.block.time.seconds();
+    let newest_usable_observation = TWAP_OBSERVATIONS
+        .prefix(&collateral_id)
+        .keys(
+            deps.storage,
+            None,
+            Some(Bound::inclusive(now.saturating_sub(window_seconds))),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?;
+
+    if let Some(newest_usable_observation) = newest_usable_observation {
+        let stale_observations = TWAP_OBSERVATIONS
+            .prefix(&collateral_id)
+            .keys(
+                deps.storage,
+                None,
+                Some(Bound::exclusive(newest_usable_observation)),
+                Order::Ascending,
+            )
+            .collect::<StdResult<Vec<u64>>>()?;
+
+        for observed_at in stale_observations {
+            TWAP_OBSERVATIONS.remove(deps.storage, (&collateral_id, observed_at));
+        }
+    }
+
+    TWAP_OBSERVATIONS.save(deps.storage, (&collateral_id, now), &cumulative_price)?;
+
+    if let Ok(twap) = helper_twap_price(
+        deps.as_ref(),
+        env.block.time,
+        &collateral_id,
+        &collateral_info,
+        pair_address,
+        window_seconds,
+    ) {
+        helper_record_collateral_price(deps.storage, &collateral_id, env.block.time, twap)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "record_price_observation")
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute("cumulative_price", cumulative_price.to_string()))
+}
+
+// Function for price feeders to submit the price of a collateral type in dirham
+fn execute_submit_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collateral_id: String,
+    price: Decimal,
+) -> Result<Response, ContractError> {
+    let price_feeders = PRICE_FEEDERS.load(deps.storage)?;
+
+    if !price_feeders.contains(&info.sender) {
+        return Err(ContractError::UnauthorizedPriceFeeder {});
+    }
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.price_updates {
+        return Err(ContractError::Paused {
+            operation: "price_updates".to_string(),
+        });
+    }
+
+    if price.is_zero() {
+        return Err(ContractError::PriceCannotBeZero {});
+    }
+
+    let collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+
+    FEEDER_PRICES.save(
+        deps.storage,
+        (&collateral_id, &info.sender),
+        &FeederPrice {
+            price,
+            submitted_at: env.block.time,
+        },
+    )?;
+
+    // Every submission can move the median the collateral is priced at
+    if matches!(collateral_info.price_source, PriceSource::Feeders) {
+        if let Ok(median_price) =
+            helper_median_feeder_price(deps.storage, env.block.time, &collateral_id)
+        {
+            helper_record_collateral_price(deps.storage, &collateral_id, env.block.time, median_price)?;
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "submit_price")
+        .add_attribute("sender", info.sender)
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute("price", price.to_string()))
+}
+
+// Function to let a wallet submit collateral prices
+fn execute_add_price_feeder(
+    deps: DepsMut,
+    info: MessageInfo,
+    feeder_address: Addr,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+
+    let feeder_address = deps
+        .api
+        .addr_validate(feeder_address.as_str())
+        .map_err(|_| ContractError::InvalidWalletAddress {})?;
+
+    let mut price_feeders = PRICE_FEEDERS.load(deps.storage)?;
+
+    if price_feeders.contains(&feeder_address) {
+        return Err(ContractError::PriceFeederAlreadyAdded { feeder_address });
+    }
+
+    price_feeders.push(feeder_address.clone());
+    PRICE_FEEDERS.save(deps.storage, &price_feeders)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_price_feeder")
+        .add_attribute("sender", info.sender)
+        .add_attribute("feeder_address", feeder_address))
+}
+
+// Function to stop a wallet from submitting collateral prices. Prices it already
+// submitted are no longer used
+fn execute_remove_price_feeder(
+    deps: DepsMut,
+    info: MessageInfo,
+    feeder_address: Addr,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+
+    let mut price_feeders = PRICE_FEEDERS.load(deps.storage)?;
+
+    if !price_feeders.contains(&feeder_address) {
+        return Err(ContractError::UnknownPriceFeeder { feeder_address });
+    }
+
+    price_feeders.retain(|price_feeder| *price_feeder != feeder_address);
+    PRICE_FEEDERS.save(deps.storage, &price_feeders)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_price_feeder")
+        .add_attribute("sender", info.sender)
+        .add_attribute("feeder_address", feeder_address))
+}
+
+// Function to set how many fresh feeder prices are needed and how long they stay fresh
+fn execute_set_price_feed_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    price_feed_config: PriceFeedConfig,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+    helper_validate_price_feed_config(&price_feed_config)?;
+
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        config.price_feed_config = price_feed_config.clone();
+        Ok(config)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_price_feed_config")
+        .add_attribute("sender", info.sender)
+        .add_attribute("quorum", price_feed_config.quorum.to_string())
+        .add_attribute(
+            "max_price_age_seconds",
+            price_feed_config.max_price_age_seconds.to_string(),
+        ))
+}
+
+// Function to set liquidation health
+fn execute_set_liquidation_health(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collateral_id: String,
+    liquidation_health: Decimal,
+) -> Result<Response, ContractError> {
+    helper_queue_parameter_change(
+        deps,
+        env,
+        info,
+        ParameterChange::LiquidationHealth {
+            collateral_id,
+            liquidation_health,
+        },
+    )
+}
+
+// Function to set mintable health
+fn execute_set_mintable_health(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collateral_id: String,
+    mintable_health: Decimal,
+) -> Result<Response, ContractError> {
+    helper_queue_parameter_change(
+        deps,
+        env,
+        info,
+        ParameterChange::MintableHealth {
+            collateral_id,
+            mintable_health,
+        },
+    )
+}
+
+// Function to set how long parameter changes wait in the queue. Changing it goes through
+// the queue as well, so it cannot be used to skip the delay
+fn execute_set_timelock_delay(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    timelock_delay_seconds: u64,
+) -> Result<Response, ContractError> {
+    helper_queue_parameter_change(
+        deps,
+        env,
+        info,
+        ParameterChange::TimelockDelay {
+            timelock_delay_seconds,
+        },
+    )
+}
+
+// Function to get the role needed to queue or cancel a parameter change
+fn helper_parameter_change_role(change: &ParameterChange) -> Role {
+    match change {
+        ParameterChange::LiquidationHealth { .. }
+        | ParameterChange::MintableHealth { .. }
+        | ParameterChange::CollateralHealth { .. } => Role::RiskManager,
+        ParameterChange::Cw20DiraContractAddress { .. } | ParameterChange::TimelockDelay { .. } => {
+            Role::Owner
+        }
+    }
+}
+
+// Function to check a parameter change against the current state of the contract. Changes are
+// checked when they are queued and again when they are applied
+fn helper_validate_parameter_change(
+    deps: Deps,
+    change: &ParameterChange,
+) -> Result<(), ContractError> {
+    match change {
+        ParameterChange::LiquidationHealth {
+            collateral_id,
+            liquidation_health,
+        } => {
+            let collateral_info = helper_load_collateral_info(deps.storage, collateral_id)?;
+
+            if liquidation_health.is_zero() {
+                return Err(ContractError::HealthCannotBeZero {});
+            }
+
+            if *liquidation_health > collateral_info.mintable_health {
+                return Err(ContractError::MintableHealthLowerThanLiquidationHealth {});
+            }
+        }
+
+        ParameterChange::MintableHealth {
+            collateral_id,
+            mintable_health,
+        } => {
+            let collateral_info = helper_load_collateral_info(deps.storage, collateral_id)?;
+
+            if *mintable_health < collateral_info.liquidation_health {
+                return Err(ContractError::MintableHealthLowerThanLiquidationHealth {});
+            }
+        }
+
+        ParameterChange::CollateralHealth {
+            collateral_id,
+            liquidation_health,
+            mintable_health,
+        } => {
+            let collateral_info = helper_load_collateral_info(deps.storage, collateral_id)?;
+            let liquidation_health = liquidation_health.unwrap_or(collateral_info.liquidation_health);
+            let mintable_health = mintable_health.unwrap_or(collateral_info.mintable_health);
+
+            if liquidation_health.is_zero() || mintable_health.is_zero() {
+                return Err(ContractError::HealthCannotBeZero {});
+            }
+
+            if mintable_health < liquidation_health {
+                return Err(ContractError::MintableHealthLowerThanLiquidationHealth {});
+            }
+        }
+
+        ParameterChange::Cw20DiraContractAddress {
+            cw20_dira_contract_address,
+        } => {
+            if !helper_is_cw20_contract(deps, cw20_dira_contract_address) {
+                return Err(ContractError::InvalidCW20ContractAddress {});
+            }
+        }
+
+        ParameterChange::TimelockDelay {
+            timelock_delay_seconds,
+        } => {
+            if !(MIN_TIMELOCK_DELAY_SECONDS..=MAX_TIMELOCK_DELAY_SECONDS).contains(timelock_delay_seconds) {
+                return Err(ContractError::InvalidTimelockDelay {
+                    min_timelock_delay_seconds: MIN_TIMELOCK_DELAY_SECONDS,
+                    max_timelock_delay_seconds: MAX_TIMELOCK_DELAY_SECONDS,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Function to apply a parameter change once it has been checked
+fn helper_apply_parameter_change(
+    deps: DepsMut,
+    change: &ParameterChange,
+) -> Result<(), ContractError> {
+    helper_validate_parameter_change(deps.as_ref(), change)?;
+
+    match change {
+        ParameterChange::LiquidationHealth {
+            collateral_id,
+            liquidation_health,
+        } => {
+            let mut collateral_info = helper_load_collateral_info(deps.storage, collateral_id)?;
+            collateral_info.liquidation_health = *liquidation_health;
+            COLLATERALS.save(deps.storage, collateral_id, &collateral_info)?;
+        }
+
+        ParameterChange::MintableHealth {
+            collateral_id,
+            mintable_health,
+        } => {
+            let mut collateral_info = helper_load_collateral_info(deps.storage, collateral_id)?;
+            collateral_info.mintable_health = *mintable_health;
+            COLLATERALS.save(deps.storage, collateral_id, &collateral_info)?;
+        }
+
+        ParameterChange::CollateralHealth {
+            collateral_id,
+            liquidation_health,
+            mintable_health,
+        } => {
+            let mut collateral_info = helper_load_collateral_info(deps.storage, collateral_id)?;
+            collateral_info.liquidation_health =
+                liquidation_health.unwrap_or(collateral_info.liquidation_health);
+            collateral_info.mintable_health = mintable_health.unwrap_or(collateral_info.mintable_health);
+            COLLATERALS.save(deps.storage, collateral_id, &collateral_info)?;
+        }
+
+        ParameterChange::Cw20DiraContractAddress {
+            cw20_dira_contract_address,
+        } => {
+            CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+                config.cw20_dira_contract_address = Some(cw20_dira_contract_address.clone());
+                Ok(config)
+            })?;
+        }
+
+        ParameterChange::TimelockDelay {
+            timelock_delay_seconds,
+        } => {
+            CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+                config.timelock_delay_seconds = *timelock_delay_seconds;
+                Ok(config)
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+// Function to queue a parameter change, to be applied once the timelock delay has passed
+fn helper_queue_parameter_change(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    change: ParameterChange,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, helper_parameter_change_role(&change))?;
+    helper_validate_parameter_change(deps.as_ref(), &change)?;
+
+    let (id, eta) = helper_enqueue_parameter_change(deps.storage, &env, &info.sender, change)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "queue_parameter_change")
+        .add_attribute("sender", info.sender)
+        .add_attribute("id", id.to_string())
+        .add_attribute("eta", eta.to_string()))
+}
+
+// Function to add a parameter change that has already been checked to the queue. Returns its
+// id and the time it can be applied at
+fn helper_enqueue_parameter_change(
+    storage: &mut dyn Storage,
+    env: &Env,
+    proposer: &Addr,
+    change: ParameterChange,
+) -> Result<(u64, Timestamp), ContractError> {
+    let timelock_delay_seconds = CONFIG.load(storage)?.timelock_delay_seconds;
+    let eta = env.block.time.plus_seconds(timelock_delay_seconds);
+
+    let id = QUEUED_CHANGE_COUNT.update(storage, |queued_change_count| -> StdResult<u64> {
+        Ok(queued_change_count + 1)
+    })?;
+
+    QUEUED_CHANGES.save(
+        storage,
+        id,
+        &QueuedChange {
+            change,
+            proposer: proposer.clone(),
+            eta,
+        },
+    )?;
+
+    Ok((id, eta))
+}
+
+// Function to apply a queued parameter change once its timelock delay has passed. Anyone can
+// apply it, until its grace period runs out
+fn execute_execute_queued(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let queued_change = QUEUED_CHANGES
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::UnknownQueuedChange { id })?;
+
+    if env.block.time < queued_change.eta {
+        return Err(ContractError::QueuedChangeNotReady {
+            id,
+            eta: queued_change.eta,
+        });
+    }
+
+    let expires_at = queued_change.eta.plus_seconds(TIMELOCK_GRACE_PERIOD_SECONDS);
+
+    if env.block.time >= expires_at {
+        return Err(ContractError::QueuedChangeExpired { id, expires_at });
+    }
+
+    QUEUED_CHANGES.remove(deps.storage, id);
+    helper_apply_parameter_change(deps, &queued_change.change)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "execute_queued_parameter_change")
+        .add_attribute("sender", info.sender)
+        .add_attribute("id", id.to_string()))
+}
+
+// Function to cancel a queued parameter change before it is applied
+fn execute_cancel_queued(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let queued_change = QUEUED_CHANGES
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::UnknownQueuedChange { id })?;
+
+    helper_check_role(
+        deps.storage,
+        &info.sender,
+        helper_parameter_change_role(&queued_change.change),
+    )?;
+
+    QUEUED_CHANGES.remove(deps.storage, id);
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_queued_parameter_change")
+        .add_attribute("sender", info.sender)
+        .add_attribute("id", id.to_string()))
+}
+
+// Function to set the debt ceiling of a collateral type
+fn execute_set_debt_ceiling(
+    deps: DepsMut,
+    info: MessageInfo,
+    collateral_id: String,
+    debt_ceiling: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+
+    let mut collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+    collateral_info.debt_ceiling = debt_ceiling;
+    COLLATERALS.save(deps.storage, &collateral_id, &collateral_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_debt_ceiling")
+        .add_attribute("sender", info.sender)
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute(
+            "new_debt_ceiling",
+            debt_ceiling.map_or("none".to_string(), |ceiling| ceiling.to_string()),
+        ))
+}
+
+// Function to change the yearly stability fee of a collateral type. The fee accrued so far
+// is charged at the old rate before the new one applies
+fn execute_set_stability_fee(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collateral_id: String,
+    stability_fee: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::FeeManager)?;
+
+    helper_accrue_stability_fee(deps.storage, &collateral_id, env.block.time)?;
+
+    let mut collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+    collateral_info.stability_fee = stability_fee;
+    COLLATERALS.save(deps.storage, &collateral_id, &collateral_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_stability_fee")
+        .add_attribute("sender", info.sender)
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute("new_stability_fee", stability_fee.to_string()))
+}
+
+// Function to change the yearly savings rate. Interest earned so far is paid at the old rate first
+fn execute_set_savings_rate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    savings_rate: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::FeeManager)?;
+
+    let (_, pay_savings_interest_message) =
+        helper_accrue_savings(deps.storage, env.block.time, &env.contract.address)?;
+
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        config.savings_rate = savings_rate;
+        Ok(config)
+    })?;
+
+    Ok(Response::new()
+        .add_messages(pay_savings_interest_message)
+        .add_attribute("action", "set_savings_rate")
+        .add_attribute("sender", info.sender)
+        .add_attribute("new_savings_rate", savings_rate.to_string()))
+}
+
+// Function to set the share of a position's debt that a single liquidation can repay
+fn execute_set_close_factor(
+    deps: DepsMut,
+    info: MessageInfo,
+    close_factor: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+    helper_validate_close_factor(close_factor)?;
+
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        config.close_factor = close_factor;
+        Ok(config)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_close_factor")
+        .add_attribute("sender", info.sender)
+        .add_attribute("new_close_factor", close_factor.to_string()))
+}
+
+// Function to set the bonus collateral a liquidator seizes on top of the repaid debt
+fn execute_set_liquidation_bonus(
+    deps: DepsMut,
+    info: MessageInfo,
+    liquidation_bonus: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+    helper_validate_liquidation_bonus(liquidation_bonus)?;
+
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        config.liquidation_bonus = liquidation_bonus;
+        Ok(config)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_liquidation_bonus")
+        .add_attribute("sender", info.sender)
+        .add_attribute("new_liquidation_bonus", liquidation_bonus.to_string()))
+}
+
+// Function to set the share of the liquidation bonus that is paid to the treasury
+fn execute_set_liquidation_protocol_share(
+    deps: DepsMut,
+    info: MessageInfo,
+    liquidation_protocol_share: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::FeeManager)?;
+    helper_validate_liquidation_protocol_share(liquidation_protocol_share)?;
+
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        config.liquidation_protocol_share = liquidation_protocol_share;
+        Ok(config)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_liquidation_protocol_share")
+        .add_attribute("sender", info.sender)
+        .add_attribute(
+            "new_liquidation_protocol_share",
+            liquidation_protocol_share.to_string(),
+        ))
+}
+
+fn execute_set_auction_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    auction_config: AuctionConfig,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+    helper_validate_auction_config(&auction_config)?;
+
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        config.auction_config = auction_config.clone();
+        Ok(config)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_auction_config")
+        .add_attribute("sender", info.sender)
+        .add_attribute(
+            "new_starting_premium",
+            auction_config.starting_premium.to_string(),
+        )
+        .add_attribute(
+            "new_price_decay_seconds",
+            auction_config.price_decay_seconds.to_string(),
+        )
+        .add_attribute(
+            "new_reset_after_seconds",
+            auction_config.reset_after_seconds.to_string(),
+        ))
+}
+
+// Function to set the CW20 DIRA contract address. Once it has been set, changing it goes
+// through the parameter change queue
+fn execute_set_cw20_dira_contact_address(
+    deps: DepsMut,
+    env: Env,
+    cw20_dira_contract_address: Addr,
+    info:MessageInfo,
+) -> Result<Response, ContractError> {
+    let change = ParameterChange::Cw20DiraContractAddress {
+        cw20_dira_contract_address: cw20_dira_contract_address.clone(),
+    };
+
+    if CONFIG.load(deps.storage)?.cw20_dira_contract_address.is_some() {
+        return helper_queue_parameter_change(deps, env, info, change);
+    }
+
+    helper_check_role(deps.storage, &info.sender, Role::Owner)?;
+    helper_apply_parameter_change(deps, &change)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_cw20_dira_contract_address")
+        .add_attribute("contract_address", cw20_dira_contract_address.into_string()))
+}
+
+fn execute_enable_fee_switch_state(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::FeeManager)?;
+
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        config.fee_switch.enabled = true;
+        Ok(config)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "enable_fee")
+        .add_attribute("fee_enabled", "true"))
+}
+
+
+fn execute_disable_fee_switch_state(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::FeeManager)?;
+
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        config.fee_switch.enabled = false;
+        Ok(config)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "disable_fee")
+        .add_attribute("fee_enabled", "false"))
+}
+
+// Function to check a price guard config
+fn helper_validate_price_guard_config(price_guard_config: &PriceGuardConfig) -> Result<(), ContractError> {
+    if price_guard_config.max_price_deviation.is_zero() {
+        return Err(ContractError::InvalidPriceGuardConfig {});
+    }
+
+    Ok(())
+}
+
+// Function to check a price feed config
+fn helper_validate_price_feed_config(price_feed_config: &PriceFeedConfig) -> Result<(), ContractError> {
+    if price_feed_config.quorum == 0 || price_feed_config.max_price_age_seconds == 0 {
+        return Err(ContractError::InvalidPriceFeedConfig {});
+    }
+
+    Ok(())
+}
+
+// Function to check a close factor
+fn helper_validate_close_factor(close_factor: Decimal) -> Result<(), ContractError> {
+    if close_factor.is_zero() || close_factor > Decimal::one() {
+        return Err(ContractError::InvalidCloseFactor {});
+    }
+
+    Ok(())
+}
+
+// Function to check a liquidation bonus
+fn helper_validate_liquidation_bonus(liquidation_bonus: Decimal) -> Result<(), ContractError> {
+    if liquidation_bonus >= Decimal::one() {
+        return Err(ContractError::InvalidLiquidationBonus {});
+    }
+
+    Ok(())
+}
+
+// Function to check a liquidation protocol share
+fn helper_validate_liquidation_protocol_share(liquidation_protocol_share: Decimal) -> Result<(), ContractError> {
+    if liquidation_protocol_share > Decimal::one() {
+        return Err(ContractError::InvalidLiquidationProtocolShare {});
+    }
+
+    Ok(())
+}
+
+// Function to check an auction config. Auctions start above the collateral price and are
+// reset before their price reaches zero
+fn helper_validate_auction_config(auction_config: &AuctionConfig) -> Result<(), ContractError> {
+    if auction_config.starting_premium < Decimal::one()
+        || auction_config.reset_after_seconds == 0
+        || auction_config.reset_after_seconds >= auction_config.price_decay_seconds
+    {
+        return Err(ContractError::InvalidAuctionConfig {});
+    }
+
+    Ok(())
+}
+
+// Function to change any subset of the contract's parameters at once. The sender needs the role
+// of every parameter changed, and every parameter is checked before any of them is applied.
+// Health, timelock delay and CW20 DIRA contract changes are queued like their own setters, with
+// a combined health change checked against both healths
+fn execute_update_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    config_update: Box<ConfigUpdate>,
+) -> Result<Response, ContractError> {
+    if *config_update == ConfigUpdate::default() {
+        return Err(ContractError::EmptyConfigUpdate {});
+    }
+
+    let mut parameter_changes = vec![];
+
+    if let Some(collateral_health) = config_update.collateral_health {
+        parameter_changes.push(ParameterChange::CollateralHealth {
+            collateral_id: collateral_health.collateral_id,
+            liquidation_health: collateral_health.liquidation_health,
+            mintable_health: collateral_health.mintable_health,
+        });
+    }
+    if let Some(timelock_delay_seconds) = config_update.timelock_delay_seconds {
+        parameter_changes.push(ParameterChange::TimelockDelay {
+            timelock_delay_seconds,
+        });
+    }
+    if let Some(cw20_dira_contract_address) = config_update.cw20_dira_contract_address {
+        parameter_changes.push(ParameterChange::Cw20DiraContractAddress {
+            cw20_dira_contract_address,
+        });
+    }
+
+    if config_update.close_factor.is_some()
+        || config_update.liquidation_bonus.is_some()
+        || config_update.auction_config.is_some()
+        || config_update.price_guard_config.is_some()
+        || config_update.price_feed_config.is_some()
+        || config_update.debt_ceiling.is_some()
+    {
+        helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+    }
+
+    if config_update.liquidation_protocol_share.is_some()
+        || config_update.fee_switch_enabled.is_some()
+        || config_update.savings_rate.is_some()
+        || config_update.stability_fee.is_some()
+    {
+        helper_check_role(deps.storage, &info.sender, Role::FeeManager)?;
+    }
+
+    for change in parameter_changes.iter() {
+        helper_check_role(deps.storage, &info.sender, helper_parameter_change_role(change))?;
+        helper_validate_parameter_change(deps.as_ref(), change)?;
+    }
+    if let Some(debt_ceiling) = &config_update.debt_ceiling {
+        helper_load_collateral_info(deps.storage, &debt_ceiling.collateral_id)?;
+    }
+    if let Some(stability_fee) = &config_update.stability_fee {
+        helper_load_collateral_info(deps.storage, &stability_fee.collateral_id)?;
+    }
+    if let Some(close_factor) = config_update.close_factor {
+        helper_validate_close_factor(close_factor)?;
+    }
+    if let Some(liquidation_bonus) = config_update.liquidation_bonus {
+        helper_validate_liquidation_bonus(liquidation_bonus)?;
+    }
+    if let Some(liquidation_protocol_share) = config_update.liquidation_protocol_share {
+        helper_validate_liquidation_protocol_share(liquidation_protocol_share)?;
+    }
+    if let Some(auction_config) = &config_update.auction_config {
+        helper_validate_auction_config(auction_config)?;
+    }
+    if let Some(price_guard_config) = &config_update.price_guard_config {
+        helper_validate_price_guard_config(price_guard_config)?;
+    }
+    if let Some(price_feed_config) = &config_update.price_feed_config {
+        helper_validate_price_feed_config(price_feed_config)?;
+    }
+
+    let mut response = Response::new()
+        .add_attribute("action", "update_config")
+        .add_attribute("sender", info.sender.clone());
+
+    // Interest earned so far is paid at the old savings rate first
+    if config_update.savings_rate.is_some() {
+        let (_, pay_savings_interest_message) =
+            helper_accrue_savings(deps.storage, env.block.time, &env.contract.address)?;
+        response = response.add_messages(pay_savings_interest_message);
+    }
+
+    let mut config = CONFIG.load(deps.storage)?;
+
+    for change in parameter_changes {
+        // The CW20 DIRA contract is set at once while there is none yet, like SetCW20DiraContractAddress
+        if let ParameterChange::Cw20DiraContractAddress {
+            cw20_dira_contract_address,
+        } = &change
+        {
+            if config.cw20_dira_contract_address.is_none() {
+                config.cw20_dira_contract_address = Some(cw20_dira_contract_address.clone());
+                response = response.add_attribute(
+                    "contract_address",
+                    cw20_dira_contract_address.to_string(),
+                );
+                continue;
+            }
+        }
+
+        let (id, eta) = helper_enqueue_parameter_change(deps.storage, &env, &info.sender, change)?;
+        response = response
+            .add_attribute("queued_change_id", id.to_string())
+            .add_attribute("eta", eta.to_string());
+    }
+
+    if let Some(debt_ceiling) = config_update.debt_ceiling {
+        let mut collateral_info = helper_load_collateral_info(deps.storage, &debt_ceiling.collateral_id)?;
+        collateral_info.debt_ceiling = debt_ceiling.debt_ceiling;
+        COLLATERALS.save(deps.storage, &debt_ceiling.collateral_id, &collateral_info)?;
+        response = response.add_attribute(
+            "new_debt_ceiling",
+            debt_ceiling
+                .debt_ceiling
+                .map_or("none".to_string(), |ceiling| ceiling.to_string()),
+        );
+    }
+
+    // The fee accrued so far is charged at the old stability fee first
+    if let Some(stability_fee) = config_update.stability_fee {
+        helper_accrue_stability_fee(deps.storage, &stability_fee.collateral_id, env.block.time)?;
+
+        let mut collateral_info = helper_load_collateral_info(deps.storage, &stability_fee.collateral_id)?;
+        collateral_info.stability_fee = stability_fee.stability_fee;
+        COLLATERALS.save(deps.storage, &stability_fee.collateral_id, &collateral_info)?;
+        response = response.add_attribute(
+            "new_stability_fee",
+            stability_fee.stability_fee.to_string(),
+        );
+    }
+
+    if let Some(close_factor) = config_update.close_factor {
+        config.close_factor = close_factor;
+        response = response.add_attribute("new_close_factor", close_factor.to_string());
+    }
+
+    if let Some(liquidation_bonus) = config_update.liquidation_bonus {
+        config.liquidation_bonus = liquidation_bonus;
+        response = response.add_attribute("new_liquidation_bonus", liquidation_bonus.to_string());
+    }
+
+    if let Some(liquidation_protocol_share) = config_update.liquidation_protocol_share {
+        config.liquidation_protocol_share = liquidation_protocol_share;
+        response = response.add_attribute(
+            "new_liquidation_protocol_share",
+            liquidation_protocol_share.to_string(),
+        );
+    }
+
+    if let Some(fee_switch_enabled) = config_update.fee_switch_enabled {
+        config.fee_switch.enabled = fee_switch_enabled;
+        response = response.add_attribute("fee_enabled", fee_switch_enabled.to_string());
+    }
+
+    if let Some(savings_rate) = config_update.savings_rate {
+        config.savings_rate = savings_rate;
+        response = response.add_attribute("new_savings_rate", savings_rate.to_string());
+    }
+
+    if let Some(auction_config) = config_update.auction_config {
+        config.auction_config = auction_config;
+        response = response.add_attribute("new_auction_config", "true");
+    }
+
+    if let Some(price_guard_config) = config_update.price_guard_config {
+        config.price_guard_config = price_guard_config;
+        response = response.add_attribute("new_price_guard_config", "true");
+    }
+
+    if let Some(price_feed_config) = config_update.price_feed_config {
+        config.price_feed_config = price_feed_config;
+        response = response.add_attribute("new_price_feed_config", "true");
+    }
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(response)
+}
+
+// Function to accrue the stability fees of every collateral type, pay the savings
+// interest out of them, write off bad debt and mint whatever is left to the treasury
+fn execute_drip(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    helper_check_not_shut_down(deps.storage)?;
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.minting {
+        return Err(ContractError::Paused {
+            operation: "minting".to_string(),
+        });
+    }
+
+    // Savers are paid their interest out of the fees before the rest goes to the treasury
+    let (_, pay_savings_interest_message) =
+        helper_accrue_savings(deps.storage, env.block.time, &env.contract.address)?;
+
+    let unminted_stability_fees = UNMINTED_STABILITY_FEES
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    // The fees first cover the DIRA auctions left in circulation without backing
+    let bad_debt = BAD_DEBT.may_load(deps.storage)?.unwrap_or_default();
+    let bad_debt_written_off = bad_debt.min(unminted_stability_fees);
+    let unminted_stability_fees = unminted_stability_fees - bad_debt_written_off;
+
+    BAD_DEBT.save(deps.storage, &(bad_debt - bad_debt_written_off))?;
+
+    // Only whole CW20 units can be minted, anything smaller waits for the next drip
+    let dira_to_mint = helper_decimal_to_amount(unminted_stability_fees, DIRA_DECIMALS);
+    let stability_fees_minted = helper_amount_to_decimal(dira_to_mint, DIRA_DECIMALS)?;
+
+    UNMINTED_STABILITY_FEES.save(
+        deps.storage,
+        &(unminted_stability_fees - stability_fees_minted),
+    )?;
+
+    let mut response = Response::new().add_messages(pay_savings_interest_message);
+
+    if !dira_to_mint.is_zero() {
+        let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
+        let treasury_address = helper_load_treasury_address(deps.storage)?;
+
+        response = response.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Mint {
+                recipient: treasury_address.to_string(),
+                amount: dira_to_mint,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    Ok(response
+        .add_attribute("action", "drip")
+        .add_attribute("bad_debt_written_off", bad_debt_written_off.to_string())
+        .add_attribute("stability_fees_minted", stability_fees_minted.to_string()))
+}
+
+/****
+ * STABILITY POOL
+ ****/
+
+// Function to compute how much of a stability pool deposit is left after the
+// liquidations absorbed by the pool since it was made
+fn helper_compounded_stability_pool_deposit(
+    stability_pool: &StabilityPoolState,
+    deposit: &StabilityPoolDeposit,
+) -> Result<Decimal, ContractError> {
+    // A liquidation that emptied the pool since this deposit was made wiped it out
+    if deposit.initial_deposit.is_zero() || deposit.epoch < stability_pool.epoch {
+        return Ok(Decimal::zero());
+    }
+
+    let compounded_deposit = match stability_pool.scale - deposit.scale {
+        0 => Decimal256::from(deposit.initial_deposit) * stability_pool.product / deposit.product,
+        1 => {
+            Decimal256::from(deposit.initial_deposit) * stability_pool.product
+                / deposit.product
+                / Decimal256::from_ratio(STABILITY_POOL_SCALE_FACTOR, 1u128)
+        }
+        // Anything that has been scaled down twice is smaller than dust
+        _ => Decimal256::zero(),
+    };
+
+    Ok(Decimal::try_from(compounded_deposit)?)
+}
+
+// Function to compute the collateral of a single type gained by a stability pool
+// deposit since it was last updated
+fn helper_stability_pool_collateral_gain(
+    storage: &dyn Storage,
+    depositor: &Addr,
+    deposit: &StabilityPoolDeposit,
+    collateral_id: &str,
+) -> Result<Decimal, ContractError> {
+    if deposit.initial_deposit.is_zero() {
+        return Ok(Decimal::zero());
+    }
+
+    let sum_snapshot = STABILITY_POOL_SUM_SNAPSHOTS
+        .may_load(storage, (depositor, collateral_id))?
+        .unwrap_or_default();
+
+    // Gains made in the deposit's own scale, plus the ones made in the scale right after it
+    let first_portion = STABILITY_POOL_SUMS
+        .may_load(storage, (deposit.epoch, deposit.scale, collateral_id))?
+        .unwrap_or_default()
+        .saturating_sub(sum_snapshot);
+
+    let second_portion = STABILITY_POOL_SUMS
+        .may_load(storage, (deposit.epoch, deposit.scale + 1, collateral_id))?
+        .unwrap_or_default()
+        / Decimal256::from_ratio(STABILITY_POOL_SCALE_FACTOR, 1u128);
+
+    let collateral_gain = Decimal256::from(deposit.initial_deposit)
+        * (first_portion + second_portion)
+        / deposit.product;
+
+    Ok(Decimal::try_from(collateral_gain)?)
+}
+
+// Function to collect every collateral gain of a depositor, as a list of
+// (collateral id, collateral info, amount)
+fn helper_stability_pool_collateral_gains(
+    storage: &dyn Storage,
+    depositor: &Addr,
+    deposit: &StabilityPoolDeposit,
+) -> Result<Vec<(String, CollateralInfo, Decimal)>, ContractError> {
+    let mut collateral_gains = vec![];
+
+    for item in COLLATERALS.range(storage, None, None, Order::Ascending) {
+        let (collateral_id, collateral_info) = item?;
+        let collateral_gain =
+            helper_stability_pool_collateral_gain(storage, depositor, deposit, &collateral_id)?;
+
+        if !collateral_gain.is_zero() {
+            collateral_gains.push((collateral_id, collateral_info, collateral_gain));
+        }
+    }
+
+    Ok(collateral_gains)
+}
+
+// Function to pay out a depositor's collateral gains and replace their deposit with a new one,
+// snapshotting the pool so that the new deposit only earns from later liquidations
+fn helper_update_stability_pool_deposit(
+    storage: &mut dyn Storage,
+    depositor: &Addr,
+    stability_pool: &StabilityPoolState,
+    new_deposit: Decimal,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let mut messages = vec![];
+
+    if let Some(deposit) = STABILITY_POOL_DEPOSITS.may_load(storage, depositor)? {
+        for (collateral_id, collateral_info, collateral_gain) in
+            helper_stability_pool_collateral_gains(storage, depositor, &deposit)?
+        {
+            if !helper_decimal_to_amount(collateral_gain, collateral_info.decimals).is_zero() {
+                messages.push(helper_send_collateral_message(
+                    &collateral_id,
+                    &collateral_info,
+                    depositor,
+                    collateral_gain,
+                )?);
+            }
+        }
+    }
+
+    let collateral_ids = COLLATERALS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+
+    for collateral_id in collateral_ids {
+        if new_deposit.is_zero() {
+            STABILITY_POOL_SUM_SNAPSHOTS.remove(storage, (depositor, &collateral_id));
+            continue;
+        }
+
+        let current_sum = STABILITY_POOL_SUMS
+            .may_load(
+                storage,
+                (stability_pool.epoch, stability_pool.scale, &collateral_id),
+            )?
+            .unwrap_or_default();
+        STABILITY_POOL_SUM_SNAPSHOTS.save(storage, (depositor, &collateral_id), &current_sum)?;
+    }
+
+    if new_deposit.is_zero() {
+        STABILITY_POOL_DEPOSITS.remove(storage, depositor);
+    } else {
+        STABILITY_POOL_DEPOSITS.save(
+            storage,
+            depositor,
+            &StabilityPoolDeposit {
+                initial_deposit: new_deposit,
+                product: stability_pool.product,
+                epoch: stability_pool.epoch,
+                scale: stability_pool.scale,
+            },
+        )?;
+    }
+
+    Ok(messages)
+}
+
+// Function to absorb a liquidation with the stability pool. The repaid debt is taken out
+// of every deposit pro rata, and the seized collateral is shared out the same way
+fn helper_stability_pool_offset(
+    storage: &mut dyn Storage,
+    collateral_id: &str,
+    debt_to_offset: Decimal,
+    collateral_to_add: Decimal,
+) -> Result<(), ContractError> {
+    let mut stability_pool = STABILITY_POOL.load(storage)?;
+    let total_deposits = Decimal256::from(stability_pool.total_deposits);
+
+    let collateral_gain_per_unit_staked = Decimal256::from(collateral_to_add) / total_deposits;
+    let loss_per_unit_staked = Decimal256::from(debt_to_offset) / total_deposits;
+
+    STABILITY_POOL_SUMS.update(
+        storage,
+        (stability_pool.epoch, stability_pool.scale, collateral_id),
+        |current_sum| -> StdResult<Decimal256> {
+            Ok(current_sum.unwrap_or_default()
+                + collateral_gain_per_unit_staked * stability_pool.product)
+        },
+    )?;
+
+    let new_product_factor = Decimal256::one().saturating_sub(loss_per_unit_staked);
+
+    if new_product_factor.is_zero() {
+        // The pool was emptied, start a new epoch
+        stability_pool.epoch += 1;
+        stability_pool.scale = 0;
+        stability_pool.product = Decimal256::one();
+        stability_pool.total_deposits = Decimal::zero();
+    } else {
+        let scale_factor = Decimal256::from_ratio(STABILITY_POOL_SCALE_FACTOR, 1u128);
+        let mut new_product = stability_pool.product * new_product_factor;
+
+        // Scale P back up before it loses precision
+        if new_product < Decimal256::one() / scale_factor {
+            new_product *= scale_factor;
+            stability_pool.scale += 1;
+        }
+
+        stability_pool.product = new_product;
+        stability_pool.total_deposits -= debt_to_offset;
+    }
+
+    STABILITY_POOL.save(storage, &stability_pool)?;
+
+    Ok(())
+}
+
+// Function to deposit DIRA sent through the CW20 Receive hook into the stability pool
+fn execute_deposit_stability_pool(
+    deps: DepsMut,
+    depositor: Addr,
+    dira_received: Uint128,
+) -> Result<Response, ContractError> {
+    let dira_to_deposit = helper_amount_to_decimal(dira_received, DIRA_DECIMALS)?;
+
+    if dira_to_deposit.is_zero() {
+        return Err(ContractError::AmountCannotBeZero {});
+    }
+
+    let mut stability_pool = STABILITY_POOL.load(deps.storage)?;
+
+    let compounded_deposit = match STABILITY_POOL_DEPOSITS.may_load(deps.storage, &depositor)? {
+        Some(deposit) => helper_compounded_stability_pool_deposit(&stability_pool, &deposit)?,
+        None => Decimal::zero(),
+    };
+
+    let new_deposit = compounded_deposit + dira_to_deposit;
+    let pay_gains_messages =
+        helper_update_stability_pool_deposit(deps.storage, &depositor, &stability_pool, new_deposit)?;
+
+    stability_pool.total_deposits += dira_to_deposit;
+    STABILITY_POOL.save(deps.storage, &stability_pool)?;
+
+    Ok(Response::new()
+        .add_messages(pay_gains_messages)
+        .add_attribute("action", "deposit_stability_pool")
+        .add_attribute("sender", depositor)
+        .add_attribute("dira_deposited", dira_to_deposit.to_string())
+        .add_attribute("total_deposit_by_sender", new_deposit.to_string()))
+}
+
+// Function to withdraw DIRA from the stability pool, paying out collateral gains along the way
+fn execute_withdraw_from_stability_pool(
+    deps: DepsMut,
+    info: MessageInfo,
+    dira_to_withdraw: Decimal,
+) -> Result<Response, ContractError> {
+    let mut stability_pool = STABILITY_POOL.load(deps.storage)?;
+
+    let compounded_deposit = match STABILITY_POOL_DEPOSITS.may_load(deps.storage, &info.sender)? {
+        Some(deposit) => helper_compounded_stability_pool_deposit(&stability_pool, &deposit)?,
+        None => Decimal::zero(),
+    };
+
+    if dira_to_withdraw > compounded_deposit {
+        return Err(ContractError::StabilityPoolWithdrawTooHigh {
+            max_withdrawable: compounded_deposit,
+        });
+    }
+
+    let new_deposit = compounded_deposit - dira_to_withdraw;
+    let pay_gains_messages = helper_update_stability_pool_deposit(
+        deps.storage,
+        &info.sender,
+        &stability_pool,
+        new_deposit,
+    )?;
+
+    stability_pool.total_deposits = stability_pool.total_deposits.saturating_sub(dira_to_withdraw);
+    STABILITY_POOL.save(deps.storage, &stability_pool)?;
+
+    let mut response = Response::new().add_messages(pay_gains_messages);
+
+    let dira_to_transfer = helper_decimal_to_amount(dira_to_withdraw, DIRA_DECIMALS);
+
+    if !dira_to_transfer.is_zero() {
+        let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
+
+        response = response.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Transfer {
+                recipient: info.sender.to_string(),
+                amount: dira_to_transfer,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    Ok(response
+        .add_attribute("action", "withdraw_from_stability_pool")
+        .add_attribute("sender", info.sender)
+        .add_attribute("dira_withdrawn", dira_to_withdraw.to_string())
+        .add_attribute("total_deposit_by_sender", new_deposit.to_string()))
+}
+
+// Function to pay out the collateral a depositor has gained from liquidations
+fn execute_claim_stability_pool_gains(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let stability_pool = STABILITY_POOL.load(deps.storage)?;
+
+    let compounded_deposit = match STABILITY_POOL_DEPOSITS.may_load(deps.storage, &info.sender)? {
+        Some(deposit) => helper_compounded_stability_pool_deposit(&stability_pool, &deposit)?,
+        None => Decimal::zero(),
+    };
+
+    let pay_gains_messages = helper_update_stability_pool_deposit(
+        deps.storage,
+        &info.sender,
+        &stability_pool,
+        compounded_deposit,
+    )?;
+
+    Ok(Response::new()
+        .add_messages(pay_gains_messages)
+        .add_attribute("action", "claim_stability_pool_gains")
+        .add_attribute("sender", info.sender)
+        .add_attribute("total_deposit_by_sender", compounded_deposit.to_string()))
+}
+
+/****
+ * SAVINGS VAULT
+ ****/
+
+// Function to compute the savings vault at the given time, without saving it. The interest
+// paid to savers is taken out of the stability fees that have not been minted yet, so savers
+// can never be paid more than borrowers have paid. Returns the vault and the interest it earned
+fn helper_savings_vault(
+    storage: &dyn Storage,
+    now: Timestamp,
+    unminted_stability_fees: Decimal,
+) -> Result<(SavingsVault, Decimal), ContractError> {
+    let mut savings_vault = SAVINGS_VAULT.load(storage)?;
+    let savings_rate = CONFIG.load(storage)?.savings_rate;
+
+    let elapsed_seconds = now
+        .seconds()
+        .saturating_sub(savings_vault.last_updated.seconds());
+    savings_vault.last_updated = now;
+
+    if savings_vault.total_shares.is_zero() {
+        return Ok((savings_vault, Decimal::zero()));
+    }
+
+    let total_dira_saved = savings_vault.total_shares * savings_vault.share_price;
+    let interest_owed =
+        total_dira_saved * savings_rate * Decimal::from_ratio(elapsed_seconds, SECONDS_PER_YEAR);
+
+    // Only whole CW20 units can be minted to pay the interest
+    let interest_paid = helper_amount_to_decimal(
+        helper_decimal_to_amount(interest_owed.min(unminted_stability_fees), DIRA_DECIMALS),
+        DIRA_DECIMALS,
+    )?;
+
+    savings_vault.share_price += interest_paid / savings_vault.total_shares;
+
+    Ok((savings_vault, interest_paid))
+}
+
+// Function to bring the savings vault up to date, accruing the stability fees of every collateral
+// type first so that all of them can fund the interest. Returns the updated vault and the message
+// minting the interest to this contract, where it stays until savers withdraw it. No interest
+// is paid for the time minting is paused, nor after shutdown
+fn helper_accrue_savings(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    contract_address: &Addr,
+) -> Result<(SavingsVault, Option<CosmosMsg>), ContractError> {
+    let collateral_ids = COLLATERALS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+
+    for collateral_id in collateral_ids {
+        helper_accrue_stability_fee(storage, &collateral_id, now)?;
+    }
+
+    let unminted_stability_fees = UNMINTED_STABILITY_FEES
+        .may_load(storage)?
+        .unwrap_or_default();
+
+    let funds_for_interest = if helper_active_pause_flags(storage, now)?.minting
+        || SHUTDOWN.may_load(storage)?.is_some()
+    {
+        Decimal::zero()
+    } else {
+        unminted_stability_fees
+    };
+
+    let (savings_vault, interest_paid) = helper_savings_vault(storage, now, funds_for_interest)?;
+
+    SAVINGS_VAULT.save(storage, &savings_vault)?;
+
+    if interest_paid.is_zero() {
+        return Ok((savings_vault, None));
+    }
+
+    UNMINTED_STABILITY_FEES.save(storage, &(unminted_stability_fees - interest_paid))?;
+
+    let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(storage)?;
+
+    let mint_interest_message = cosmwasm_std::WasmMsg::Execute {
+        contract_addr: cw20_dira_contract_address.to_string(),
+        msg: to_json_binary(&cw20::Cw20ExecuteMsg::Mint {
+            recipient: contract_address.to_string(),
+            amount: helper_decimal_to_amount(interest_paid, DIRA_DECIMALS),
+        })?,
+        funds: vec![],
+    };
+
+    Ok((savings_vault, Some(mint_interest_message.into())))
+}
+
+// Function to deposit DIRA sent through the CW20 Receive hook into the savings vault,
+// in exchange for shares at the current share price
+fn execute_deposit_savings(
+    deps: DepsMut,
+    env: Env,
+    depositor: Addr,
+    dira_received: Uint128,
+) -> Result<Response, ContractError> {
+    let dira_to_deposit = helper_amount_to_decimal(dira_received, DIRA_DECIMALS)?;
+
+    if dira_to_deposit.is_zero() {
+        return Err(ContractError::AmountCannotBeZero {});
+    }
+
+    let (mut savings_vault, pay_savings_interest_message) =
+        helper_accrue_savings(deps.storage, env.block.time, &env.contract.address)?;
+
+    let shares_issued = dira_to_deposit / savings_vault.share_price;
+
+    let total_shares_by_depositor = SAVINGS_SHARES.update(
+        deps.storage,
+        &depositor,
+        |savings_shares| -> StdResult<Decimal> {
+            Ok(savings_shares.unwrap_or_default() + shares_issued)
+        },
+    )?;
+
+    savings_vault.total_shares += shares_issued;
+    SAVINGS_VAULT.save(deps.storage, &savings_vault)?;
+
+    Ok(Response::new()
+        .add_messages(pay_savings_interest_message)
+        .add_attribute("action", "deposit_savings")
+        .add_attribute("sender", depositor)
+        .add_attribute("dira_deposited", dira_to_deposit.to_string())
+        .add_attribute("shares_issued", shares_issued.to_string())
+        .add_attribute("total_shares_by_sender", total_shares_by_depositor.to_string()))
+}
+
+// Function to withdraw DIRA from the savings vault, burning shares at the current share price
+fn execute_withdraw_savings(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    dira_to_withdraw: Decimal,
+) -> Result<Response, ContractError> {
+    if dira_to_withdraw.is_zero() {
+        return Err(ContractError::AmountCannotBeZero {});
+    }
+
+    let (mut savings_vault, pay_savings_interest_message) =
+        helper_accrue_savings(deps.storage, env.block.time, &env.contract.address)?;
+
+    let savings_shares = SAVINGS_SHARES
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    let redeemable_dira = savings_shares * savings_vault.share_price;
+
+    if dira_to_withdraw > redeemable_dira {
+        return Err(ContractError::SavingsWithdrawTooHigh {
+            max_withdrawable: redeemable_dira,
+        });
+    }
+
+    // Withdrawing everything burns all the shares without leaving rounding dust behind
+    let shares_burned = if dira_to_withdraw == redeemable_dira {
+        savings_shares
+    } else {
+        (dira_to_withdraw / savings_vault.share_price).min(savings_shares)
+    };
+
+    let remaining_shares = savings_shares - shares_burned;
+
+    if remaining_shares.is_zero() {
+        SAVINGS_SHARES.remove(deps.storage, &info.sender);
+    } else {
+        SAVINGS_SHARES.save(deps.storage, &info.sender, &remaining_shares)?;
+    }
+
+    savings_vault.total_shares = savings_vault.total_shares.saturating_sub(shares_burned);
+    SAVINGS_VAULT.save(deps.storage, &savings_vault)?;
+
+    let mut response = Response::new().add_messages(pay_savings_interest_message);
+
+    let dira_to_transfer = helper_decimal_to_amount(dira_to_withdraw, DIRA_DECIMALS);
+
+    if !dira_to_transfer.is_zero() {
+        let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
+
+        response = response.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Transfer {
+                recipient: info.sender.to_string(),
+                amount: dira_to_transfer,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    Ok(response
+        .add_attribute("action", "withdraw_savings")
+        .add_attribute("sender", info.sender)
+        .add_attribute("dira_withdrawn", dira_to_withdraw.to_string())
+        .add_attribute("shares_burned", shares_burned.to_string())
+        .add_attribute("total_shares_by_sender", remaining_shares.to_string()))
+}
+
+/****
+ * PEG STABILITY MODULE
+ ****/
+
+// Function to load a stablecoin accepted by the peg stability module
+fn helper_load_psm_asset(storage: &dyn Storage, asset_id: &str) -> Result<PsmAsset, ContractError> {
+    PSM_ASSETS
+        .may_load(storage, asset_id)?
+        .ok_or_else(|| ContractError::UnknownPsmAsset {
+            asset_id: asset_id.to_string(),
+        })
+}
+
+// Function to check that a stablecoin has a price, sane swap fees and supported decimals
+fn helper_validate_psm_asset(psm_asset: &PsmAsset) -> Result<(), ContractError> {
+    if psm_asset.price_in_dirham.is_zero()
+        || psm_asset.fee_in > Decimal::one()
+        || psm_asset.fee_out > Decimal::one()
+        || psm_asset.decimals > Decimal::DECIMAL_PLACES
+    {
+        return Err(ContractError::InvalidPsmAsset {});
+    }
+
+    Ok(())
+}
+
+// Function to compute the DIRA paid out for swapping stablecoins in, along with the fee kept
+fn helper_simulate_swap_stablecoin_for_dira(
+    psm_asset: &PsmAsset,
+    stablecoin_amount: Decimal,
+) -> (Decimal, Decimal) {
+    let dira_value = stablecoin_amount * psm_asset.price_in_dirham;
+    let fee = dira_value * psm_asset.fee_in;
+
+    (dira_value - fee, fee)
+}
+
+// Function to compute the stablecoins paid out for swapping DIRA in, along with the fee kept.
+// The payout is rounded down to what the stablecoin can represent on chain
+fn helper_simulate_swap_dira_for_stablecoin(
+    psm_asset: &PsmAsset,
+    dira_amount: Decimal,
+) -> Result<(Decimal, Decimal), ContractError> {
+    let fee = dira_amount * psm_asset.fee_out;
+    let stablecoin_amount = helper_amount_to_decimal(
+        helper_decimal_to_amount(
+            (dira_amount - fee) / psm_asset.price_in_dirham,
+            psm_asset.decimals,
+        ),
+        psm_asset.decimals,
+    )?;
+
+    Ok((stablecoin_amount, fee))
+}
+
+// Function to swap stablecoins received by the contract for freshly minted DIRA
+fn execute_swap_stablecoin_for_dira(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    asset_id: String,
+    psm_asset: PsmAsset,
+    stablecoin_amount: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_not_shut_down(deps.storage)?;
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.minting {
+        return Err(ContractError::Paused {
+            operation: "minting".to_string(),
+        });
+    }
+
+    let reserves = PSM_RESERVES
+        .may_load(deps.storage, &asset_id)?
+        .unwrap_or_default()
+        + stablecoin_amount;
+
+    if let Some(reserve_cap) = psm_asset.reserve_cap {
+        if reserves > reserve_cap {
+            return Err(ContractError::PsmReserveCapExceeded { reserve_cap });
+        }
+    }
+
+    PSM_RESERVES.save(deps.storage, &asset_id, &reserves)?;
+
+    let (dira_to_mint, fee) = helper_simulate_swap_stablecoin_for_dira(&psm_asset, stablecoin_amount);
+    let dira_to_mint = helper_decimal_to_amount(dira_to_mint, DIRA_DECIMALS);
+    let fee_to_mint = helper_decimal_to_amount(fee, DIRA_DECIMALS);
+
+    if dira_to_mint.is_zero() {
+        return Err(ContractError::AmountCannotBeZero {});
+    }
+
+    let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
+
+    let mut response = Response::new().add_message(cosmwasm_std::WasmMsg::Execute {
+        contract_addr: cw20_dira_contract_address.to_string(),
+        msg: to_json_binary(&cw20::Cw20ExecuteMsg::Mint {
+            recipient: sender.to_string(),
+            amount: dira_to_mint,
+        })?,
+        funds: vec![],
+    });
+
+    if !fee_to_mint.is_zero() {
+        response = response.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Mint {
+                recipient: helper_load_treasury_address(deps.storage)?.to_string(),
+                amount: fee_to_mint,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    Ok(response
+        .add_attribute("action", "swap_stablecoin_for_dira")
+        .add_attribute("sender", sender)
+        .add_attribute("asset_id", asset_id)
+        .add_attribute("stablecoin_swapped", stablecoin_amount.to_string())
+        .add_attribute("dira_minted", dira_to_mint.to_string())
+        .add_attribute("fee_paid", fee_to_mint.to_string())
+        .add_attribute("total_reserves", reserves.to_string()))
+}
+
+// Function to swap DIRA sent through the CW20 Receive hook for stablecoins held by the module.
+// The DIRA covering the payout is burned and the fee goes to the treasury
+fn execute_swap_dira_for_stablecoin(
+    deps: DepsMut,
+    sender: Addr,
+    asset_id: String,
+    dira_received: Uint128,
+) -> Result<Response, ContractError> {
+    helper_check_not_shut_down(deps.storage)?;
+
+    let psm_asset = helper_load_psm_asset(deps.storage, &asset_id)?;
+    let dira_amount = helper_amount_to_decimal(dira_received, DIRA_DECIMALS)?;
+
+    let (stablecoin_amount, fee) = helper_simulate_swap_dira_for_stablecoin(&psm_asset, dira_amount)?;
+
+    if stablecoin_amount.is_zero() {
+        return Err(ContractError::AmountCannotBeZero {});
+    }
+
+    let reserves = PSM_RESERVES
+        .may_load(deps.storage, &asset_id)?
+        .unwrap_or_default();
+
+    if stablecoin_amount > reserves {
+        return Err(ContractError::InsufficientPsmReserves {
+            available: reserves,
+        });
+    }
+
+    let remaining_reserves = reserves - stablecoin_amount;
+    PSM_RESERVES.save(deps.storage, &asset_id, &remaining_reserves)?;
+
+    let fee_to_transfer = helper_decimal_to_amount(fee, DIRA_DECIMALS);
+    let dira_to_burn = dira_received - fee_to_transfer;
+
+    let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
+
+    let mut response = Response::new().add_message(helper_send_asset_message(
+        &asset_id,
+        &psm_asset.asset_type,
+        psm_asset.decimals,
+        &sender,
+        stablecoin_amount,
+    )?);
+
+    if !dira_to_burn.is_zero() {
+        response = response.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Burn {
+                amount: dira_to_burn,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    if !fee_to_transfer.is_zero() {
+        response = response.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Transfer {
+                recipient: helper_load_treasury_address(deps.storage)?.to_string(),
+                amount: fee_to_transfer,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    Ok(response
+        .add_attribute("action", "swap_dira_for_stablecoin")
+        .add_attribute("sender", sender)
+        .add_attribute("asset_id", asset_id)
+        .add_attribute("dira_burned", dira_to_burn.to_string())
+        .add_attribute("stablecoin_sent", stablecoin_amount.to_string())
+        .add_attribute("fee_paid", fee_to_transfer.to_string())
+        .add_attribute("total_reserves", remaining_reserves.to_string()))
+}
+
+// Function to whitelist a stablecoin for the peg stability module
+fn execute_register_psm_asset(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_id: String,
+    psm_asset: PsmAsset,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+
+    if asset_id.is_empty() {
+        return Err(ContractError::MissingCollateralTokenDenom {});
+    }
+
+    if PSM_ASSETS.has(deps.storage, &asset_id) {
+        return Err(ContractError::PsmAssetAlreadyRegistered { asset_id });
+    }
+
+    // CW20 stablecoins are identified by the address of their token contract
+    if psm_asset.asset_type == CollateralAssetType::Cw20 {
+        let cw20_contract_address = deps
+            .api
+            .addr_validate(&asset_id)
+            .map_err(|_| ContractError::InvalidCW20ContractAddress {})?;
+
+        if !helper_is_cw20_contract(deps.as_ref(), &cw20_contract_address) {
+            return Err(ContractError::InvalidCW20ContractAddress {});
+        }
+    }
+
+    helper_validate_psm_asset(&psm_asset)?;
+
+    PSM_ASSETS.save(deps.storage, &asset_id, &psm_asset)?;
+    PSM_PRICE_ACCEPTED_AT.save(deps.storage, &asset_id, &env.block.time)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_psm_asset")
+        .add_attribute("sender", info.sender)
+        .add_attribute("asset_id", asset_id)
+        .add_attribute("price_in_dirham", psm_asset.price_in_dirham.to_string())
+        .add_attribute("fee_in", psm_asset.fee_in.to_string())
+        .add_attribute("fee_out", psm_asset.fee_out.to_string()))
+}
+
+// Function to set the dirham price the peg stability module swaps a stablecoin at. The price is
+// guarded like admin set collateral prices, so it cannot move too far within the deviation window
+fn execute_set_psm_asset_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_id: String,
+    price_in_dirham: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::PriceFeeder)?;
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.price_updates {
+        return Err(ContractError::Paused {
+            operation: "price_updates".to_string(),
+        });
+    }
+
+    if price_in_dirham.is_zero() {
+        return Err(ContractError::PriceCannotBeZero {});
+    }
+
+    let mut psm_asset = helper_load_psm_asset(deps.storage, &asset_id)?;
+
+    if let Some(accepted_at) = PSM_PRICE_ACCEPTED_AT.may_load(deps.storage, &asset_id)? {
+        helper_check_price_deviation(
+            &CONFIG.load(deps.storage)?.price_guard_config,
+            env.block.time,
+            psm_asset.price_in_dirham,
+            accepted_at,
+            price_in_dirham,
+        )?;
+    }
+
+    psm_asset.price_in_dirham = price_in_dirham;
+    helper_validate_psm_asset(&psm_asset)?;
+    PSM_ASSETS.save(deps.storage, &asset_id, &psm_asset)?;
+    PSM_PRICE_ACCEPTED_AT.save(deps.storage, &asset_id, &env.block.time)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_psm_asset_price")
+        .add_attribute("sender", info.sender)
+        .add_attribute("asset_id", asset_id)
+        .add_attribute("new_price_in_dirham", price_in_dirham.to_string()))
+}
+
+// Function to set the fees charged for swapping a stablecoin in and out of the peg stability module
+fn execute_set_psm_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_id: String,
+    fee_in: Decimal,
+    fee_out: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::FeeManager)?;
+
+    let mut psm_asset = helper_load_psm_asset(deps.storage, &asset_id)?;
+    psm_asset.fee_in = fee_in;
+    psm_asset.fee_out = fee_out;
+    helper_validate_psm_asset(&psm_asset)?;
+    PSM_ASSETS.save(deps.storage, &asset_id, &psm_asset)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_psm_fees")
+        .add_attribute("sender", info.sender)
+        .add_attribute("asset_id", asset_id)
+        .add_attribute("new_fee_in", fee_in.to_string())
+        .add_attribute("new_fee_out", fee_out.to_string()))
+}
+
+// Function to cap how much of a stablecoin the peg stability module will hold
+fn execute_set_psm_reserve_cap(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_id: String,
+    reserve_cap: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::RiskManager)?;
+
+    let mut psm_asset = helper_load_psm_asset(deps.storage, &asset_id)?;
+    psm_asset.reserve_cap = reserve_cap;
+    PSM_ASSETS.save(deps.storage, &asset_id, &psm_asset)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_psm_reserve_cap")
+        .add_attribute("sender", info.sender)
+        .add_attribute("asset_id", asset_id)
+        .add_attribute(
+            "new_reserve_cap",
+            reserve_cap.map_or_else(|| "none".to_string(), |cap| cap.to_string()),
+        ))
+}
+
+/****
+ * REDEMPTIONS
+ ****/
+
+// Function to file a position in the redemption queue under its current nominal collateral
+// ratio. Positions without debt are taken out of the queue. Since every position of a
+// collateral type shares the same price and stability fee rate, this ratio orders them
+// exactly like their health does
+fn helper_update_redemption_queue(
+    storage: &mut dyn Storage,
+    wallet_address: &Addr,
+    collateral_id: &str,
+) -> Result<(), ContractError> {
+    if let Some(queue_key) = REDEMPTION_QUEUE_KEYS.may_load(storage, (wallet_address, collateral_id))? {
+        REDEMPTION_QUEUE.remove(storage, (collateral_id, queue_key, wallet_address));
+        REDEMPTION_QUEUE_KEYS.remove(storage, (wallet_address, collateral_id));
+    }
+
+    let normalized_debt = MINTED_DIRA
+        .may_load(storage, (wallet_address, collateral_id))?
+        .unwrap_or_default();
+
+    if normalized_debt.is_zero() {
+        return Ok(());
+    }
+
+    let locked_collateral = LOCKED_COLLATERAL
+        .may_load(storage, (wallet_address, collateral_id))?
+        .unwrap_or_default();
+
+    let nominal_collateral_ratio = locked_collateral
+        .checked_div(normalized_debt)
+        .unwrap_or(Decimal::MAX);
+    let queue_key = nominal_collateral_ratio.atomics().u128();
+
+    REDEMPTION_QUEUE.save(storage, (collateral_id, queue_key, wallet_address), &Empty {})?;
+    REDEMPTION_QUEUE_KEYS.save(storage, (wallet_address, collateral_id), &queue_key)?;
+
+    Ok(())
+}
+
+// Function to compute the redemption base rate at the given time, after it has decayed
+fn helper_redemption_base_rate(storage: &dyn Storage, now: Timestamp) -> Result<Decimal, ContractError> {
+    let redemption_base_rate = REDEMPTION_BASE_RATE.load(storage)?;
+
+    let elapsed_minutes = now
+        .seconds()
+        .saturating_sub(redemption_base_rate.last_updated.seconds())
+        / 60;
+    let decay_factor = Decimal::from_atomics(REDEMPTION_BASE_RATE_MINUTE_DECAY_FACTOR, 18)?
+        .pow(u32::try_from(elapsed_minutes).unwrap_or(u32::MAX));
+
+    Ok(redemption_base_rate.base_rate * decay_factor)
+}
+
+// Function to compute the redemption fee from a base rate
+fn helper_redemption_fee(base_rate: Decimal) -> Decimal {
+    (base_rate + Decimal::permille(REDEMPTION_FEE_FLOOR_PERMILLE)).min(Decimal::one())
+}
+
+// Function to redeem DIRA sent through the CW20 Receive hook for collateral at face value.
+// The collateral is taken from the positions with the lowest collateral ratio first, paying
+// off their debt, while positions below the liquidation health are left for liquidators
+fn execute_redeem(
+    deps: DepsMut,
+    env: Env,
+    redeemer: Addr,
+    collateral_id: String,
+    dira_received: Uint128,
+) -> Result<Response, ContractError> {
+    helper_check_not_shut_down(deps.storage)?;
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.liquidations {
+        return Err(ContractError::Paused {
+            operation: "liquidations".to_string(),
+        });
+    }
+
+    let dira_to_redeem = helper_amount_to_decimal(dira_received, DIRA_DECIMALS)?;
+
+    if dira_to_redeem.is_zero() {
+        return Err(ContractError::AmountCannotBeZero {});
+    }
+
+    let collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+    let collateral_price_in_dirham =
+        helper_collateral_price(deps.as_ref(), env.block.time, &collateral_id, &collateral_info)?;
+    let rate = helper_accrue_stability_fee(deps.storage, &collateral_id, env.block.time)?;
+
+    // Positions left for liquidators do not count towards the positions redeemed from
+    let positions_to_redeem = REDEMPTION_QUEUE
+        .sub_prefix(&collateral_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(MAX_REDEMPTION_SCAN_POSITIONS)
+        .map(|key| key.map(|(_, wallet_address)| wallet_address))
+        .collect::<StdResult<Vec<Addr>>>()?;
+
+    let mut dira_left_to_redeem = dira_to_redeem;
+    let mut collateral_redeemed = Decimal::zero();
+    let mut positions_redeemed = 0;
+
+    for wallet_address in positions_to_redeem {
+        if dira_left_to_redeem.is_zero() || positions_redeemed == MAX_REDEMPTION_POSITIONS {
+            break;
+        }
+
+        let locked_collateral = LOCKED_COLLATERAL
+            .may_load(deps.storage, (&wallet_address, &collateral_id))?
+            .unwrap_or_default();
+        let dira_owed = helper_load_dira_owed(deps.storage, &wallet_address, &collateral_id, rate)?;
+
+        let stablecoin_health = helper_calculate_stablecoin_health(
+            dira_owed,
+            locked_collateral,
+            collateral_price_in_dirham,
+        );
+
+        if stablecoin_health < collateral_info.liquidation_health {
+            continue;
+        }
+
+        let dira_redeemed_from_position = dira_left_to_redeem.min(dira_owed);
+        let collateral_redeemed_from_position =
+            (dira_redeemed_from_position / collateral_price_in_dirham).min(locked_collateral);
+
+        LOCKED_COLLATERAL.save(
+            deps.storage,
+            (&wallet_address, &collateral_id),
+            &(locked_collateral - collateral_redeemed_from_position),
+        )?;
+
+        helper_decrease_debt(
+            deps.storage,
+            &wallet_address,
+            &collateral_id,
+            rate,
+            dira_redeemed_from_position,
+        )?;
+
+        dira_left_to_redeem -= dira_redeemed_from_position;
+        collateral_redeemed += collateral_redeemed_from_position;
+        positions_redeemed += 1;
+    }
+
+    let dira_redeemed = dira_to_redeem - dira_left_to_redeem;
+    let dira_to_burn = helper_decimal_to_amount(dira_redeemed, DIRA_DECIMALS);
+
+    if dira_to_burn.is_zero() {
+        return Err(ContractError::NothingToRedeem { collateral_id });
+    }
+
+    // The base rate rises by half the share of the DIRA supply that was redeemed
+    let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
+    let dira_token_info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(&cw20_dira_contract_address, &cw20::Cw20QueryMsg::TokenInfo {})?;
+    let dira_supply = helper_amount_to_decimal(dira_token_info.total_supply, DIRA_DECIMALS)?;
+
+    let base_rate = (helper_redemption_base_rate(deps.storage, env.block.time)?
+        + dira_redeemed / dira_supply / Decimal::from_ratio(2u128, 1u128))
+    .min(Decimal::one());
+
+    REDEMPTION_BASE_RATE.save(
+        deps.storage,
+        &RedemptionBaseRate {
+            base_rate,
+            last_updated: env.block.time,
+        },
+    )?;
+
+    let redemption_fee = helper_redemption_fee(base_rate);
+    let collateral_fee = collateral_redeemed * redemption_fee;
+    let collateral_to_redeemer = collateral_redeemed - collateral_fee;
+
+    let mut response = Response::new().add_message(cosmwasm_std::WasmMsg::Execute {
+        contract_addr: cw20_dira_contract_address.to_string(),
+        msg: to_json_binary(&cw20::Cw20ExecuteMsg::Burn {
+            amount: dira_to_burn,
+        })?,
+        funds: vec![],
+    });
+
+    let dira_to_refund = dira_received - dira_to_burn;
+
+    if !dira_to_refund.is_zero() {
+        response = response.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Transfer {
+                recipient: redeemer.to_string(),
+                amount: dira_to_refund,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    if !helper_decimal_to_amount(collateral_to_redeemer, collateral_info.decimals).is_zero() {
+        response = response.add_message(helper_send_collateral_message(
+            &collateral_id,
+            &collateral_info,
+            &redeemer,
+            collateral_to_redeemer,
+        )?);
+    }
+
+    if !helper_decimal_to_amount(collateral_fee, collateral_info.decimals).is_zero() {
+        response = response.add_message(helper_send_collateral_message(
+            &collateral_id,
+            &collateral_info,
+            &helper_load_treasury_address(deps.storage)?,
+            collateral_fee,
+        )?);
+    }
+
+    Ok(response
+        .add_attribute("action", "redeem")
+        .add_attribute("sender", redeemer)
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute("dira_redeemed", dira_redeemed.to_string())
+        .add_attribute("collateral_redeemed", collateral_to_redeemer.to_string())
+        .add_attribute("redemption_fee_paid", collateral_fee.to_string()))
+}
+
+/****
+ * COLLATERAL AUCTIONS
+ ****/
+
+// Function to compute the current price of an auction's collateral, which decays linearly
+// from the starting price down to zero over the configured decay time
+fn helper_auction_price(auction: &Auction, auction_config: &AuctionConfig, now: Timestamp) -> Decimal {
+    let elapsed_seconds = now.seconds().saturating_sub(auction.start_time.seconds());
+
+    if elapsed_seconds >= auction_config.price_decay_seconds {
+        return Decimal::zero();
+    }
+
+    auction.starting_price
+        * Decimal::from_ratio(
+            auction_config.price_decay_seconds - elapsed_seconds,
+            auction_config.price_decay_seconds,
+        )
+}
+
+// Function to check whether an auction has run too long and has to be reset before bidding again
+fn helper_auction_needs_reset(
+    auction: &Auction,
+    auction_config: &AuctionConfig,
+    now: Timestamp,
+) -> bool {
+    now.seconds().saturating_sub(auction.start_time.seconds()) >= auction_config.reset_after_seconds
+        || helper_auction_price(auction, auction_config, now).is_zero()
+}
+
+// Function to start an auction for the collateral seized from a liquidated position
+fn execute_start_auction(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wallet_address_to_liquidate: Addr,
+    collateral_id: String,
+    debt_to_repay: Decimal,
+) -> Result<Response, ContractError> {
+    helper_check_not_shut_down(deps.storage)?;
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.liquidations {
+        return Err(ContractError::Paused {
+            operation: "liquidations".to_string(),
+        });
+    }
+
+    let rate = helper_accrue_stability_fee(deps.storage, &collateral_id, env.block.time)?;
+
+    let position = helper_load_liquidatable_position(
+        deps.as_ref(),
+        &wallet_address_to_liquidate,
+        &collateral_id,
+        debt_to_repay,
+        rate,
+        env.block.time,
+    )?;
+
+    // Keep the price the position was liquidated at on record, whatever its price source
+    helper_record_collateral_price(
+        deps.storage,
+        &collateral_id,
+        env.block.time,
+        position.collateral_price_in_dirham,
+    )?;
+
+    let Config {
+        liquidation_bonus,
+        auction_config,
+        ..
+    } = CONFIG.load(deps.storage)?;
+
+    // The auction has to raise the repaid debt plus the liquidation penalty
+    let dira_to_raise = debt_to_repay * (Decimal::one() + liquidation_bonus);
+    let starting_price = position.collateral_price_in_dirham * auction_config.starting_premium;
+
+    // Seize the collateral worth what has to be raised marked up by the starting premium, so
+    // that the auction can still raise all of it once its price has decayed below the
+    // collateral price. Whatever is not sold goes back to the wallet
+    let collateral_to_auction = (dira_to_raise * auction_config.starting_premium
+        / position.collateral_price_in_dirham)
+        .min(position.collateral_locked);
+
+    if collateral_to_auction.is_zero() {
+        return Err(ContractError::InsufficientCollateral {});
+    }
+
+    LOCKED_COLLATERAL.save(
+        deps.storage,
+        (&wallet_address_to_liquidate, &collateral_id),
+        &(position.collateral_locked - collateral_to_auction),
+    )?;
+
+    // The debt moves from the position to the auction, where it no longer accrues stability fees
+    helper_decrease_debt(
+        deps.storage,
+        &wallet_address_to_liquidate,
+        &collateral_id,
+        rate,
+        debt_to_repay,
+    )?;
+
+    let auction_id = AUCTION_COUNT.update(deps.storage, |auction_count| -> StdResult<u64> {
+        Ok(auction_count + 1)
+    })?;
+
+    AUCTIONS.save(
+        deps.storage,
+        auction_id,
+        &Auction {
+            wallet_address: wallet_address_to_liquidate.clone(),
+            collateral_id: collateral_id.clone(),
+            collateral_amount: collateral_to_auction,
+            dira_to_raise,
+            debt_to_repay,
+            starting_price,
+            start_time: env.block.time,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "start_auction")
+        .add_attribute("auction_id", auction_id.to_string())
+        .add_attribute("liquidated_wallet", wallet_address_to_liquidate.to_string())
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute("collateral_amount", collateral_to_auction.to_string())
+        .add_attribute("dira_to_raise", dira_to_raise.to_string())
+        .add_attribute("starting_price", starting_price.to_string())
+        .add_attribute("initiator", info.sender.to_string()))
+}
+
+// Function to buy collateral from an auction at its current price with DIRA sent
+// through the CW20 Receive hook
+fn execute_bid_auction(
+    deps: DepsMut,
+    env: Env,
+    bidder: Addr,
+    auction_id: u64,
+    max_collateral: Decimal,
+    dira_received: Uint128,
+) -> Result<Response, ContractError> {
+    helper_check_not_shut_down(deps.storage)?;
+
+    if helper_active_pause_flags(deps.storage, env.block.time)?.liquidations {
+        return Err(ContractError::Paused {
+            operation: "liquidations".to_string(),
+        });
+    }
+
+    let mut auction = AUCTIONS
+        .may_load(deps.storage, auction_id)?
+        .ok_or(ContractError::UnknownAuction { auction_id })?;
+    let auction_config = CONFIG.load(deps.storage)?.auction_config;
+
+    if helper_auction_needs_reset(&auction, &auction_config, env.block.time) {
+        return Err(ContractError::AuctionNeedsReset { auction_id });
+    }
+
+    let collateral_info = helper_load_collateral_info(deps.storage, &auction.collateral_id)?;
+    let auction_price = helper_auction_price(&auction, &auction_config, env.block.time);
+    let dira_offered = helper_amount_to_decimal(dira_received, DIRA_DECIMALS)?;
+
+    // Buy as much collateral as the bidder asked for and paid for, without
+    // raising more DIRA than the auction still needs
+    let collateral_to_close_auction = auction.dira_to_raise / auction_price;
+    let collateral_wanted = max_collateral
+        .min(auction.collateral_amount)
+        .min(dira_offered / auction_price);
+
+    let (collateral_bought, dira_paid) = if collateral_wanted >= collateral_to_close_auction {
+        (
+            collateral_to_close_auction,
+            auction.dira_to_raise.min(dira_offered),
+        )
+    } else {
+        (collateral_wanted, collateral_wanted * auction_price)
+    };
+
+    if helper_decimal_to_amount(collateral_bought, collateral_info.decimals).is_zero() {
+        return Err(ContractError::AmountCannotBeZero {});
+    }
+
+    // The DIRA paid repays the debt first and is burned, anything above that
+    // is the liquidation penalty and goes to the treasury
+    let debt_repaid = dira_paid.min(auction.debt_to_repay);
+
+    auction.collateral_amount -= collateral_bought;
+    auction.dira_to_raise -= dira_paid;
+    auction.debt_to_repay -= debt_repaid;
+
+    let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
+    let dira_paid_amount = helper_decimal_to_amount(dira_paid, DIRA_DECIMALS);
+    let dira_to_burn = helper_decimal_to_amount(debt_repaid, DIRA_DECIMALS);
+    let dira_to_treasury = dira_paid_amount - dira_to_burn;
+    let dira_to_refund = dira_received - dira_paid_amount;
+
+    let mut response = Response::new().add_message(helper_send_collateral_message(
+        &auction.collateral_id,
+        &collateral_info,
+        &bidder,
+        collateral_bought,
+    )?);
+
+    if !dira_to_burn.is_zero() {
+        response = response.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Burn {
+                amount: dira_to_burn,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    if !dira_to_treasury.is_zero() {
+        let treasury_address = helper_load_treasury_address(deps.storage)?;
+
+        response = response.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Transfer {
+                recipient: treasury_address.to_string(),
+                amount: dira_to_treasury,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    if !dira_to_refund.is_zero() {
+        response = response.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: cw20_dira_contract_address.to_string(),
+            msg: to_json_binary(&cw20::Cw20ExecuteMsg::Transfer {
+                recipient: bidder.to_string(),
+                amount: dira_to_refund,
+            })?,
+            funds: vec![],
+        });
+    }
+
+    // The auction is over once everything has been raised or all the collateral is sold.
+    // Leftover collateral goes back to the liquidated wallet, while debt that could not be
+    // raised is booked as bad debt
+    let auction_closed = helper_decimal_to_amount(auction.dira_to_raise, DIRA_DECIMALS).is_zero()
+        || helper_decimal_to_amount(auction.collateral_amount, collateral_info.decimals).is_zero();
+
+    if auction_closed {
+        if !auction.debt_to_repay.is_zero() {
+            let bad_debt = BAD_DEBT.may_load(deps.storage)?.unwrap_or_default();
+            BAD_DEBT.save(deps.storage, &(bad_debt + auction.debt_to_repay))?;
+        }
+
+        LOCKED_COLLATERAL.update(
+            deps.storage,
+            (&auction.wallet_address, &auction.collateral_id),
+            |locked_collateral| -> StdResult<Decimal> {
+                Ok(locked_collateral.unwrap_or_default() + auction.collateral_amount)
+            },
+        )?;
+
+        helper_update_redemption_queue(
+            deps.storage,
+            &auction.wallet_address,
+            &auction.collateral_id,
+        )?;
+
+        AUCTIONS.remove(deps.storage, auction_id);
+    } else {
+        AUCTIONS.save(deps.storage, auction_id, &auction)?;
+    }
+
+    Ok(response
+        .add_attribute("action", "bid_auction")
+        .add_attribute("auction_id", auction_id.to_string())
+        .add_attribute("bidder", bidder.to_string())
+        .add_attribute("auction_price", auction_price.to_string())
+        .add_attribute("collateral_bought", collateral_bought.to_string())
+        .add_attribute("dira_paid", dira_paid.to_string())
+        .add_attribute("auction_closed", auction_closed.to_string()))
+}
+
+// Function to restart an auction that has run too long at the current collateral price
+fn execute_reset_auction(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    auction_id: u64,
+) -> Result<Response, ContractError> {
+    if helper_active_pause_flags(deps.storage, env.block.time)?.liquidations {
+        return Err(ContractError::Paused {
+            operation: "liquidations".to_string(),
+        });
+    }
+
+    let mut auction = AUCTIONS
+        .may_load(deps.storage, auction_id)?
+        .ok_or(ContractError::UnknownAuction { auction_id })?;
+    let auction_config = CONFIG.load(deps.storage)?.auction_config;
+
+    if !helper_auction_needs_reset(&auction, &auction_config, env.block.time) {
+        return Err(ContractError::AuctionNotResettable { auction_id });
+    }
+
+    let collateral_info = helper_load_collateral_info(deps.storage, &auction.collateral_id)?;
+
+    auction.starting_price =
+        helper_collateral_price(deps.as_ref(), env.block.time, &auction.collateral_id, &collateral_info)?
+            * auction_config.starting_premium;
+    auction.start_time = env.block.time;
+
+    AUCTIONS.save(deps.storage, auction_id, &auction)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "reset_auction")
+        .add_attribute("auction_id", auction_id.to_string())
+        .add_attribute("starting_price", auction.starting_price.to_string())
+        .add_attribute("sender", info.sender.to_string()))
+}
+
+/****
+ * MIGRATIONS
+ ****/
+
+// Function to parse a contract version such as 0.2.0 into its numbers, so versions can be
+// compared. Pre-release and build suffixes are ignored
+fn helper_parse_contract_version(version: &str) -> Result<Vec<u64>, ContractError> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|number| {
+            number
+                .parse::<u64>()
+                .map_err(|_| ContractError::InvalidContractVersion {
+                    version: version.to_string(),
+                })
+        })
+        .collect()
+}
+
+// Function to get the contract parameters a new instance starts with
+fn helper_default_config() -> Config {
+    Config {
+        cw20_dira_contract_address: None,
+        fee_switch: FeeConfig {
+            enabled: true,
+            tier: FeeTier::Low,
+        },
+        close_factor: Decimal::percent(DEFAULT_CLOSE_FACTOR_PERCENT),
+        liquidation_bonus: Decimal::percent(DEFAULT_LIQUIDATION_BONUS_PERCENT),
+        liquidation_protocol_share: Decimal::percent(DEFAULT_LIQUIDATION_PROTOCOL_SHARE_PERCENT),
+        savings_rate: Decimal::zero(),
+        auction_config: AuctionConfig {
+            starting_premium: Decimal::percent(DEFAULT_AUCTION_STARTING_PREMIUM_PERCENT),
+            price_decay_seconds: DEFAULT_AUCTION_PRICE_DECAY_SECONDS,
+            reset_after_seconds: DEFAULT_AUCTION_RESET_AFTER_SECONDS,
+        },
+        price_guard_config: PriceGuardConfig {
+            max_price_deviation: Decimal::percent(DEFAULT_MAX_PRICE_DEVIATION_PERCENT),
+            deviation_window_seconds: 0,
+            price_delay_seconds: 0,
+        },
+        price_feed_config: PriceFeedConfig {
+            quorum: DEFAULT_PRICE_FEED_QUORUM,
+            max_price_age_seconds: DEFAULT_MAX_PRICE_AGE_SECONDS,
+        },
+        timelock_delay_seconds: DEFAULT_TIMELOCK_DELAY_SECONDS,
+    }
+}
+
+// Function to save the default of every config item that is not set yet. Instantiate sets
+// them all, and migrations fill in the ones added since an instance was instantiated
+fn helper_save_missing_config(storage: &mut dyn Storage, now: Timestamp) -> Result<(), ContractError> {
+    if CONFIG.may_load(storage)?.is_none() {
+        CONFIG.save(storage, &helper_default_config())?;
+    }
+
+    if STABILITY_POOL.may_load(storage)?.is_none() {
+        STABILITY_POOL.save(
+            storage,
+            &StabilityPoolState {
+                total_deposits: Decimal::zero(),
+                product: Decimal256::one(),
+                epoch: 0,
+                scale: 0,
+            },
+        )?;
+    }
+
+    if AUCTION_COUNT.may_load(storage)?.is_none() {
+        AUCTION_COUNT.save(storage, &0)?;
+    }
+
+    if QUEUED_CHANGE_COUNT.may_load(storage)?.is_none() {
+        QUEUED_CHANGE_COUNT.save(storage, &0)?;
+    }
+
+    if PRICE_FEEDERS.may_load(storage)?.is_none() {
+        PRICE_FEEDERS.save(storage, &vec![])?;
+    }
+
+    if REDEMPTION_BASE_RATE.may_load(storage)?.is_none() {
+        REDEMPTION_BASE_RATE.save(
+            storage,
+            &RedemptionBaseRate {
+                base_rate: Decimal::zero(),
+                last_updated: now,
+            },
+        )?;
+    }
+
+    if UNMINTED_STABILITY_FEES.may_load(storage)?.is_none() {
+        UNMINTED_STABILITY_FEES.save(storage, &Decimal::zero())?;
+    }
+
+    if BAD_DEBT.may_load(storage)?.is_none() {
+        BAD_DEBT.save(storage, &Decimal::zero())?;
+    }
+
+    if SAVINGS_VAULT.may_load(storage)?.is_none() {
+        SAVINGS_VAULT.save(
+            storage,
+            &SavingsVault {
+                total_shares: Decimal::zero(),
+                share_price: Decimal::one(),
+                last_updated: now,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+// State migration 1. Moves instances of the first release, which accepted a single collateral
+// type, over to the collateral registry: the collateral type is registered with its risk
+// parameters and price, positions are keyed by collateral type, and every admin gets the
+// roles admins hold
+fn migrate_state_to_collateral_registry(storage: &mut dyn Storage, env: &Env) -> Result<(), ContractError> {
+    if let Some(collateral_id) = LEGACY_COLLATERAL_TOKEN_DENOM.may_load(storage)? {
+        if !COLLATERALS.has(storage, &collateral_id) {
+            let collateral_info = CollateralInfo {
+                asset_type: CollateralAssetType::Native,
+                collateral_price_in_dirham: LEGACY_COLLATERAL_TOKEN_PRICE.may_load(storage)?,
+                price_source: PriceSource::Manual,
+                liquidation_health: LEGACY_LIQUIDATION_HEALTH.load(storage)?,
+                mintable_health: LEGACY_MINTABLE_HEALTH.load(storage)?,
+                debt_ceiling: None,
+                decimals: DEFAULT_COLLATERAL_DECIMALS,
+                stability_fee: Decimal::zero(),
+            };
+
+            COLLATERALS.save(storage, &collateral_id, &collateral_info)?;
+        }
+
+        let locked_collaterals = LEGACY_LOCKED_COLLATERAL
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<(Addr, Decimal)>>>()?;
+
+        for (wallet_address, collateral_locked) in locked_collaterals {
+            LOCKED_COLLATERAL.update(
+                storage,
+                (&wallet_address, &collateral_id),
+                |locked| -> StdResult<Decimal> { Ok(locked.unwrap_or_default() + collateral_locked) },
+            )?;
+            LEGACY_LOCKED_COLLATERAL.remove(storage, &wallet_address);
+        }
+
+        let minted_dira = LEGACY_MINTED_DIRA
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<(Addr, Decimal)>>>()?;
+        let rate = helper_accrue_stability_fee(storage, &collateral_id, env.block.time)?;
+
+        for (wallet_address, dira_minted) in minted_dira {
+            if !dira_minted.is_zero() {
+                helper_increase_debt(storage, &wallet_address, &collateral_id, rate, dira_minted)?;
+            }
+            LEGACY_MINTED_DIRA.remove(storage, &wallet_address);
+        }
+
+        let admin_addresses = ADMIN_ADDRESSES.may_load(storage)?.unwrap_or_default();
+
+        for (index, admin_address) in admin_addresses.iter().enumerate() {
+            let roles: &[Role] = if index == 0 { &Role::ALL } else { &ADMIN_ROLES };
+
+            for role in roles.iter() {
+                ROLES.save(storage, (role.as_str(), admin_address), &Empty {})?;
+            }
+        }
+
+        LEGACY_COLLATERAL_TOKEN_DENOM.remove(storage);
+        LEGACY_COLLATERAL_TOKEN_PRICE.remove(storage);
+        LEGACY_LIQUIDATION_HEALTH.remove(storage);
+        LEGACY_MINTABLE_HEALTH.remove(storage);
+    }
+
+    Ok(())
+}
+
+// State migration 2. Moves the contract parameters, kept in an item each before, into the
+// config item, giving the ones never set their defaults. Then the config items added since are
+// set to their defaults
+fn migrate_state_to_config(storage: &mut dyn Storage, env: &Env) -> Result<(), ContractError> {
+    if CONFIG.may_load(storage)?.is_none() {
+        let default_config = helper_default_config();
+
+        let config = Config {
+            cw20_dira_contract_address: LEGACY_CW20_DIRA_CONTRACT_ADDRESS.may_load(storage)?,
+            fee_switch: LEGACY_FEE_SWITCH
+                .may_load(storage)?
+                .unwrap_or(default_config.fee_switch),
+            close_factor: LEGACY_CLOSE_FACTOR
+                .may_load(storage)?
+                .unwrap_or(default_config.close_factor),
+            liquidation_bonus: LEGACY_LIQUIDATION_BONUS
+                .may_load(storage)?
+                .unwrap_or(default_config.liquidation_bonus),
+            liquidation_protocol_share: LEGACY_LIQUIDATION_PROTOCOL_SHARE
+                .may_load(storage)?
+                .unwrap_or(default_config.liquidation_protocol_share),
+            savings_rate: LEGACY_SAVINGS_RATE
+                .may_load(storage)?
+                .unwrap_or(default_config.savings_rate),
+            auction_config: LEGACY_AUCTION_CONFIG
+                .may_load(storage)?
+                .unwrap_or(default_config.auction_config),
+            price_guard_config: LEGACY_PRICE_GUARD_CONFIG
+                .may_load(storage)?
+                .unwrap_or(default_config.price_guard_config),
+            price_feed_config: LEGACY_PRICE_FEED_CONFIG
+                .may_load(storage)?
+                .unwrap_or(default_config.price_feed_config),
+            timelock_delay_seconds: LEGACY_TIMELOCK_DELAY
+                .may_load(storage)?
+                .unwrap_or(default_config.timelock_delay_seconds),
+        };
+
+        CONFIG.save(storage, &config)?;
+    }
+
+    LEGACY_CW20_DIRA_CONTRACT_ADDRESS.remove(storage);
+    LEGACY_FEE_SWITCH.remove(storage);
+    LEGACY_CLOSE_FACTOR.remove(storage);
+    LEGACY_LIQUIDATION_BONUS.remove(storage);
+    LEGACY_LIQUIDATION_PROTOCOL_SHARE.remove(storage);
+    LEGACY_SAVINGS_RATE.remove(storage);
+    LEGACY_AUCTION_CONFIG.remove(storage);
+    LEGACY_PRICE_GUARD_CONFIG.remove(storage);
+    LEGACY_PRICE_FEED_CONFIG.remove(storage);
+    LEGACY_TIMELOCK_DELAY.remove(storage);
+
+    helper_save_missing_config(storage, env.block.time)
+}
+
+/****
+ * GLOBAL SHUTDOWN
+ ****/
+
+// Function for the owner to shut the contract down for good. Every collateral price is frozen
+// and the debt owed against every collateral type is snapshotted. Running auctions are settled
+// at once, their debt netted against the collateral for sale and the rest returned to the wallet
+fn execute_shutdown(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    helper_check_role(deps.storage, &info.sender, Role::Owner)?;
+    helper_check_not_shut_down(deps.storage)?;
+
+    let collaterals = COLLATERALS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, CollateralInfo)>>>()?;
+
+    for (collateral_id, collateral_info) in collaterals.iter() {
+        let rate = helper_accrue_stability_fee(deps.storage, collateral_id, env.block.time)?;
+        let total_debt = TOTAL_MINTED_DIRA
+            .may_load(deps.storage, collateral_id)?
+            .unwrap_or_default()
+            * rate;
+        let price = helper_shutdown_collateral_price(
+            deps.as_ref(),
+            env.block.time,
+            collateral_id,
+            collateral_info,
+            total_debt,
+        )?;
+
+        SHUTDOWN_COLLATERALS.save(
+            deps.storage,
+            collateral_id,
+            &ShutdownCollateral {
+                price,
+                total_debt,
+                settled_debt: Decimal::zero(),
+                backing: Decimal::zero(),
+            },
+        )?;
+    }
+
+    let auctions = AUCTIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(u64, Auction)>>>()?;
+
+    for (auction_id, auction) in auctions {
+        let mut shutdown_collateral = SHUTDOWN_COLLATERALS.load(deps.storage, &auction.collateral_id)?;
+        let netted_collateral = helper_shutdown_netted_collateral(
+            auction.debt_to_repay,
+            shutdown_collateral.price,
+            auction.collateral_amount,
+        );
+
+        shutdown_collateral.total_debt += auction.debt_to_repay;
+        shutdown_collateral.settled_debt += auction.debt_to_repay;
+        shutdown_collateral.backing += netted_collateral;
+        SHUTDOWN_COLLATERALS.save(deps.storage, &auction.collateral_id, &shutdown_collateral)?;
+
+        LOCKED_COLLATERAL.update(
+            deps.storage,
+            (&auction.wallet_address, &auction.collateral_id),
+            |locked_collateral| -> StdResult<Decimal> {
+                Ok(locked_collateral.unwrap_or_default() + auction.collateral_amount - netted_collateral)
+            },
+        )?;
+
+        helper_update_redemption_queue(
+            deps.storage,
+            &auction.wallet_address,
+            &auction.collateral_id,
+        )?;
+
+        AUCTIONS.remove(deps.storage, auction_id);
+    }
+
+    // No DIRA can have been minted before the CW20 DIRA contract is set
+    let dira_supply = match CONFIG.load(deps.storage)?.cw20_dira_contract_address {
+        Some(cw20_dira_contract_address) => {
+            let dira_token_info: TokenInfoResponse = deps
+                .querier
+                .query_wasm_smart(&cw20_dira_contract_address, &cw20::Cw20QueryMsg::TokenInfo {})?;
+            helper_amount_to_decimal(dira_token_info.total_supply, DIRA_DECIMALS)?
+        }
+        None => Decimal::zero(),
+    };
+
+    SHUTDOWN.save(
+        deps.storage,
+        &Shutdown {
+            shutdown_at: env.block.time,
+            dira_supply,
+            dira_cashed_out: Decimal::zero(),
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "shutdown")
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("dira_supply", dira_supply.to_string())
+        .add_event(
+            Event::new("shutdown")
+                .add_attribute("sender", info.sender)
+                .add_attribute("shutdown_at", env.block.time.to_string()),
+        ))
+}
+
+// Function to get the price a collateral type is frozen at on shutdown. Shutdown must not
+// depend on live price sources, so when the current price cannot be read the last price in the
+// price history is used, or else the last price accepted from an admin. Collateral types without
+// debt never need a price, and are frozen at the last known price or at zero when they were
+// never priced
+fn helper_shutdown_collateral_price(
+    deps: Deps,
+    now: Timestamp,
+    collateral_id: &str,
+    collateral_info: &CollateralInfo,
+    total_debt: Decimal,
+) -> Result<Decimal, ContractError> {
+    if !total_debt.is_zero() {
+        if let Ok(price) = helper_collateral_price(deps, now, collateral_id, collateral_info) {
+            return Ok(price);
+        }
+    }
+
+    let last_recorded_price = PRICE_HISTORY
+        .prefix(collateral_id)
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive(now.seconds())),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?
+        .map(|(_, price)| price)
+        .or(collateral_info.collateral_price_in_dirham);
+
+    match last_recorded_price {
+        Some(price) => Ok(price),
+        None if total_debt.is_zero() => Ok(Decimal::zero()),
+        None => Err(ContractError::CollateralPriceNotSet {}),
+    }
+}
+
+// Function to get the collateral netted against a debt at the price frozen on shutdown, at most
+// all of the collateral
+fn helper_shutdown_netted_collateral(debt: Decimal, price: Decimal, collateral: Decimal) -> Decimal {
+    if debt.is_zero() {
+        return Decimal::zero();
+    }
+
+    debt.checked_div(price)
+        .map_or(collateral, |netted_collateral| netted_collateral.min(collateral))
+}
+
+// Function to settle a position after shutdown. Its debt is netted against its collateral at
+// the frozen price, the netted collateral backs the DIRA in circulation and the rest is sent
+// back to the owner of the position
+fn execute_settle_position(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wallet_address: Addr,
+    collateral_id: String,
+) -> Result<Response, ContractError> {
+    SHUTDOWN
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NotShutDown {})?;
+
+    let collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+    let mut shutdown_collateral = SHUTDOWN_COLLATERALS.load(deps.storage, &collateral_id)?;
+
+    let rate = helper_accrue_stability_fee(deps.storage, &collateral_id, env.block.time)?;
+    let dira_owed = helper_load_dira_owed(deps.storage, &wallet_address, &collateral_id, rate)?;
+    let collateral_locked = LOCKED_COLLATERAL
+        .may_load(deps.storage, (&wallet_address, &collateral_id))?
+        .unwrap_or_default();
+
+    if dira_owed.is_zero() && collateral_locked.is_zero() {
+        return Err(ContractError::NothingToSettle { wallet_address });
+    }
+
+    // Underwater positions give up all of their collateral, the shortfall is shared by
+    // every DIRA holder
+    let netted_collateral =
+        helper_shutdown_netted_collateral(dira_owed, shutdown_collateral.price, collateral_locked);
+    let excess_collateral = collateral_locked - netted_collateral;
+
+    LOCKED_COLLATERAL.save(
+        deps.storage,
+        (&wallet_address, &collateral_id),
+        &Decimal::zero(),
+    )?;
+    helper_decrease_debt(deps.storage, &wallet_address, &collateral_id, rate, dira_owed)?;
+
+    shutdown_collateral.settled_debt += dira_owed;
+    shutdown_collateral.backing += netted_collateral;
+    SHUTDOWN_COLLATERALS.save(deps.storage, &collateral_id, &shutdown_collateral)?;
 
-    if !admins.contains(&info.sender) {
-        return Err(ContractError::UnauthorizedUser {});
-    }
+    let mut response = Response::new();
 
-    LIQUIDATION_HEALTH.save(deps.storage, &liquidation_health)?;
+    if !helper_decimal_to_amount(excess_collateral, collateral_info.decimals).is_zero() {
+        response = response.add_message(helper_send_collateral_message(
+            &collateral_id,
+            &collateral_info,
+            &wallet_address,
+            excess_collateral,
+        )?);
+    }
 
-    Ok(Response::new()
-        .add_attribute("action", "set_liquidation_health")
+    Ok(response
+        .add_attribute("action", "settle_position")
         .add_attribute("sender", info.sender)
-        .add_attribute("new_liquidation_health", liquidation_health.to_string()))
+        .add_attribute("wallet_address", wallet_address)
+        .add_attribute("collateral_id", collateral_id)
+        .add_attribute("debt_settled", dira_owed.to_string())
+        .add_attribute("collateral_netted", netted_collateral.to_string())
+        .add_attribute("collateral_returned", excess_collateral.to_string()))
 }
 
-// Function to set mintable health
-fn execute_set_mintable_health(
+// Function to burn DIRA after shutdown for its pro rata share of every collateral type's
+// backing and of the peg stability module's reserves, out of the DIRA supply snapshotted at
+// shutdown that has not been cashed out yet. Cashing out opens once the settlement period is
+// over or every position has been settled. Positions settled after that add to the backing
+// left for the DIRA still in circulation
+fn execute_cash_out(
     deps: DepsMut,
-    info: MessageInfo,
-    mintable_health: Decimal,
+    env: Env,
+    sender: Addr,
+    dira_received: Uint128,
 ) -> Result<Response, ContractError> {
-    let admins = ADMIN_ADDRESSES.load(deps.storage)?;
+    let mut shutdown = SHUTDOWN
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NotShutDown {})?;
 
-    if !admins.contains(&info.sender) {
-        return Err(ContractError::UnauthorizedUser {});
+    let dira_to_cash_out = helper_amount_to_decimal(dira_received, DIRA_DECIMALS)?;
+
+    if dira_to_cash_out.is_zero() {
+        return Err(ContractError::AmountCannotBeZero {});
     }
 
-    let current_liquidation_health = LIQUIDATION_HEALTH.load(deps.storage)?;
+    let shutdown_collaterals = SHUTDOWN_COLLATERALS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, ShutdownCollateral)>>>()?;
 
-    MINTABLE_HEALTH.update(
-        deps.storage,
-        |_current_mintable_health| -> Result<Decimal, ContractError> {
-            if mintable_health < current_liquidation_health {
-                return Err(ContractError::MintableHealthLowerThanLiquidationHealth {});
-            } else {
-                return Ok(mintable_health);
-            }
-        },
-    )?;
+    let opens_at = shutdown.shutdown_at.plus_seconds(SHUTDOWN_SETTLEMENT_PERIOD_SECONDS);
 
-    Ok(Response::new()
-        .add_attribute("action", "set_mintable_health")
-        .add_attribute("sender", info.sender)
-        .add_attribute("new_liquidation_health", mintable_health.to_string()))
-}
+    if env.block.time < opens_at {
+        for (collateral_id, _) in shutdown_collaterals.iter() {
+            let total_normalized_debt = TOTAL_MINTED_DIRA
+                .may_load(deps.storage, collateral_id)?
+                .unwrap_or_default();
 
-fn execute_set_cw20_dira_contact_address(
-    deps: DepsMut,
-    cw20_dira_contract_address: Addr,
-    info:MessageInfo,
-) -> Result<Response, ContractError> {
-    let admins = ADMIN_ADDRESSES.load(deps.storage)?;
-    if !admins.contains(&info.sender) {
-        return Err(ContractError::UnauthorizedUser {});
+            if !total_normalized_debt.is_zero() {
+                return Err(ContractError::CashOutNotOpen { opens_at });
+            }
+        }
     }
 
-    if helper_is_cw20_contract(deps.as_ref(), &cw20_dira_contract_address) {
-        CW20_DIRA_CONTRACT_ADDRESS.save(deps.storage, &cw20_dira_contract_address)?;
-        return Ok(Response::new()
-            .add_attribute("action", "set_cw20_dira_contract_address")
-            .add_attribute("contract_address", cw20_dira_contract_address.into_string()));
-    } else {
-        return Err(ContractError::InvalidCW20ContractAddress {});
+    // The supply cannot grow after shutdown, so the DIRA left to cash out covers the DIRA sent
+    let dira_left_to_cash_out = shutdown.dira_supply - shutdown.dira_cashed_out;
+    let share = dira_to_cash_out
+        .checked_div(dira_left_to_cash_out)
+        .unwrap_or(Decimal::one())
+        .min(Decimal::one());
+
+    shutdown.dira_cashed_out = (shutdown.dira_cashed_out + dira_to_cash_out).min(shutdown.dira_supply);
+    SHUTDOWN.save(deps.storage, &shutdown)?;
+
+    let cw20_dira_contract_address = helper_load_cw20_dira_contract_address(deps.storage)?;
+
+    let mut response = Response::new().add_message(cosmwasm_std::WasmMsg::Execute {
+        contract_addr: cw20_dira_contract_address.to_string(),
+        msg: to_json_binary(&cw20::Cw20ExecuteMsg::Burn {
+            amount: dira_received,
+        })?,
+        funds: vec![],
+    });
+
+    for (collateral_id, mut shutdown_collateral) in shutdown_collaterals {
+        let collateral_info = helper_load_collateral_info(deps.storage, &collateral_id)?;
+        let collateral_paid_out = shutdown_collateral.backing * share;
+
+        if helper_decimal_to_amount(collateral_paid_out, collateral_info.decimals).is_zero() {
+            continue;
+        }
+
+        shutdown_collateral.backing -= collateral_paid_out;
+        SHUTDOWN_COLLATERALS.save(deps.storage, &collateral_id, &shutdown_collateral)?;
+
+        response = response
+            .add_message(helper_send_collateral_message(
+                &collateral_id,
+                &collateral_info,
+                &sender,
+                collateral_paid_out,
+            )?)
+            .add_attribute(format!("{}_paid_out", collateral_id), collateral_paid_out.to_string());
     }
-}
 
-fn execute_enable_fee_switch_state(
-    deps: DepsMut,
-    info: MessageInfo,
-) -> Result<Response, ContractError> {
-    let admins = ADMIN_ADDRESSES.load(deps.storage)?;
+    let psm_assets = PSM_ASSETS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, PsmAsset)>>>()?;
 
-    if !admins.contains(&info.sender) {
-        return Err(ContractError::UnauthorizedUser {});
+    for (asset_id, psm_asset) in psm_assets {
+        let reserves = PSM_RESERVES
+            .may_load(deps.storage, &asset_id)?
+            .unwrap_or_default();
+        let stablecoin_paid_out = reserves * share;
+
+        if helper_decimal_to_amount(stablecoin_paid_out, psm_asset.decimals).is_zero() {
+            continue;
+        }
+
+        PSM_RESERVES.save(deps.storage, &asset_id, &(reserves - stablecoin_paid_out))?;
+
+        response = response
+            .add_message(helper_send_asset_message(
+                &asset_id,
+                &psm_asset.asset_type,
+                psm_asset.decimals,
+                &sender,
+                stablecoin_paid_out,
+            )?)
+            .add_attribute(format!("{}_paid_out", asset_id), stablecoin_paid_out.to_string());
     }
 
-    FEE_SWITCH.update(deps.storage, |mut config| -> Result<_, ContractError> {
-        config.enabled = true;
-        Ok(config)
-    })?;
+    Ok(response
+        .add_attribute("action", "cash_out")
+        .add_attribute("sender", sender)
+        .add_attribute("dira_cashed_out", dira_to_cash_out.to_string()))
+}
 
-    Ok(Response::new()
-        .add_attribute("action", "enable_fee")
-        .add_attribute("fee_enabled", "true"))
+/// Load a collateral type from the registry for a query.
+fn query_helper_load_collateral_info(deps: Deps, collateral_id: &str) -> StdResult<CollateralInfo> {
+    COLLATERALS
+        .may_load(deps.storage, collateral_id)?
+        .ok_or_else(|| StdError::not_found(format!("collateral {}", collateral_id)))
 }
 
+/// Get the price of a collateral type in dirham, from the admin or its oracle.
+fn query_helper_collateral_price(deps: Deps, env: &Env, collateral_id: &str) -> StdResult<Decimal> {
+    let collateral_info = query_helper_load_collateral_info(deps, collateral_id)?;
 
-fn execute_disable_fee_switch_state(
-    deps: DepsMut,
-    info: MessageInfo,
-) -> Result<Response, ContractError> {
-    let admins = ADMIN_ADDRESSES.load(deps.storage)?;
+    helper_collateral_price(deps, env.block.time, collateral_id, &collateral_info).map_err(
+        |error| match error {
+            ContractError::CollateralPriceNotSet {} => StdError::not_found("collateral_price"),
+            error => StdError::generic_err(error.to_string()),
+        },
+    )
+}
 
-    if !admins.contains(&info.sender) {
-        return Err(ContractError::UnauthorizedUser {});
-    }
+/// Load the stability fee rate of a collateral type as it would be at the current block.
+fn query_helper_stability_fee_rate(deps: Deps, env: &Env, collateral_id: &str) -> StdResult<Decimal> {
+    helper_stability_fee_rate(deps.storage, collateral_id, env.block.time)
+        .map(|stability_fee_rate| stability_fee_rate.rate)
+        .map_err(|error| StdError::generic_err(error.to_string()))
+}
 
-    FEE_SWITCH.update(deps.storage, |mut config| -> Result<_, ContractError> {
-        config.enabled = false;
-        Ok(config)
-    })?;
+/// Query the yearly stability fee of a collateral type and its rate at the current block.
+fn query_stability_fee(deps: Deps, env: Env, collateral_id: String) -> StdResult<Binary> {
+    let stability_fee = query_helper_load_collateral_info(deps, &collateral_id)?.stability_fee;
+    let rate = query_helper_stability_fee_rate(deps, &env, &collateral_id)?;
+    let unminted_stability_fees = UNMINTED_STABILITY_FEES
+        .may_load(deps.storage)?
+        .unwrap_or_default();
 
-    Ok(Response::new()
-        .add_attribute("action", "disable_fee")
-        .add_attribute("fee_enabled", "false"))
+    to_json_binary(&StabilityFeeResponse {
+        stability_fee,
+        rate,
+        unminted_stability_fees,
+    })
 }
 
-/// Query the price of the collateral in dirham
-fn query_collateral_price(deps: Deps) -> StdResult<Binary> {
-    let collateral_price = COLLATERAL_TOKEN_PRICE
-        .may_load(deps.storage)?
-        .ok_or_else(|| StdError::not_found("collateral_price"))?;
+/// Query the debt auctions could not raise that has not been written off yet.
+fn query_bad_debt(deps: Deps) -> StdResult<Binary> {
+    let bad_debt = BAD_DEBT.may_load(deps.storage)?.unwrap_or_default();
+
+    to_json_binary(&BadDebtResponse { bad_debt })
+}
+
+/// Query the price of a collateral type in dirham, from the admin or its oracle
+fn query_collateral_price(deps: Deps, env: Env, collateral_id: String) -> StdResult<Binary> {
+    let collateral_price = query_helper_collateral_price(deps, &env, &collateral_id)?;
 
     let response = CollateralPriceResponse { collateral_price };
 
     to_json_binary(&response)
 }
 
+/// Query the admin set price of a collateral type in use and the next one waiting out the delay.
+fn query_next_collateral_price(deps: Deps, env: Env, collateral_id: String) -> StdResult<Binary> {
+    let collateral_info = query_helper_load_collateral_info(deps, &collateral_id)?;
+
+    let current_price =
+        helper_manual_collateral_price(deps.storage, env.block.time, &collateral_id, &collateral_info)
+            .map_err(|error| StdError::generic_err(error.to_string()))?;
+    let next_price = NEXT_COLLATERAL_PRICES
+        .may_load(deps.storage, &collateral_id)?
+        .filter(|next_price| next_price.effective_at > env.block.time);
+
+    to_json_binary(&NextCollateralPriceResponse {
+        current_price,
+        next_price: next_price.as_ref().map(|next_price| next_price.price),
+        next_price_effective_at: next_price.map(|next_price| next_price.effective_at),
+    })
+}
+
+/// Query how admin set prices are guarded against fat-fingered updates.
+fn query_price_guard_config(deps: Deps) -> StdResult<Binary> {
+    let price_guard_config = CONFIG.load(deps.storage)?.price_guard_config;
+
+    to_json_binary(&PriceGuardConfigResponse {
+        max_price_deviation: price_guard_config.max_price_deviation,
+        deviation_window_seconds: price_guard_config.deviation_window_seconds,
+        price_delay_seconds: price_guard_config.price_delay_seconds,
+    })
+}
+
+/// Query the recorded prices of a collateral type, oldest first, starting after a given time.
+fn query_price_history(
+    deps: Deps,
+    env: Env,
+    collateral_id: String,
+    start_after: Option<Timestamp>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    query_helper_load_collateral_info(deps, &collateral_id)?;
+
+    let limit = limit
+        .unwrap_or(DEFAULT_PRICE_HISTORY_LIMIT)
+        .min(MAX_PRICE_HISTORY_LIMIT) as usize;
+
+    // Next prices still waiting out the price delay are not part of the history yet
+    let prices = PRICE_HISTORY
+        .prefix(&collateral_id)
+        .range(
+            deps.storage,
+            start_after.map(|start_after| Bound::exclusive(start_after.seconds())),
+            Some(Bound::inclusive(env.block.time.seconds())),
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|entry| {
+            let (time, price) = entry?;
+
+            Ok(PriceHistoryEntry {
+                time: Timestamp::from_seconds(time),
+                price,
+            })
+        })
+        .collect::<StdResult<Vec<PriceHistoryEntry>>>()?;
+
+    to_json_binary(&PriceHistoryResponse { prices })
+}
+
+/// Query the price of a collateral type that was in use at a given time.
+fn query_price_at(
+    deps: Deps,
+    env: Env,
+    collateral_id: String,
+    time: Timestamp,
+) -> StdResult<Binary> {
+    query_helper_load_collateral_info(deps, &collateral_id)?;
+
+    let (recorded_at, price) = PRICE_HISTORY
+        .prefix(&collateral_id)
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive(time.min(env.block.time).seconds())),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?
+        .ok_or_else(|| StdError::not_found("price_history"))?;
+
+    to_json_binary(&PriceHistoryEntry {
+        time: Timestamp::from_seconds(recorded_at),
+        price,
+    })
+}
+
 /// Query the locked collateral of a given wallet address.
-fn query_locked_collateral(deps: Deps, wallet_address_to_query: Addr) -> StdResult<Binary> {
+fn query_locked_collateral(
+    deps: Deps,
+    wallet_address_to_query: Addr,
+    collateral_id: String,
+) -> StdResult<Binary> {
     let locked_collateral = LOCKED_COLLATERAL
-        .may_load(deps.storage, wallet_address_to_query.clone())?
+        .may_load(deps.storage, (&wallet_address_to_query, &collateral_id))?
         .unwrap_or_default();
 
     to_json_binary(&CollateralResponse {
@@ -754,49 +5812,100 @@ fn query_locked_collateral(deps: Deps, wallet_address_to_query: Addr) -> StdResu
 }
 
 /// Query the amount of DIRA minted by a given wallet address.
-fn query_minted_dira(deps: Deps, wallet_address_to_query: Addr) -> StdResult<Binary> {
-    let dira_minted = MINTED_DIRA
-        .may_load(deps.storage, wallet_address_to_query.clone())?
-        .unwrap_or_default();
-
-    to_json_binary(&MintedDiraResponse { dira_minted })
+fn query_minted_dira(
+    deps: Deps,
+    env: Env,
+    wallet_address_to_query: Addr,
+    collateral_id: String,
+) -> StdResult<Binary> {
+    let rate = query_helper_stability_fee_rate(deps, &env, &collateral_id)?;
+    let dira_minted =
+        helper_load_dira_owed(deps.storage, &wallet_address_to_query, &collateral_id, rate)?;
+    let principal_dira =
+        helper_load_principal_dira(deps.storage, &wallet_address_to_query, &collateral_id)?;
+
+    to_json_binary(&MintedDiraResponse {
+        dira_minted,
+        accrued_stability_fee: dira_minted.saturating_sub(principal_dira),
+    })
 }
 
 /// Query the stablecoin health of a specific minter.
 fn query_stablecoin_health(
     deps: Deps,
+    env: Env,
     stablecoin_minter_address_to_query: Addr,
+    collateral_id: String,
 ) -> StdResult<Binary> {
     let locked_collateral = LOCKED_COLLATERAL
-        .may_load(deps.storage, stablecoin_minter_address_to_query.clone())?
+        .may_load(
+            deps.storage,
+            (&stablecoin_minter_address_to_query, &collateral_id),
+        )?
         .unwrap_or_default();
 
-    let minted_dira = MINTED_DIRA
-        .may_load(deps.storage, stablecoin_minter_address_to_query.clone())?
-        .unwrap_or_default();
+    let rate = query_helper_stability_fee_rate(deps, &env, &collateral_id)?;
+    let minted_dira = helper_load_dira_owed(
+        deps.storage,
+        &stablecoin_minter_address_to_query,
+        &collateral_id,
+        rate,
+    )?;
+    let principal_dira = helper_load_principal_dira(
+        deps.storage,
+        &stablecoin_minter_address_to_query,
+        &collateral_id,
+    )?;
 
-    let collateral_price = COLLATERAL_TOKEN_PRICE.load(deps.storage)?;
+    let collateral_price = query_helper_collateral_price(deps, &env, &collateral_id)?;
 
     let health =
         helper_calculate_stablecoin_health(minted_dira, locked_collateral, collateral_price);
 
-    to_json_binary(&StablecoinHealthResponse { health })
+    to_json_binary(&StablecoinHealthResponse {
+        health,
+        accrued_stability_fee: minted_dira.saturating_sub(principal_dira),
+    })
 }
 
 /// Query the current liquidation health threshold.
-fn query_liquidation_health(deps: Deps) -> StdResult<Binary> {
-    let liquidation_health = LIQUIDATION_HEALTH.load(deps.storage)?;
+fn query_liquidation_health(deps: Deps, collateral_id: String) -> StdResult<Binary> {
+    let liquidation_health =
+        query_helper_load_collateral_info(deps, &collateral_id)?.liquidation_health;
 
     to_json_binary(&LiquidationHealthResponse { liquidation_health })
 }
 
 /// Query the current mintable health threshold.
-fn query_mintable_health(deps: Deps) -> StdResult<Binary> {
-    let mintable_health = MINTABLE_HEALTH.load(deps.storage)?;
+fn query_mintable_health(deps: Deps, collateral_id: String) -> StdResult<Binary> {
+    let mintable_health = query_helper_load_collateral_info(deps, &collateral_id)?.mintable_health;
 
     to_json_binary(&MintableHealthResponse { mintable_health })
 }
 
+/// Query the share of a position's debt that a single liquidation can repay.
+fn query_close_factor(deps: Deps) -> StdResult<Binary> {
+    let close_factor = CONFIG.load(deps.storage)?.close_factor;
+
+    to_json_binary(&CloseFactorResponse { close_factor })
+}
+
+/// Query the bonus collateral a liquidator seizes on top of the repaid debt.
+fn query_liquidation_bonus(deps: Deps) -> StdResult<Binary> {
+    let liquidation_bonus = CONFIG.load(deps.storage)?.liquidation_bonus;
+
+    to_json_binary(&LiquidationBonusResponse { liquidation_bonus })
+}
+
+/// Query the share of the liquidation bonus that is paid to the treasury.
+fn query_liquidation_protocol_share(deps: Deps) -> StdResult<Binary> {
+    let liquidation_protocol_share = CONFIG.load(deps.storage)?.liquidation_protocol_share;
+
+    to_json_binary(&LiquidationProtocolShareResponse {
+        liquidation_protocol_share,
+    })
+}
+
 /// Query the list of admin addresses.
 fn query_admin_addresses(deps: Deps) -> StdResult<Binary> {
     let admin_addresses = ADMIN_ADDRESSES.load(deps.storage)?;
@@ -804,18 +5913,213 @@ fn query_admin_addresses(deps: Deps) -> StdResult<Binary> {
     to_json_binary(&AdminAddressesResponse { admin_addresses })
 }
 
-/// Query the collateral token denom allowed by the contract.
-fn query_collateral_token_denom(deps: Deps) -> StdResult<Binary> {
-    let collateral_token_denom = COLLATERAL_TOKEN_DENOM.load(deps.storage)?;
+/// Query the owner of the contract and any ownership transfer waiting to be accepted.
+fn query_ownership(deps: Deps, env: Env) -> StdResult<Binary> {
+    let owner = ADMIN_ADDRESSES
+        .load(deps.storage)?
+        .first()
+        .cloned()
+        .ok_or_else(|| StdError::not_found("owner"))?;
+
+    // Expired transfers can no longer be accepted
+    let pending_ownership_transfer = PENDING_OWNERSHIP_TRANSFER
+        .may_load(deps.storage)?
+        .filter(|pending_ownership_transfer| pending_ownership_transfer.expires_at > env.block.time);
+
+    to_json_binary(&OwnershipResponse {
+        owner,
+        pending_owner: pending_ownership_transfer
+            .as_ref()
+            .map(|pending_ownership_transfer| pending_ownership_transfer.new_owner.clone()),
+        pending_expires_at: pending_ownership_transfer
+            .map(|pending_ownership_transfer| pending_ownership_transfer.expires_at),
+    })
+}
+
+/// Query a queued parameter change.
+fn query_queued_change(deps: Deps, id: u64) -> StdResult<Binary> {
+    let queued_change = QUEUED_CHANGES.load(deps.storage, id)?;
+
+    to_json_binary(&query_helper_queued_change_response(id, queued_change))
+}
+
+/// Query all queued parameter changes, oldest first, and the timelock delay.
+fn query_queued_changes(deps: Deps) -> StdResult<Binary> {
+    let changes = QUEUED_CHANGES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|entry| {
+            let (id, queued_change) = entry?;
+            Ok(query_helper_queued_change_response(id, queued_change))
+        })
+        .collect::<StdResult<Vec<QueuedChangeResponse>>>()?;
+
+    to_json_binary(&QueuedChangesResponse {
+        timelock_delay_seconds: CONFIG.load(deps.storage)?.timelock_delay_seconds,
+        changes,
+    })
+}
+
+// Function to build the query response of a queued parameter change
+fn query_helper_queued_change_response(id: u64, queued_change: QueuedChange) -> QueuedChangeResponse {
+    QueuedChangeResponse {
+        id,
+        change: queued_change.change,
+        proposer: queued_change.proposer,
+        eta: queued_change.eta,
+        expires_at: queued_change.eta.plus_seconds(TIMELOCK_GRACE_PERIOD_SECONDS),
+    }
+}
+
+/// Query which operations are paused and until when.
+fn query_pause_state(deps: Deps, env: Env) -> StdResult<Binary> {
+    let flags = helper_active_pause_flags(deps.storage, env.block.time)?;
+    let paused_until = PAUSE_STATE
+        .may_load(deps.storage)?
+        .map(|pause_state| pause_state.paused_until)
+        .filter(|paused_until| env.block.time < *paused_until);
+
+    to_json_binary(&PauseStateResponse {
+        flags,
+        paused_until,
+    })
+}
+
+/// Query the roles granted to an address, or to every address when none is given.
+fn query_roles(deps: Deps, address: Option<Addr>) -> StdResult<Binary> {
+    let mut roles = vec![];
+
+    for role in Role::ALL.iter() {
+        match &address {
+            Some(address) => {
+                if ROLES.has(deps.storage, (role.as_str(), address)) {
+                    roles.push(RoleGrant {
+                        role: role.clone(),
+                        address: address.clone(),
+                    });
+                }
+            }
+            None => {
+                for address in ROLES
+                    .prefix(role.as_str())
+                    .keys(deps.storage, None, None, Order::Ascending)
+                {
+                    roles.push(RoleGrant {
+                        role: role.clone(),
+                        address: address?,
+                    });
+                }
+            }
+        }
+    }
+
+    to_json_binary(&RolesResponse { roles })
+}
+
+/// Query the price feeders and how many fresh prices they have to agree on.
+fn query_price_feeders(deps: Deps) -> StdResult<Binary> {
+    let price_feeders = PRICE_FEEDERS.load(deps.storage)?;
+    let price_feed_config = CONFIG.load(deps.storage)?.price_feed_config;
+
+    to_json_binary(&PriceFeedersResponse {
+        price_feeders,
+        quorum: price_feed_config.quorum,
+        max_price_age_seconds: price_feed_config.max_price_age_seconds,
+    })
+}
+
+/// Query the prices the current price feeders submitted for a collateral type.
+fn query_feeder_prices(deps: Deps, env: Env, collateral_id: String) -> StdResult<Binary> {
+    let price_feeders = PRICE_FEEDERS.load(deps.storage)?;
+    let price_feed_config = CONFIG.load(deps.storage)?.price_feed_config;
+
+    let mut prices = vec![];
+
+    for feeder_address in price_feeders {
+        if let Some(feeder_price) = FEEDER_PRICES.may_load(deps.storage, (&collateral_id, &feeder_address))? {
+            prices.push(FeederPriceResponse {
+                stale: helper_is_feeder_price_stale(&feeder_price, &price_feed_config, env.block.time),
+                feeder_address,
+                price: feeder_price.price,
+                submitted_at: feeder_price.submitted_at,
+            });
+        }
+    }
+
+    to_json_binary(&FeederPricesResponse { prices })
+}
+
+/// Query a single collateral type from the registry.
+fn query_collateral_info(deps: Deps, env: Env, collateral_id: String) -> StdResult<Binary> {
+    let collateral_info = query_helper_load_collateral_info(deps, &collateral_id)?;
+    let total_dira_minted = TOTAL_MINTED_DIRA
+        .may_load(deps.storage, &collateral_id)?
+        .unwrap_or_default()
+        * query_helper_stability_fee_rate(deps, &env, &collateral_id)?;
+
+    to_json_binary(&CollateralInfoResponse {
+        collateral_id,
+        collateral_info,
+        total_dira_minted,
+    })
+}
+
+/// Query every collateral type registered with the contract.
+fn query_collaterals(deps: Deps, env: Env) -> StdResult<Binary> {
+    to_json_binary(&CollateralsResponse {
+        collaterals: query_helper_collaterals(deps, &env)?,
+    })
+}
 
-    to_json_binary(&CollateralTokenDenomResponse {
-        collateral_token_denom,
+/// Query the contract's parameters and collateral types in one go.
+fn query_config(deps: Deps, env: Env) -> StdResult<Binary> {
+    let admin_addresses = ADMIN_ADDRESSES.load(deps.storage)?;
+    let owner = admin_addresses
+        .first()
+        .cloned()
+        .ok_or_else(|| StdError::not_found("owner"))?;
+    let config = CONFIG.load(deps.storage)?;
+
+    to_json_binary(&ConfigResponse {
+        owner,
+        admin_addresses,
+        cw20_dira_contract_address: config.cw20_dira_contract_address,
+        fee_switch: config.fee_switch,
+        close_factor: config.close_factor,
+        liquidation_bonus: config.liquidation_bonus,
+        liquidation_protocol_share: config.liquidation_protocol_share,
+        savings_rate: config.savings_rate,
+        auction_config: config.auction_config,
+        price_guard_config: config.price_guard_config,
+        price_feed_config: config.price_feed_config,
+        price_feeders: PRICE_FEEDERS.load(deps.storage)?,
+        timelock_delay_seconds: config.timelock_delay_seconds,
+        collaterals: query_helper_collaterals(deps, &env)?,
     })
 }
 
+/// Load every registered collateral type, with the DIRA minted against it, for a query.
+fn query_helper_collaterals(deps: Deps, env: &Env) -> StdResult<Vec<CollateralInfoResponse>> {
+    COLLATERALS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (collateral_id, collateral_info) = item?;
+            let total_dira_minted = TOTAL_MINTED_DIRA
+                .may_load(deps.storage, &collateral_id)?
+                .unwrap_or_default()
+                * query_helper_stability_fee_rate(deps, env, &collateral_id)?;
+
+            Ok(CollateralInfoResponse {
+                collateral_id,
+                collateral_info,
+                total_dira_minted,
+            })
+        })
+        .collect()
+}
+
 /// Query the CW20 DIRA contract address set in the contract.
 fn query_cw20_dira_contract_address(deps: Deps) -> StdResult<Binary> {
-    let cw20_dira_contract_address = CW20_DIRA_CONTRACT_ADDRESS.may_load(deps.storage)?.clone();
+    let cw20_dira_contract_address = CONFIG.load(deps.storage)?.cw20_dira_contract_address;
 
     to_json_binary(&CW20DiraContractAddressResponse {
         cw20_dira_contract_address,
@@ -824,6 +6128,383 @@ fn query_cw20_dira_contract_address(deps: Deps) -> StdResult<Binary> {
 
 /// Query the Fee Switch Config state
 fn query_fee_config_state(deps: Deps) -> StdResult<Binary> {
-    let fee_config = FEE_SWITCH.load(deps.storage)?;
+    let fee_config = CONFIG.load(deps.storage)?.fee_switch;
     to_json_binary(&fee_config)
-}
\ No newline at end of file
+}
+
+/// Query the total DIRA in the stability pool and its running product.
+fn query_stability_pool(deps: Deps) -> StdResult<Binary> {
+    let stability_pool = STABILITY_POOL.load(deps.storage)?;
+
+    to_json_binary(&StabilityPoolResponse {
+        total_deposits: stability_pool.total_deposits,
+        product: stability_pool.product,
+        epoch: stability_pool.epoch,
+        scale: stability_pool.scale,
+    })
+}
+
+/// Query a depositor's stability pool deposit after liquidation losses.
+fn query_stability_pool_deposit(deps: Deps, depositor: Addr) -> StdResult<Binary> {
+    let stability_pool = STABILITY_POOL.load(deps.storage)?;
+
+    let response = match STABILITY_POOL_DEPOSITS.may_load(deps.storage, &depositor)? {
+        Some(deposit) => StabilityPoolDepositResponse {
+            initial_deposit: deposit.initial_deposit,
+            compounded_deposit: helper_compounded_stability_pool_deposit(
+                &stability_pool,
+                &deposit,
+            )
+            .map_err(|error| StdError::generic_err(error.to_string()))?,
+        },
+        None => StabilityPoolDepositResponse {
+            initial_deposit: Decimal::zero(),
+            compounded_deposit: Decimal::zero(),
+        },
+    };
+
+    to_json_binary(&response)
+}
+
+/// Query the collateral a depositor has gained from liquidations and not claimed yet.
+fn query_stability_pool_gains(deps: Deps, depositor: Addr) -> StdResult<Binary> {
+    let collateral_gains = match STABILITY_POOL_DEPOSITS.may_load(deps.storage, &depositor)? {
+        Some(deposit) => helper_stability_pool_collateral_gains(deps.storage, &depositor, &deposit)
+            .map_err(|error| StdError::generic_err(error.to_string()))?
+            .into_iter()
+            .map(|(collateral_id, _collateral_info, amount)| CollateralGain {
+                collateral_id,
+                amount,
+            })
+            .collect(),
+        None => vec![],
+    };
+
+    to_json_binary(&StabilityPoolGainsResponse { collateral_gains })
+}
+
+/// Query how auction prices decay and when auctions have to be reset.
+fn query_auction_config(deps: Deps) -> StdResult<Binary> {
+    let auction_config = CONFIG.load(deps.storage)?.auction_config;
+
+    to_json_binary(&AuctionConfigResponse {
+        starting_premium: auction_config.starting_premium,
+        price_decay_seconds: auction_config.price_decay_seconds,
+        reset_after_seconds: auction_config.reset_after_seconds,
+    })
+}
+
+/// Build the response for an auction, pricing it at the current block time.
+fn query_helper_auction_response(
+    auction_id: u64,
+    auction: Auction,
+    auction_config: &AuctionConfig,
+    env: &Env,
+) -> AuctionResponse {
+    AuctionResponse {
+        auction_id,
+        current_price: helper_auction_price(&auction, auction_config, env.block.time),
+        needs_reset: helper_auction_needs_reset(&auction, auction_config, env.block.time),
+        wallet_address: auction.wallet_address,
+        collateral_id: auction.collateral_id,
+        collateral_amount: auction.collateral_amount,
+        dira_to_raise: auction.dira_to_raise,
+        starting_price: auction.starting_price,
+        start_time: auction.start_time,
+    }
+}
+
+/// Query a collateral auction along with its current price.
+fn query_auction(deps: Deps, env: Env, auction_id: u64) -> StdResult<Binary> {
+    let auction = AUCTIONS
+        .may_load(deps.storage, auction_id)?
+        .ok_or_else(|| StdError::not_found(format!("auction {}", auction_id)))?;
+    let auction_config = CONFIG.load(deps.storage)?.auction_config;
+
+    to_json_binary(&query_helper_auction_response(
+        auction_id,
+        auction,
+        &auction_config,
+        &env,
+    ))
+}
+
+/// Query all collateral auctions that are still running.
+fn query_active_auctions(deps: Deps, env: Env) -> StdResult<Binary> {
+    let auction_config = CONFIG.load(deps.storage)?.auction_config;
+
+    let auctions = AUCTIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (auction_id, auction) = item?;
+            Ok(query_helper_auction_response(
+                auction_id,
+                auction,
+                &auction_config,
+                &env,
+            ))
+        })
+        .collect::<StdResult<Vec<AuctionResponse>>>()?;
+
+    to_json_binary(&AuctionsResponse { auctions })
+}
+
+/// Load the savings vault as it would be if it was brought up to date at the current block.
+fn query_helper_savings_vault(deps: Deps, env: &Env) -> StdResult<SavingsVault> {
+    helper_unminted_stability_fees(deps.storage, env.block.time)
+        .and_then(|unminted_stability_fees| {
+            helper_savings_vault(deps.storage, env.block.time, unminted_stability_fees)
+        })
+        .map(|(savings_vault, _)| savings_vault)
+        .map_err(|error| StdError::generic_err(error.to_string()))
+}
+
+/// Query the savings rate, share price and total DIRA held by the savings vault.
+fn query_savings_vault(deps: Deps, env: Env) -> StdResult<Binary> {
+    let savings_rate = CONFIG.load(deps.storage)?.savings_rate;
+    let savings_vault = query_helper_savings_vault(deps, &env)?;
+
+    to_json_binary(&SavingsVaultResponse {
+        savings_rate,
+        share_price: savings_vault.share_price,
+        total_shares: savings_vault.total_shares,
+        total_dira_saved: savings_vault.total_shares * savings_vault.share_price,
+    })
+}
+
+/// Query a depositor's savings shares and the DIRA they can be redeemed for.
+fn query_savings(deps: Deps, env: Env, depositor: Addr) -> StdResult<Binary> {
+    let savings_vault = query_helper_savings_vault(deps, &env)?;
+    let shares = SAVINGS_SHARES
+        .may_load(deps.storage, &depositor)?
+        .unwrap_or_default();
+
+    to_json_binary(&SavingsResponse {
+        shares,
+        redeemable_dira: shares * savings_vault.share_price,
+    })
+}
+
+/// Load a stablecoin accepted by the peg stability module.
+fn query_helper_load_psm_asset(deps: Deps, asset_id: &str) -> StdResult<PsmAsset> {
+    PSM_ASSETS
+        .may_load(deps.storage, asset_id)?
+        .ok_or_else(|| StdError::not_found(format!("psm asset {}", asset_id)))
+}
+
+/// Query a stablecoin accepted by the peg stability module along with its reserves.
+fn query_psm_asset(deps: Deps, asset_id: String) -> StdResult<Binary> {
+    let psm_asset = query_helper_load_psm_asset(deps, &asset_id)?;
+    let reserves = PSM_RESERVES
+        .may_load(deps.storage, &asset_id)?
+        .unwrap_or_default();
+
+    to_json_binary(&PsmAssetResponse {
+        asset_id,
+        psm_asset,
+        reserves,
+    })
+}
+
+/// Query every stablecoin accepted by the peg stability module along with their reserves.
+fn query_psm_assets(deps: Deps) -> StdResult<Binary> {
+    let psm_assets = PSM_ASSETS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (asset_id, psm_asset) = item?;
+            let reserves = PSM_RESERVES
+                .may_load(deps.storage, &asset_id)?
+                .unwrap_or_default();
+
+            Ok(PsmAssetResponse {
+                asset_id,
+                psm_asset,
+                reserves,
+            })
+        })
+        .collect::<StdResult<Vec<PsmAssetResponse>>>()?;
+
+    to_json_binary(&PsmAssetsResponse { psm_assets })
+}
+
+/// Query how much DIRA swapping an amount of a stablecoin would mint, and the fee charged.
+fn query_simulate_swap_stablecoin_for_dira(
+    deps: Deps,
+    asset_id: String,
+    stablecoin_amount: Decimal,
+) -> StdResult<Binary> {
+    let psm_asset = query_helper_load_psm_asset(deps, &asset_id)?;
+    let (amount_out, fee) = helper_simulate_swap_stablecoin_for_dira(&psm_asset, stablecoin_amount);
+
+    to_json_binary(&PsmSwapSimulationResponse { amount_out, fee })
+}
+
+/// Query how much of a stablecoin swapping an amount of DIRA would pay out, and the fee charged.
+fn query_simulate_swap_dira_for_stablecoin(
+    deps: Deps,
+    asset_id: String,
+    dira_amount: Decimal,
+) -> StdResult<Binary> {
+    let psm_asset = query_helper_load_psm_asset(deps, &asset_id)?;
+    let (amount_out, fee) = helper_simulate_swap_dira_for_stablecoin(&psm_asset, dira_amount)
+        .map_err(|error| StdError::generic_err(error.to_string()))?;
+
+    to_json_binary(&PsmSwapSimulationResponse { amount_out, fee })
+}
+
+/// Query the current redemption fee and the base rate it is built on.
+fn query_redemption_fee(deps: Deps, env: Env) -> StdResult<Binary> {
+    let base_rate = helper_redemption_base_rate(deps.storage, env.block.time)
+        .map_err(|error| StdError::generic_err(error.to_string()))?;
+
+    to_json_binary(&RedemptionFeeResponse {
+        base_rate,
+        redemption_fee: helper_redemption_fee(base_rate),
+    })
+}
+
+/// Query the positions of a collateral type in the order redemptions reach them.
+fn query_redemption_queue(
+    deps: Deps,
+    env: Env,
+    collateral_id: String,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let rate = query_helper_stability_fee_rate(deps, &env, &collateral_id)?;
+    let limit = limit
+        .unwrap_or(MAX_REDEMPTION_POSITIONS as u32)
+        .min(MAX_REDEMPTION_POSITIONS as u32) as usize;
+
+    let positions = REDEMPTION_QUEUE
+        .sub_prefix(&collateral_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|key| {
+            let (_, wallet_address) = key?;
+            let collateral_locked = LOCKED_COLLATERAL
+                .may_load(deps.storage, (&wallet_address, &collateral_id))?
+                .unwrap_or_default();
+            let dira_owed = MINTED_DIRA
+                .may_load(deps.storage, (&wallet_address, &collateral_id))?
+                .unwrap_or_default()
+                * rate;
+
+            Ok(RedemptionQueuePosition {
+                wallet_address,
+                collateral_locked,
+                dira_owed,
+            })
+        })
+        .collect::<StdResult<Vec<RedemptionQueuePosition>>>()?;
+
+    to_json_binary(&RedemptionQueueResponse { positions })
+}
+
+/// Query whether the contract was shut down, and how far settlement has come.
+fn query_shutdown(deps: Deps) -> StdResult<Binary> {
+    let shutdown = SHUTDOWN.may_load(deps.storage)?;
+
+    let collaterals = SHUTDOWN_COLLATERALS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|entry| {
+            let (collateral_id, shutdown_collateral) = entry?;
+
+            Ok(ShutdownCollateralResponse {
+                collateral_id,
+                shutdown_collateral,
+            })
+        })
+        .collect::<StdResult<Vec<ShutdownCollateralResponse>>>()?;
+
+    to_json_binary(&ShutdownResponse {
+        shutdown_at: shutdown.as_ref().map(|shutdown| shutdown.shutdown_at),
+        cash_out_opens_at: shutdown.as_ref().map(|shutdown| {
+            shutdown
+                .shutdown_at
+                .plus_seconds(SHUTDOWN_SETTLEMENT_PERIOD_SECONDS)
+        }),
+        dira_supply: shutdown.as_ref().map(|shutdown| shutdown.dira_supply),
+        dira_cashed_out: shutdown.map(|shutdown| shutdown.dira_cashed_out),
+        collaterals,
+    })
+}
+
+/// Query every position, a page at a time, optionally only the ones below a given health.
+fn query_all_positions(
+    deps: Deps,
+    env: Env,
+    start_after: Option<(Addr, String)>,
+    limit: Option<u32>,
+    below_health: Option<Decimal>,
+) -> StdResult<Binary> {
+    let limit = limit
+        .unwrap_or(DEFAULT_POSITIONS_LIMIT)
+        .min(MAX_POSITIONS_LIMIT) as usize;
+
+    let keys = LOCKED_COLLATERAL
+        .keys(
+            deps.storage,
+            start_after
+                .as_ref()
+                .map(|(wallet_address, collateral_id)| Bound::exclusive((wallet_address, collateral_id.as_str()))),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<(Addr, String)>>>()?;
+
+    let last_position = if keys.len() == limit {
+        keys.last().cloned()
+    } else {
+        None
+    };
+
+    // Every position of a collateral type shares its stability fee rate and price
+    let mut collateral_rates_and_prices: BTreeMap<String, (Decimal, Option<Decimal>)> = BTreeMap::new();
+    let mut positions = vec![];
+
+    for (wallet_address, collateral_id) in keys {
+        let (rate, collateral_price) = match collateral_rates_and_prices.get(&collateral_id) {
+            Some(rate_and_price) => *rate_and_price,
+            None => {
+                let rate_and_price = (
+                    query_helper_stability_fee_rate(deps, &env, &collateral_id)?,
+                    query_helper_collateral_price(deps, &env, &collateral_id).ok(),
+                );
+                collateral_rates_and_prices.insert(collateral_id.clone(), rate_and_price);
+                rate_and_price
+            }
+        };
+
+        let collateral_locked = LOCKED_COLLATERAL.load(deps.storage, (&wallet_address, &collateral_id))?;
+        let dira_owed = helper_load_dira_owed(deps.storage, &wallet_address, &collateral_id, rate)?;
+
+        // Positions that were closed keep their entry, with nothing left in them
+        if collateral_locked.is_zero() && dira_owed.is_zero() {
+            continue;
+        }
+
+        let health = collateral_price.map(|collateral_price| {
+            helper_calculate_stablecoin_health(dira_owed, collateral_locked, collateral_price)
+        });
+
+        if let Some(below_health) = below_health {
+            if health.is_none_or(|health| health >= below_health) {
+                continue;
+            }
+        }
+
+        positions.push(PositionResponse {
+            wallet_address,
+            collateral_id,
+            collateral_locked,
+            dira_owed,
+            health,
+        });
+    }
+
+    to_json_binary(&PositionsResponse {
+        positions,
+        last_position,
+    })
+}
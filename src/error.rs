@@ -1,4 +1,4 @@
-use cosmwasm_std::{Decimal, StdError};
+use cosmwasm_std::{Decimal, DecimalRangeExceeded, StdError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,6 +6,9 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    DecimalRangeExceeded(#[from] DecimalRangeExceeded),
+
     #[error("The user is not an admin authorized to perform this action")]
     UnauthorizedUser {},
 
@@ -30,6 +33,42 @@ pub enum ContractError {
     #[error("Collateral price has not been set")]
     CollateralPriceNotSet {},
 
+    #[error("Price cannot be zero")]
+    PriceCannotBeZero {},
+
+    #[error("Price oracle for collateral type {collateral_id} did not return a price")]
+    OraclePriceUnavailable { collateral_id: String },
+
+    #[error("Price moves more than {max_price_deviation} away from the last accepted price within the deviation window")]
+    PriceDeviationTooHigh { max_price_deviation: Decimal },
+
+    #[error("Maximum price deviation has to be higher than zero")]
+    InvalidPriceGuardConfig {},
+
+    #[error("No price observation old enough to compute the TWAP of collateral type {collateral_id}")]
+    TwapUnavailable { collateral_id: String },
+
+    #[error("TWAP window has to be higher than zero")]
+    InvalidTwapWindow {},
+
+    #[error("Collateral type {collateral_id} is not priced by a TWAP")]
+    NotTwapPriced { collateral_id: String },
+
+    #[error("Fewer than {quorum} fresh prices were submitted for collateral type {collateral_id}")]
+    StalePrice { collateral_id: String, quorum: u32 },
+
+    #[error("The user is not a price feeder")]
+    UnauthorizedPriceFeeder {},
+
+    #[error("{feeder_address} is already a price feeder")]
+    PriceFeederAlreadyAdded { feeder_address: cosmwasm_std::Addr },
+
+    #[error("{feeder_address} is not a price feeder")]
+    UnknownPriceFeeder { feeder_address: cosmwasm_std::Addr },
+
+    #[error("Price feed quorum and maximum price age have to be higher than zero")]
+    InvalidPriceFeedConfig {},
+
     #[error("Cannot return more Dira than minted")]
     ReturningMoreDiraThanMinted {},
 
@@ -51,6 +90,156 @@ pub enum ContractError {
     #[error("No admin addresses are set in the contract.")]
     NoAdminAddressesSet {},
 
+    #[error("{admin_address} is already an admin")]
+    AdminAlreadyAdded { admin_address: cosmwasm_std::Addr },
+
+    #[error("{admin_address} is not an admin")]
+    UnknownAdmin { admin_address: cosmwasm_std::Addr },
+
+    #[error("The last admin cannot be removed")]
+    CannotRemoveLastAdmin {},
+
+    #[error("The owner cannot be removed as an admin, ownership has to be transferred first")]
+    CannotRemoveOwner {},
+
+    #[error("Only the owner of the contract can perform this action")]
+    NotOwner {},
+
+    #[error("The user does not have the {role} role needed to perform this action")]
+    MissingRole { role: String },
+
+    #[error("{address} already has the {role} role")]
+    RoleAlreadyGranted { role: String, address: cosmwasm_std::Addr },
+
+    #[error("{address} does not have the {role} role")]
+    RoleNotGranted { role: String, address: cosmwasm_std::Addr },
+
+    #[error("The owner role cannot be revoked from the owner, ownership has to be transferred instead")]
+    CannotRevokeOwnerRole {},
+
+    #[error("No ownership transfer is waiting to be accepted")]
+    NoPendingOwnershipTransfer {},
+
+    #[error("Ownership transfer expired at {expires_at}")]
+    OwnershipTransferExpired { expires_at: cosmwasm_std::Timestamp },
+
+    #[error("Ownership transfer expiry has to be higher than zero and at most {max_expiry_seconds} seconds")]
+    InvalidOwnershipTransferExpiry { max_expiry_seconds: u64 },
+
+    #[error("Collateral type {collateral_id} is not registered")]
+    UnknownCollateral { collateral_id: String },
+
+    #[error("Collateral type {collateral_id} is already registered")]
+    CollateralAlreadyRegistered { collateral_id: String },
+
+    #[error("Amount cannot be zero")]
+    AmountCannotBeZero {},
+
+    #[error("Liquidation repays too much debt. Max repayable dira: {max_repayable}")]
+    LiquidationAmountTooHigh { max_repayable: Decimal },
+
+    #[error("Close factor has to be higher than zero and at most one")]
+    InvalidCloseFactor {},
+
+    #[error("Liquidation bonus has to be lower than one")]
+    InvalidLiquidationBonus {},
+
+    #[error("Liquidation protocol share cannot be higher than one")]
+    InvalidLiquidationProtocolShare {},
+
+    #[error("Withdraw amount too high. Max withdrawable dira: {max_withdrawable}")]
+    StabilityPoolWithdrawTooHigh { max_withdrawable: Decimal },
+
+    #[error("Withdraw amount too high. Max withdrawable dira: {max_withdrawable}")]
+    SavingsWithdrawTooHigh { max_withdrawable: Decimal },
+
+    #[error("Only the CW20 Dira token can be sent with this message")]
+    NotDiraToken {},
+
+    #[error("Collateral type {collateral_id} cannot be transferred this way")]
+    CollateralAssetTypeMismatch { collateral_id: String },
+
+    #[error("Collateral decimals cannot be higher than 18")]
+    InvalidCollateralDecimals {},
+
+    #[error("Debt ceiling reached. Max dira mintable against this collateral: {debt_ceiling}")]
+    DebtCeilingExceeded { debt_ceiling: Decimal },
+
+    #[error("Stablecoin {asset_id} is not accepted by the peg stability module")]
+    UnknownPsmAsset { asset_id: String },
+
+    #[error("Stablecoin {asset_id} is already accepted by the peg stability module")]
+    PsmAssetAlreadyRegistered { asset_id: String },
+
+    #[error("Stablecoin price has to be higher than zero, swap fees at most one and decimals at most 18")]
+    InvalidPsmAsset {},
+
+    #[error("Swap would take the stablecoin reserves over their cap of {reserve_cap}")]
+    PsmReserveCapExceeded { reserve_cap: Decimal },
+
+    #[error("Not enough stablecoin reserves. Available: {available}")]
+    InsufficientPsmReserves { available: Decimal },
+
+    #[error("No position of collateral type {collateral_id} can be redeemed against")]
+    NothingToRedeem { collateral_id: String },
+
+    #[error("Parameter change {id} is not queued")]
+    UnknownQueuedChange { id: u64 },
+
+    #[error("Parameter change {id} cannot be applied before {eta}")]
+    QueuedChangeNotReady { id: u64, eta: cosmwasm_std::Timestamp },
+
+    #[error("Parameter change {id} expired at {expires_at} and has to be queued again")]
+    QueuedChangeExpired { id: u64, expires_at: cosmwasm_std::Timestamp },
+
+    #[error("Timelock delay has to be between {min_timelock_delay_seconds} and {max_timelock_delay_seconds} seconds")]
+    InvalidTimelockDelay { min_timelock_delay_seconds: u64, max_timelock_delay_seconds: u64 },
+
+    #[error("Contract is paused for {operation}")]
+    Paused { operation: String },
+
+    #[error("Pause duration has to be higher than zero and at most {max_pause_duration_seconds} seconds")]
+    InvalidPauseDuration { max_pause_duration_seconds: u64 },
+
+    #[error("The contract was paused too recently, it can be paused again at {available_at}")]
+    PauseCooldown { available_at: cosmwasm_std::Timestamp },
+
+    #[error("No config parameters to update")]
+    EmptyConfigUpdate {},
+
+    #[error("Cannot migrate from contract {contract_name}")]
+    InvalidMigrationContract { contract_name: String },
+
+    #[error("Cannot migrate from version {stored_version} to older version {version}")]
+    CannotMigrateToOlderVersion { stored_version: String, version: String },
+
+    #[error("Invalid contract version {version}")]
+    InvalidContractVersion { version: String },
+
+    #[error("The contract has been shut down")]
+    ShutDown {},
+
+    #[error("The contract has not been shut down")]
+    NotShutDown {},
+
+    #[error("Position of {wallet_address} has nothing left to settle")]
+    NothingToSettle { wallet_address: cosmwasm_std::Addr },
+
+    #[error("Cashing out opens at {opens_at}, or once every position has been settled")]
+    CashOutNotOpen { opens_at: cosmwasm_std::Timestamp },
+
+    #[error("Auction {auction_id} does not exist or is already over")]
+    UnknownAuction { auction_id: u64 },
+
+    #[error("Auction {auction_id} has run too long and has to be reset")]
+    AuctionNeedsReset { auction_id: u64 },
+
+    #[error("Auction {auction_id} cannot be reset yet")]
+    AuctionNotResettable { auction_id: u64 },
+
+    #[error("Auction starting premium has to be at least one, and auctions have to be reset before their price decays to zero")]
+    InvalidAuctionConfig {},
+
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
@@ -1,57 +1,374 @@
 use cosmwasm_schema::QueryResponses;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cosmwasm_std::{Addr, Decimal};
-use crate::state::FeeTier;
+use cosmwasm_std::{Addr, Decimal, Decimal256, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
+use crate::state::{AuctionConfig, CollateralAssetType, CollateralInfo, FeeConfig, FeeTier, ParameterChange, PriceFeedConfig, PriceGuardConfig, PauseFlags, PriceSource, PsmAsset, Role, ShutdownCollateral};
 
 /// InstantiateMsg is used for initializing the contract.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct InstantiateMsg {
+    // Risk parameters of the first collateral type registered with the contract
     pub liquidation_health: Decimal,
     pub mintable_health: Decimal,
     pub collateral_token_denom: String,
     pub cw20_dira_contract_address: Option<Addr>,
 }
 
+/// Parameters to change with ExecuteMsg::UpdateConfig, the ones left out are kept.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct ConfigUpdate {
+    pub cw20_dira_contract_address: Option<Addr>,
+    pub timelock_delay_seconds: Option<u64>,
+    pub collateral_health: Option<CollateralHealthUpdate>,
+    pub debt_ceiling: Option<DebtCeilingUpdate>,
+    pub stability_fee: Option<StabilityFeeUpdate>,
+    pub close_factor: Option<Decimal>,
+    pub liquidation_bonus: Option<Decimal>,
+    pub liquidation_protocol_share: Option<Decimal>,
+    pub fee_switch_enabled: Option<bool>,
+    pub savings_rate: Option<Decimal>,
+    pub auction_config: Option<AuctionConfig>,
+    pub price_guard_config: Option<PriceGuardConfig>,
+    pub price_feed_config: Option<PriceFeedConfig>,
+}
+
+/// Liquidation and mintable health of a collateral type to change together.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CollateralHealthUpdate {
+    pub collateral_id: String,
+    pub liquidation_health: Option<Decimal>,
+    pub mintable_health: Option<Decimal>,
+}
+
+/// Debt ceiling of a collateral type to change, None removes the ceiling.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct DebtCeilingUpdate {
+    pub collateral_id: String,
+    pub debt_ceiling: Option<Decimal>,
+}
+
+/// Yearly stability fee of a collateral type to change.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct StabilityFeeUpdate {
+    pub collateral_id: String,
+    pub stability_fee: Decimal,
+}
+
+/// MigrateMsg is used for upgrading a deployed contract to this version.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {}
+
 /// ExecuteMsg contains all the executable contract endpoints.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     // Lock and unlock collateral
-    LockCollateral {},
+    LockCollateral {
+        collateral_id: String,
+    },
+    Receive(Cw20ReceiveMsg),
     UnlockCollateral {
+        collateral_id: String,
         collateral_amount_to_unlock: Decimal,
     },
 
-    // Mint and burn DIRA stablecoin
+    // Mint DIRA stablecoin, repaying is done by sending DIRA with ReceiveMsg::Repay
     MintDira {
+        collateral_id: String,
         dira_to_mint: Decimal,
     },
-    BurnDira {
-        dira_to_burn: Decimal,
-    },
 
-    // Liquidation
+    // Liquidation, absorbed by the stability pool when it holds enough DIRA. Otherwise the
+    // seized collateral is auctioned off, bids are made by sending DIRA with
+    // ReceiveMsg::BidAuction. Sending DIRA with ReceiveMsg::Liquidate instead repays the
+    // debt right away with the liquidator's DIRA
     LiquidateStablecoins {
         wallet_address_to_liquidate: Addr,
+        collateral_id: String,
+        debt_to_repay: Decimal,
+    },
+
+    // Restart an auction that has run too long at the current collateral price
+    ResetAuction {
+        auction_id: u64,
     },
 
     // Admin functionalities
+    AddAdmin {
+        admin_address: Addr,
+    },
+    RemoveAdmin {
+        admin_address: Addr,
+    },
+    // Ownership is handed over in two steps, the new owner has to accept the transfer
+    // before it expires, at most thirty days later. The previous owner stays on as an admin
+    ProposeOwnershipTransfer {
+        new_owner: Addr,
+        expires_in_seconds: u64,
+    },
+    AcceptOwnershipTransfer {},
+    CancelOwnershipTransfer {},
+    // Roles let a key perform some privileged operations only, see Role
+    GrantRole {
+        role: Role,
+        address: Addr,
+    },
+    RevokeRole {
+        role: Role,
+        address: Addr,
+    },
+    RegisterCollateral {
+        collateral_id: String,
+        asset_type: CollateralAssetType,
+        liquidation_health: Decimal,
+        mintable_health: Decimal,
+        debt_ceiling: Option<Decimal>,
+        decimals: u32,
+    },
     SetCollateralPriceInDirham {
+        collateral_id: String,
         collateral_price_in_dirham: Decimal,
     },
+    // Limit how far admin set prices can move at once and delay them before they are used
+    SetPriceGuardConfig {
+        max_price_deviation: Decimal,
+        deviation_window_seconds: u64,
+        price_delay_seconds: u64,
+    },
+    // Read a collateral's price from an oracle contract or the price feeders, or go back
+    // to the admin set price
+    SetCollateralPriceSource {
+        collateral_id: String,
+        price_source: PriceSource,
+    },
+
+    // Record the cumulative price of a collateral type priced by a TWAP. Anyone can call
+    // this, and it has to be called at least once every TWAP window
+    RecordPriceObservation {
+        collateral_id: String,
+    },
+
+    // Price feeders submit collateral prices, the median of the fresh ones is used
+    // for collateral types priced by the feeders
+    SubmitPrice {
+        collateral_id: String,
+        price: Decimal,
+    },
+    AddPriceFeeder {
+        feeder_address: Addr,
+    },
+    RemovePriceFeeder {
+        feeder_address: Addr,
+    },
+    SetPriceFeedConfig {
+        quorum: u32,
+        max_price_age_seconds: u64,
+    },
+    // Liquidation health, mintable health, the CW20 DIRA contract address and the timelock
+    // delay are queued as parameter changes, which anyone can apply with ExecuteQueued once
+    // the timelock delay has passed
     SetLiquidationHealth {
+        collateral_id: String,
         liquidation_health: Decimal,
     },
     SetMintableHealth {
+        collateral_id: String,
         mintable_health: Decimal,
     },
+    SetTimelockDelay {
+        timelock_delay_seconds: u64,
+    },
+    ExecuteQueued {
+        id: u64,
+    },
+    CancelQueued {
+        id: u64,
+    },
+    // The guardian, holding the Pauser role, pauses operations for a limited time. Pausing
+    // again while paused can add operations but never pushes the deadline out
+    Pause {
+        minting: bool,
+        unlocking: bool,
+        liquidations: bool,
+        price_updates: bool,
+        duration_seconds: u64,
+    },
+    Unpause {},
+    // Shut the contract down for good, freezing collateral prices and stopping minting,
+    // unlocking, liquidations, redemptions and swaps
+    Shutdown {},
+    // After shutdown, net a position's debt against its collateral at the frozen price and
+    // send whatever collateral is left over back to its owner. Anyone can settle a position
+    SettlePosition {
+        wallet_address: Addr,
+        collateral_id: String,
+    },
+    SetDebtCeiling {
+        collateral_id: String,
+        debt_ceiling: Option<Decimal>,
+    },
+    SetStabilityFee {
+        collateral_id: String,
+        stability_fee: Decimal,
+    },
+    SetCloseFactor {
+        close_factor: Decimal,
+    },
+    SetLiquidationBonus {
+        liquidation_bonus: Decimal,
+    },
+    SetLiquidationProtocolShare {
+        liquidation_protocol_share: Decimal,
+    },
+    SetAuctionConfig {
+        starting_premium: Decimal,
+        price_decay_seconds: u64,
+        reset_after_seconds: u64,
+    },
     SetCW20DiraContractAddress {
         cw20_dira_contract_address: Addr,
     },
     EnableFeeSwitch {} ,
     DisableFeeSwitch {},
+    // Change any subset of the contract's parameters at once. Every parameter is checked
+    // before any is applied, and health changes still go through the parameter change queue
+    UpdateConfig(Box<ConfigUpdate>),
+
+    // Stability pool, depositing is done by sending DIRA with ReceiveMsg::DepositStabilityPool
+    WithdrawFromStabilityPool {
+        dira_to_withdraw: Decimal,
+    },
+    ClaimStabilityPoolGains {},
+
+    // Accrue the stability fees of every collateral type, pay the savings interest out of
+    // them and mint the rest to the treasury
+    Drip {},
+
+    // Savings vault, depositing is done by sending DIRA with ReceiveMsg::DepositSavings
+    WithdrawSavings {
+        dira_to_withdraw: Decimal,
+    },
+    SetSavingsRate {
+        savings_rate: Decimal,
+    },
+
+    // Peg stability module. Native stablecoins are swapped for DIRA by sending them along with
+    // this message, CW20 stablecoins by sending them with ReceiveMsg::SwapStablecoinForDira.
+    // DIRA is swapped back by sending it with ReceiveMsg::SwapDiraForStablecoin.
+    // Swaps always use the admin set price_in_dirham of the stablecoin, the oracle and price
+    // feeder sources of collaterals are never read. SetPsmAssetPrice updates go through the same
+    // price guard and price update pause as collateral prices
+    SwapStablecoinForDira {
+        asset_id: String,
+    },
+    RegisterPsmAsset {
+        asset_id: String,
+        asset_type: CollateralAssetType,
+        decimals: u32,
+        price_in_dirham: Decimal,
+        fee_in: Decimal,
+        fee_out: Decimal,
+        reserve_cap: Option<Decimal>,
+    },
+    SetPsmAssetPrice {
+        asset_id: String,
+        price_in_dirham: Decimal,
+    },
+    SetPsmFees {
+        asset_id: String,
+        fee_in: Decimal,
+        fee_out: Decimal,
+    },
+    SetPsmReserveCap {
+        asset_id: String,
+        reserve_cap: Option<Decimal>,
+    },
+}
+
+/// ReceiveMsg contains the hooks that can be passed along when sending CW20 tokens
+/// to the contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    // Lock the sent CW20 tokens as collateral for the sender
+    LockCollateral {},
+    // Repay the sender's dira debt against a collateral type with the sent DIRA
+    Repay {
+        collateral_id: String,
+    },
+    // Liquidate a position, repaying its debt with the sent DIRA
+    Liquidate {
+        wallet_address_to_liquidate: Addr,
+        collateral_id: String,
+    },
+    // Deposit the sent DIRA into the stability pool
+    DepositStabilityPool {},
+    // Deposit the sent DIRA into the savings vault
+    DepositSavings {},
+    // Swap the sent CW20 stablecoins for DIRA through the peg stability module
+    SwapStablecoinForDira {},
+    // Swap the sent DIRA for a stablecoin held by the peg stability module
+    SwapDiraForStablecoin {
+        asset_id: String,
+    },
+    // Redeem the sent DIRA for collateral at face value, taken from the riskiest positions
+    // first. Whatever DIRA cannot be redeemed is sent back
+    Redeem {
+        collateral_id: String,
+    },
+    // Buy up to max_collateral from an auction at its current price with the sent DIRA,
+    // whatever DIRA is not needed is sent back
+    BidAuction {
+        auction_id: u64,
+        max_collateral: Decimal,
+    },
+    // After shutdown, burn the sent DIRA for its pro rata share of the collateral and
+    // stablecoins backing the DIRA still in circulation
+    CashOut {},
+}
+
+/// OracleQueryMsg is the query sent to a collateral's price oracle contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleQueryMsg {
+    // Price of a single token of the denom in dirham, answered with an OraclePriceResponse
+    Price { denom: String },
+}
+
+/// Response expected from a price oracle contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OraclePriceResponse {
+    pub price: Decimal,
+}
+
+/// PairQueryMsg is the query sent to a DEX pair contract used for TWAP prices.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PairQueryMsg {
+    // Cumulative prices of the pair's assets, answered with a CumulativePricesResponse
+    CumulativePrices {},
+}
+
+/// An asset traded by a DEX pair.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetInfo {
+    Token { contract_addr: Addr },
+    NativeToken { denom: String },
+}
+
+/// Response expected from a DEX pair contract. Each entry holds the price of the first asset
+/// in the second one, summed up every second with 6 decimals of precision.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CumulativePricesResponse {
+    pub cumulative_prices: Vec<(AssetInfo, AssetInfo, Uint128)>,
 }
 
 /// QueryMsg contains all queryable contract endpoints.
@@ -60,43 +377,129 @@ pub enum ExecuteMsg {
 #[serde(rename_all = "snake_case")]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
-    /// Query the total collateral locked by a specific wallet address.
+    /// Query the collateral of a given type locked by a specific wallet address.
     #[returns(CollateralResponse)]
     QueryLockedCollateral {
         wallet_address_to_query: Addr,
+        collateral_id: String,
     },
 
-    /// Query the total DIRA stablecoins minted by a specific wallet address.
+    /// Query the DIRA stablecoins minted by a specific wallet address against a collateral type.
     #[returns(MintedDiraResponse)]
     QueryMintedDira {
         wallet_address_to_query: Addr,
+        collateral_id: String,
     },
 
-    /// Query the current health of a stablecoin minter.
+    /// Query the current health of a stablecoin minter for a collateral type.
     #[returns[StablecoinHealthResponse]]
     QueryStablecoinHealth {
         stablecoin_minter_address_to_query: Addr,
+        collateral_id: String,
     },
 
-    /// Query the price of the collateral in dirham.
+    /// Query the price of a collateral type in dirham.
     #[returns(CollateralPriceResponse)]
-    QueryCollateralPrice {},
+    QueryCollateralPrice {
+        collateral_id: String,
+    },
 
-    /// Query the liquidation health threshold.
+    /// Query the admin set price of a collateral type in use and the next one waiting out the delay.
+    #[returns(NextCollateralPriceResponse)]
+    QueryNextCollateralPrice {
+        collateral_id: String,
+    },
+
+    /// Query how admin set prices are guarded against fat-fingered updates.
+    #[returns(PriceGuardConfigResponse)]
+    QueryPriceGuardConfig {},
+
+    /// Query the recorded prices of a collateral type, oldest first.
+    #[returns(PriceHistoryResponse)]
+    QueryPriceHistory {
+        collateral_id: String,
+        start_after: Option<Timestamp>,
+        limit: Option<u32>,
+    },
+
+    /// Query the price of a collateral type that was in use at a given time.
+    #[returns(PriceHistoryEntry)]
+    QueryPriceAt {
+        collateral_id: String,
+        time: Timestamp,
+    },
+
+    /// Query the liquidation health threshold of a collateral type.
     #[returns(LiquidationHealthResponse)]
-    QueryLiquidationHealth {},
+    QueryLiquidationHealth {
+        collateral_id: String,
+    },
 
-    /// Query the mintable health threshold.
+    /// Query the mintable health threshold of a collateral type.
     #[returns(MintableHealthResponse)]
-    QueryMintableHealth {},
+    QueryMintableHealth {
+        collateral_id: String,
+    },
+
+    /// Query the yearly stability fee of a collateral type and its accumulated rate.
+    #[returns(StabilityFeeResponse)]
+    QueryStabilityFee {
+        collateral_id: String,
+    },
+
+    /// Query the debt auctions could not raise that has not been written off yet.
+    #[returns(BadDebtResponse)]
+    QueryBadDebt {},
+
+    /// Query the share of a position's debt that a single liquidation can repay.
+    #[returns(CloseFactorResponse)]
+    QueryCloseFactor {},
+
+    /// Query the bonus collateral a liquidator seizes on top of the repaid debt.
+    #[returns(LiquidationBonusResponse)]
+    QueryLiquidationBonus {},
+
+    /// Query the share of the liquidation bonus that is paid to the treasury.
+    #[returns(LiquidationProtocolShareResponse)]
+    QueryLiquidationProtocolShare {},
 
     /// Query the list of admin addresses.
     #[returns(AdminAddressesResponse)]
     QueryAdminAddresses {},
 
-    /// Query the collateral token denom allowed.
-    #[returns(CollateralTokenDenomResponse)]
-    QueryCollateralTokenDenom {},
+    /// Query the owner of the contract and any ownership transfer waiting to be accepted.
+    #[returns(OwnershipResponse)]
+    QueryOwnership {},
+
+    /// Query the roles granted to an address, or to every address when none is given.
+    #[returns(RolesResponse)]
+    QueryRoles {
+        address: Option<Addr>,
+    },
+
+    /// Query the price feeders and how many fresh prices they have to agree on.
+    #[returns(PriceFeedersResponse)]
+    QueryPriceFeeders {},
+
+    /// Query the prices the feeders submitted for a collateral type.
+    #[returns(FeederPricesResponse)]
+    QueryFeederPrices {
+        collateral_id: String,
+    },
+
+    /// Query the registry entry of a single collateral type.
+    #[returns(CollateralInfoResponse)]
+    QueryCollateralInfo {
+        collateral_id: String,
+    },
+
+    /// Query every collateral type registered with the contract.
+    #[returns(CollateralsResponse)]
+    QueryCollaterals {},
+
+    /// Query the contract's parameters and collateral types in one go.
+    #[returns(ConfigResponse)]
+    QueryConfig {},
 
     /// Query the CW20 DIRA token contract address.
     #[returns(CW20DiraContractAddressResponse)]
@@ -104,9 +507,112 @@ pub enum QueryMsg {
 
     #[returns(FeeConfigResponse)]
     QueryGetFeeConfig {} ,
+
+    /// Query the total DIRA in the stability pool and its running product.
+    #[returns(StabilityPoolResponse)]
+    QueryStabilityPool {},
+
+    /// Query a depositor's stability pool deposit after liquidation losses.
+    #[returns(StabilityPoolDepositResponse)]
+    QueryStabilityPoolDeposit {
+        depositor: Addr,
+    },
+
+    /// Query the collateral a depositor has gained from liquidations and not claimed yet.
+    #[returns(StabilityPoolGainsResponse)]
+    QueryStabilityPoolGains {
+        depositor: Addr,
+    },
+
+    /// Query the savings rate and the current share price of the savings vault.
+    #[returns(SavingsVaultResponse)]
+    QuerySavingsVault {},
+
+    /// Query a depositor's savings shares and the DIRA they can be redeemed for.
+    #[returns(SavingsResponse)]
+    QuerySavings {
+        depositor: Addr,
+    },
+
+    /// Query a stablecoin accepted by the peg stability module and its reserves.
+    #[returns(PsmAssetResponse)]
+    QueryPsmAsset {
+        asset_id: String,
+    },
+
+    /// Query every stablecoin accepted by the peg stability module and their reserves.
+    #[returns(PsmAssetsResponse)]
+    QueryPsmAssets {},
+
+    /// Query how much DIRA swapping an amount of a stablecoin would pay out.
+    #[returns(PsmSwapSimulationResponse)]
+    QuerySimulateSwapStablecoinForDira {
+        asset_id: String,
+        stablecoin_amount: Decimal,
+    },
+
+    /// Query how much of a stablecoin swapping an amount of DIRA would pay out.
+    #[returns(PsmSwapSimulationResponse)]
+    QuerySimulateSwapDiraForStablecoin {
+        asset_id: String,
+        dira_amount: Decimal,
+    },
+
+    /// Query the current redemption fee and the base rate it is built on.
+    #[returns(RedemptionFeeResponse)]
+    QueryRedemptionFee {},
+
+    /// Query the positions of a collateral type in the order redemptions reach them.
+    #[returns(RedemptionQueueResponse)]
+    QueryRedemptionQueue {
+        collateral_id: String,
+        limit: Option<u32>,
+    },
+
+    /// Query every position, ordered by wallet address and collateral type. At most limit
+    /// positions are looked at per page, and only the ones below below_health are returned.
+    /// Pages continue from the last position looked at, which is returned with them
+    #[returns(PositionsResponse)]
+    QueryAllPositions {
+        start_after: Option<(Addr, String)>,
+        limit: Option<u32>,
+        below_health: Option<Decimal>,
+    },
+
+    /// Query how auction prices decay and when auctions have to be reset.
+    #[returns(AuctionConfigResponse)]
+    QueryAuctionConfig {},
+
+    /// Query a collateral auction along with its current price.
+    #[returns(AuctionResponse)]
+    QueryAuction {
+        auction_id: u64,
+    },
+
+    /// Query all collateral auctions that are still running.
+    #[returns(AuctionsResponse)]
+    QueryActiveAuctions {},
+
+    /// Query a queued parameter change.
+    #[returns(QueuedChangeResponse)]
+    QueryQueuedChange {
+        id: u64,
+    },
+
+    /// Query all queued parameter changes and the timelock delay.
+    #[returns(QueuedChangesResponse)]
+    QueryQueuedChanges {},
+
+    /// Query which operations are paused and until when.
+    #[returns(PauseStateResponse)]
+    QueryPauseState {},
+
+    /// Query whether the contract was shut down, and how far settlement has come.
+    #[returns(ShutdownResponse)]
+    QueryShutdown {},
 }
 
-/// Responses for each query
+// Responses for each query
 
 /// Response for querying locked collateral.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -117,13 +623,17 @@ pub struct CollateralResponse {
 /// Response for querying minted DIRA.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct MintedDiraResponse {
+    // Dira owed, including the accrued stability fee
     pub dira_minted: Decimal,
+    pub accrued_stability_fee: Decimal,
 }
 
 /// Response for querying stablecoin health.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StablecoinHealthResponse {
+    // Health of the position with the accrued stability fee counted as debt
     pub health: Decimal,
+    pub accrued_stability_fee: Decimal,
 }
 
 /// Response for querying the collateral price.
@@ -132,6 +642,35 @@ pub struct CollateralPriceResponse {
     pub collateral_price: Decimal,
 }
 
+/// Response for querying the current and next admin set price of a collateral type.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NextCollateralPriceResponse {
+    pub current_price: Option<Decimal>,
+    pub next_price: Option<Decimal>,
+    pub next_price_effective_at: Option<Timestamp>,
+}
+
+/// Response for querying the price guard configuration.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceGuardConfigResponse {
+    pub max_price_deviation: Decimal,
+    pub deviation_window_seconds: u64,
+    pub price_delay_seconds: u64,
+}
+
+/// A recorded collateral price and the time it took effect at.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceHistoryEntry {
+    pub time: Timestamp,
+    pub price: Decimal,
+}
+
+/// Response for querying the price history of a collateral type.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceHistoryResponse {
+    pub prices: Vec<PriceHistoryEntry>,
+}
+
 /// Response for querying the liquidation health threshold.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct LiquidationHealthResponse {
@@ -144,16 +683,161 @@ pub struct MintableHealthResponse {
     pub mintable_health: Decimal,
 }
 
+/// Response for querying the stability fee of a collateral type.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StabilityFeeResponse {
+    pub stability_fee: Decimal,
+    pub rate: Decimal,
+    // Stability fees accrued across all collateral types that Drip has not minted yet
+    pub unminted_stability_fees: Decimal,
+}
+
+/// Response for querying the bad debt left by auctions.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BadDebtResponse {
+    pub bad_debt: Decimal,
+}
+
+/// Response for querying the liquidation close factor.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CloseFactorResponse {
+    pub close_factor: Decimal,
+}
+
+/// Response for querying the liquidation bonus.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LiquidationBonusResponse {
+    pub liquidation_bonus: Decimal,
+}
+
+/// Response for querying the share of the liquidation bonus paid to the treasury.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LiquidationProtocolShareResponse {
+    pub liquidation_protocol_share: Decimal,
+}
+
 /// Response for querying the list of admin addresses.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AdminAddressesResponse {
     pub admin_addresses: Vec<Addr>,
 }
 
-/// Response for querying the collateral token denom.
+/// A role granted to an address.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct CollateralTokenDenomResponse {
-    pub collateral_token_denom: String,
+pub struct RoleGrant {
+    pub role: Role,
+    pub address: Addr,
+}
+
+/// Response for querying granted roles.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RolesResponse {
+    pub roles: Vec<RoleGrant>,
+}
+
+/// Response for querying a queued parameter change.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueuedChangeResponse {
+    pub id: u64,
+    pub change: ParameterChange,
+    pub proposer: Addr,
+    pub eta: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+/// Response for querying all queued parameter changes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueuedChangesResponse {
+    pub timelock_delay_seconds: u64,
+    pub changes: Vec<QueuedChangeResponse>,
+}
+
+/// Response for querying which operations are paused.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PauseStateResponse {
+    pub flags: PauseFlags,
+    pub paused_until: Option<Timestamp>,
+}
+
+/// A collateral type's snapshot and settlement progress after shutdown.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShutdownCollateralResponse {
+    pub collateral_id: String,
+    pub shutdown_collateral: ShutdownCollateral,
+}
+
+/// Response for querying the shutdown of the contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShutdownResponse {
+    pub shutdown_at: Option<Timestamp>,
+    pub cash_out_opens_at: Option<Timestamp>,
+    pub dira_supply: Option<Decimal>,
+    pub dira_cashed_out: Option<Decimal>,
+    pub collaterals: Vec<ShutdownCollateralResponse>,
+}
+
+/// Response for querying the owner of the contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnershipResponse {
+    pub owner: Addr,
+    pub pending_owner: Option<Addr>,
+    pub pending_expires_at: Option<Timestamp>,
+}
+
+/// Response for querying the price feeders.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceFeedersResponse {
+    pub price_feeders: Vec<Addr>,
+    pub quorum: u32,
+    pub max_price_age_seconds: u64,
+}
+
+/// A price submitted by a price feeder.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeederPriceResponse {
+    pub feeder_address: Addr,
+    pub price: Decimal,
+    pub submitted_at: Timestamp,
+    pub stale: bool,
+}
+
+/// Response for querying the prices submitted for a collateral type.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeederPricesResponse {
+    pub prices: Vec<FeederPriceResponse>,
+}
+
+/// Response for querying a collateral type from the registry.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralInfoResponse {
+    pub collateral_id: String,
+    pub collateral_info: CollateralInfo,
+    pub total_dira_minted: Decimal,
+}
+
+/// Response for querying the contract's parameters and collateral types.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: Addr,
+    pub admin_addresses: Vec<Addr>,
+    pub cw20_dira_contract_address: Option<Addr>,
+    pub fee_switch: FeeConfig,
+    pub close_factor: Decimal,
+    pub liquidation_bonus: Decimal,
+    pub liquidation_protocol_share: Decimal,
+    pub savings_rate: Decimal,
+    pub auction_config: AuctionConfig,
+    pub price_guard_config: PriceGuardConfig,
+    pub price_feed_config: PriceFeedConfig,
+    pub price_feeders: Vec<Addr>,
+    pub timelock_delay_seconds: u64,
+    pub collaterals: Vec<CollateralInfoResponse>,
+}
+
+/// Response for querying all registered collateral types.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralsResponse {
+    pub collaterals: Vec<CollateralInfoResponse>,
 }
 
 /// Response for querying the CW20 DIRA contract address.
@@ -168,3 +852,140 @@ pub struct FeeConfigResponse {
     pub fee_enabled: bool,
     pub tier : FeeTier
 }
+
+/// Response for querying the stability pool.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StabilityPoolResponse {
+    pub total_deposits: Decimal,
+    pub product: Decimal256,
+    pub epoch: u64,
+    pub scale: u64,
+}
+
+/// Response for querying a stability pool deposit.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StabilityPoolDepositResponse {
+    pub initial_deposit: Decimal,
+    pub compounded_deposit: Decimal,
+}
+
+/// Collateral of a single type gained by a stability pool depositor.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralGain {
+    pub collateral_id: String,
+    pub amount: Decimal,
+}
+
+/// Response for querying a depositor's stability pool gains.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StabilityPoolGainsResponse {
+    pub collateral_gains: Vec<CollateralGain>,
+}
+
+/// Response for querying the savings vault.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SavingsVaultResponse {
+    pub savings_rate: Decimal,
+    pub share_price: Decimal,
+    pub total_shares: Decimal,
+    pub total_dira_saved: Decimal,
+}
+
+/// Response for querying a depositor's savings.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SavingsResponse {
+    pub shares: Decimal,
+    pub redeemable_dira: Decimal,
+}
+
+/// Response for querying a stablecoin accepted by the peg stability module.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PsmAssetResponse {
+    pub asset_id: String,
+    pub psm_asset: PsmAsset,
+    pub reserves: Decimal,
+}
+
+/// Response for querying all stablecoins accepted by the peg stability module.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PsmAssetsResponse {
+    pub psm_assets: Vec<PsmAssetResponse>,
+}
+
+/// Response for simulating a peg stability module swap.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PsmSwapSimulationResponse {
+    pub amount_out: Decimal,
+    // Fee charged on the swap, in DIRA
+    pub fee: Decimal,
+}
+
+/// Response for querying the redemption fee.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RedemptionFeeResponse {
+    pub base_rate: Decimal,
+    // Share of the redeemed collateral kept as a fee, the base rate plus the fee floor
+    pub redemption_fee: Decimal,
+}
+
+/// A position waiting in the redemption queue.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RedemptionQueuePosition {
+    pub wallet_address: Addr,
+    pub collateral_locked: Decimal,
+    pub dira_owed: Decimal,
+}
+
+/// A position of a wallet against a collateral type.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PositionResponse {
+    pub wallet_address: Addr,
+    pub collateral_id: String,
+    pub collateral_locked: Decimal,
+    // Dira owed, including the accrued stability fee
+    pub dira_owed: Decimal,
+    // Health at the current collateral price, none if the collateral has no price
+    pub health: Option<Decimal>,
+}
+
+/// Response for querying all positions.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PositionsResponse {
+    pub positions: Vec<PositionResponse>,
+    // Last position looked at, to start the next page after. None once every position was looked at
+    pub last_position: Option<(Addr, String)>,
+}
+
+/// Response for querying the redemption queue of a collateral type.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RedemptionQueueResponse {
+    pub positions: Vec<RedemptionQueuePosition>,
+}
+
+/// Response for querying the auction configuration.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuctionConfigResponse {
+    pub starting_premium: Decimal,
+    pub price_decay_seconds: u64,
+    pub reset_after_seconds: u64,
+}
+
+/// Response for querying a collateral auction.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuctionResponse {
+    pub auction_id: u64,
+    pub wallet_address: Addr,
+    pub collateral_id: String,
+    pub collateral_amount: Decimal,
+    pub dira_to_raise: Decimal,
+    pub starting_price: Decimal,
+    pub current_price: Decimal,
+    pub start_time: Timestamp,
+    pub needs_reset: bool,
+}
+
+/// Response for querying the running collateral auctions.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuctionsResponse {
+    pub auctions: Vec<AuctionResponse>,
+}
@@ -96,13 +96,14 @@
 //     }
 // }
 
-use stable_dira::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use stable_dira::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 use cosmwasm_schema::write_api;
  
 fn main() {
     write_api! {
         instantiate: InstantiateMsg,
         execute: ExecuteMsg,
-        query: QueryMsg
+        query: QueryMsg,
+        migrate: MigrateMsg
     }
 }
\ No newline at end of file
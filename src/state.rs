@@ -1,40 +1,280 @@
-use cosmwasm_std::{Addr, Decimal,Uint128};
+use cosmwasm_std::{Addr, Decimal, Decimal256, Empty, Timestamp, Uint128};
 use cosmwasm_schema::cw_serde;
 use cw_storage_plus::Item;
 
-// What token is allowed to be used as collateral for Dira
-pub const COLLATERAL_TOKEN_DENOM: cw_storage_plus::Item<String> =
-    cw_storage_plus::Item::new("native-token-name");
+// Version of the layout the state is stored in, upgraded one version at a time by the
+// state migrations run on migrate. Instances instantiated before it was tracked have none
+pub const STATE_VERSION: Item<u32> = Item::new("state-version");
+
+// Contract wide parameters, read and changed together. Risk parameters of each collateral type
+// are kept in the collateral registry instead
+#[cw_serde]
+pub struct Config {
+    // Contract address of the cw20 Dira token, None until it is set
+    pub cw20_dira_contract_address: Option<Addr>,
+    pub fee_switch: FeeConfig,
+    // Admin changeable, share of a position's debt that can be repaid in a single liquidation
+    pub close_factor: Decimal,
+    // Admin changeable, extra collateral (as a share of the repaid debt's value) that
+    // a liquidator seizes on top of the repaid debt. This is the penalty paid by the
+    // liquidated wallet
+    pub liquidation_bonus: Decimal,
+    // Admin changeable, share of the liquidation bonus that is paid to the treasury
+    // instead of the liquidator
+    pub liquidation_protocol_share: Decimal,
+    // Admin changeable, yearly interest paid to DIRA deposited in the savings vault.
+    // Paid out of the stability fees, so it can never be more than what borrowers pay
+    pub savings_rate: Decimal,
+    pub auction_config: AuctionConfig,
+    pub price_guard_config: PriceGuardConfig,
+    pub price_feed_config: PriceFeedConfig,
+    // How long parameter changes wait in the queue before they can be applied
+    pub timelock_delay_seconds: u64,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
 
 // List of admin addresses that are allowed to change parameters of the contract
 pub const ADMIN_ADDRESSES: cw_storage_plus::Item<Vec<Addr>> =
     cw_storage_plus::Item::new("admin-addresses");
 
-// Admin changeable, below what health of the collateral for the stablecoin can
-// a user's collateral be liquidated?
-pub const LIQUIDATION_HEALTH: cw_storage_plus::Item<Decimal> =
-    cw_storage_plus::Item::new("liquidation-health");
+// The first admin is the owner of the contract, who receives protocol fees and hands
+// ownership over in two steps: the new owner has to accept before the proposal expires
+#[cw_serde]
+pub struct PendingOwnershipTransfer {
+    pub new_owner: Addr,
+    pub expires_at: Timestamp,
+}
 
-// Admin changeable, what is the lowest health at which a user can mint stablecoins
-// has to be higher than the liquidation health
-pub const MINTABLE_HEALTH: cw_storage_plus::Item<Decimal> = 
-    cw_storage_plus::Item::new("mintable-health");
+pub const PENDING_OWNERSHIP_TRANSFER: Item<PendingOwnershipTransfer> =
+    Item::new("pending-ownership-transfer");
 
-// Track collateral locked by each wallet
-pub const LOCKED_COLLATERAL: cw_storage_plus::Map<Addr, Decimal> =
-    cw_storage_plus::Map::new("locked-collaterals");
+// Named roles privileged operations are split into, so that a key can be trusted with
+// some of them only. Admins hold every role, the owner holds the Owner role on top
+#[cw_serde]
+pub enum Role {
+    // Manages admins and roles, and sets the CW20 DIRA contract address
+    Owner,
+    // Sets admin set collateral and stablecoin prices
+    PriceFeeder,
+    // Registers collateral types and stablecoins, and sets their risk parameters and price sources
+    RiskManager,
+    // Sets stability fees, the savings rate, the liquidation protocol share and swap fees,
+    // and toggles the fee switch
+    FeeManager,
+    // Pauses minting, unlocking, liquidations or price updates in an emergency
+    Pauser,
+}
 
-// Track dira minted by each wallet
-pub const MINTED_DIRA: cw_storage_plus::Map<Addr, Decimal> =
-    cw_storage_plus::Map::new("minted-dira");
+impl Role {
+    pub const ALL: [Role; 5] = [
+        Role::Owner,
+        Role::PriceFeeder,
+        Role::RiskManager,
+        Role::FeeManager,
+        Role::Pauser,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Owner => "owner",
+            Role::PriceFeeder => "price_feeder",
+            Role::RiskManager => "risk_manager",
+            Role::FeeManager => "fee_manager",
+            Role::Pauser => "pauser",
+        }
+    }
+}
+
+// Parameter changes that can only be applied once the timelock delay has passed, giving
+// users time to exit before they land
+#[cw_serde]
+pub enum ParameterChange {
+    LiquidationHealth {
+        collateral_id: String,
+        liquidation_health: Decimal,
+    },
+    MintableHealth {
+        collateral_id: String,
+        mintable_health: Decimal,
+    },
+    // Liquidation and mintable health changed together, checked against each other
+    CollateralHealth {
+        collateral_id: String,
+        liquidation_health: Option<Decimal>,
+        mintable_health: Option<Decimal>,
+    },
+    Cw20DiraContractAddress {
+        cw20_dira_contract_address: Addr,
+    },
+    TimelockDelay {
+        timelock_delay_seconds: u64,
+    },
+}
+
+#[cw_serde]
+pub struct QueuedChange {
+    pub change: ParameterChange,
+    pub proposer: Addr,
+    // Earliest time the change can be applied at
+    pub eta: Timestamp,
+}
+
+pub const QUEUED_CHANGES: cw_storage_plus::Map<u64, QueuedChange> =
+    cw_storage_plus::Map::new("queued-changes");
+
+// Number of parameter changes queued so far, used as the id of the next one
+pub const QUEUED_CHANGE_COUNT: Item<u64> = Item::new("queued-change-count");
+
+// Operations that can be paused in an emergency. Locking collateral and repaying DIRA
+// are always allowed
+#[cw_serde]
+#[derive(Default)]
+pub struct PauseFlags {
+    pub minting: bool,
+    pub unlocking: bool,
+    // Liquidations, collateral auctions and redemptions
+    pub liquidations: bool,
+    // Admin set prices, price feeder submissions and TWAP observations
+    pub price_updates: bool,
+}
+
+// Paused operations are unpaused automatically once the deadline has passed
+#[cw_serde]
+pub struct PauseState {
+    pub flags: PauseFlags,
+    pub paused_until: Timestamp,
+}
+
+pub const PAUSE_STATE: Item<PauseState> = Item::new("pause-state");
+
+// Set once the contract has been shut down for good. Prices and stability fees are frozen,
+// positions are settled at the frozen prices and DIRA holders cash out the collateral left
+#[cw_serde]
+pub struct Shutdown {
+    pub shutdown_at: Timestamp,
+    // DIRA supply when the contract was shut down
+    pub dira_supply: Decimal,
+    // DIRA burned for a share of the backing so far
+    pub dira_cashed_out: Decimal,
+}
+
+pub const SHUTDOWN: Item<Shutdown> = Item::new("shutdown");
+
+// Snapshot of a collateral type taken at shutdown, along with how much of its debt has been
+// settled and the collateral netted against it, which backs the DIRA still in circulation
+#[cw_serde]
+pub struct ShutdownCollateral {
+    pub price: Decimal,
+    pub total_debt: Decimal,
+    pub settled_debt: Decimal,
+    pub backing: Decimal,
+}
+
+pub const SHUTDOWN_COLLATERALS: cw_storage_plus::Map<&str, ShutdownCollateral> =
+    cw_storage_plus::Map::new("shutdown-collaterals");
+
+// Roles granted to each address, keyed by role name and address
+pub const ROLES: cw_storage_plus::Map<(&str, &Addr), Empty> = cw_storage_plus::Map::new("roles");
+
+// Whether a collateral type is a native bank token or a CW20 token
+#[cw_serde]
+pub enum CollateralAssetType {
+    // Locked by sending the denom along with LockCollateral
+    Native,
+    // Locked by sending the tokens to the contract through the CW20 Receive hook
+    Cw20,
+}
+
+// Where the price of a collateral type comes from
+#[cw_serde]
+#[derive(Default)]
+pub enum PriceSource {
+    // The price set by an admin through SetCollateralPriceInDirham
+    #[default]
+    Manual,
+    // The price reported by an oracle contract, queried with OracleQueryMsg::Price
+    // whenever the contract needs it
+    Oracle { oracle_address: Addr },
+    // The median of the fresh prices submitted by the price feeders
+    Feeders,
+    // The time weighted average price over the window, read from the cumulative prices of
+    // a DEX pair quoting the collateral in dirham
+    Twap {
+        pair_address: Addr,
+        window_seconds: u64,
+    },
+}
+
+// Risk parameters and price of a single collateral type accepted by the protocol
+#[cw_serde]
+pub struct CollateralInfo {
+    // How the collateral token is transferred in and out of the contract
+    pub asset_type: CollateralAssetType,
+    // Admin changeable, collateral prices in dirham. Only used when the price source is manual
+    pub collateral_price_in_dirham: Option<Decimal>,
+    // Admin changeable, whether the price is set by an admin or read from an oracle
+    #[serde(default)]
+    pub price_source: PriceSource,
+    // Admin changeable, below what health of the collateral for the stablecoin can
+    // a user's collateral be liquidated?
+    pub liquidation_health: Decimal,
+    // Admin changeable, what is the lowest health at which a user can mint stablecoins
+    // has to be higher than the liquidation health
+    pub mintable_health: Decimal,
+    // Admin changeable, maximum amount of dira that can be minted against this
+    // collateral type across all wallets. None means there is no ceiling
+    pub debt_ceiling: Option<Decimal>,
+    // Number of decimals the collateral token uses on chain
+    pub decimals: u32,
+    // Admin changeable, yearly interest charged on the dira minted against this collateral type
+    #[serde(default)]
+    pub stability_fee: Decimal,
+}
 
-// Collateral prices in dirham
-pub const COLLATERAL_TOKEN_PRICE: cw_storage_plus::Item<Decimal> =
-    cw_storage_plus::Item::new("collateral-price");
+// Registry of the tokens that are allowed to be used as collateral for Dira,
+// keyed by collateral id (the denom of a native token, or the contract address
+// of a CW20 token)
+pub const COLLATERALS: cw_storage_plus::Map<&str, CollateralInfo> =
+    cw_storage_plus::Map::new("collaterals");
 
-// Contract address of the cw20 Dira token
-pub const CW20_DIRA_CONTRACT_ADDRESS: cw_storage_plus::Item<Addr> =
-    cw_storage_plus::Item::new("cw20-dira-contract-address");
+// Track collateral locked by each wallet, per collateral type
+pub const LOCKED_COLLATERAL: cw_storage_plus::Map<(&Addr, &str), Decimal> =
+    cw_storage_plus::Map::new("locked-collateral-by-type");
+
+// Track dira minted by each wallet, per collateral type. Stored as normalized debt,
+// the dira owed is this multiplied by the collateral's stability fee rate
+pub const MINTED_DIRA: cw_storage_plus::Map<(&Addr, &str), Decimal> =
+    cw_storage_plus::Map::new("minted-dira-by-type");
+
+// Track the part of each wallet's debt that was actually minted, per collateral type.
+// Whatever is owed on top of it is accrued stability fee
+pub const PRINCIPAL_DIRA: cw_storage_plus::Map<(&Addr, &str), Decimal> =
+    cw_storage_plus::Map::new("principal-dira-by-type");
+
+// Track total dira minted against each collateral type, used to enforce debt ceilings.
+// Stored as normalized debt, like MINTED_DIRA
+pub const TOTAL_MINTED_DIRA: cw_storage_plus::Map<&str, Decimal> =
+    cw_storage_plus::Map::new("total-minted-dira");
+
+// Cumulative stability fee rate of a collateral type. Normalized debt multiplied by the
+// rate is the dira owed, and the rate grows with the stability fee as time passes
+#[cw_serde]
+pub struct StabilityFeeRate {
+    pub rate: Decimal,
+    pub last_updated: Timestamp,
+}
+
+pub const STABILITY_FEE_RATES: cw_storage_plus::Map<&str, StabilityFeeRate> =
+    cw_storage_plus::Map::new("stability-fee-rates");
+
+// Stability fees that have accrued on positions but have not been minted to the treasury yet
+pub const UNMINTED_STABILITY_FEES: cw_storage_plus::Item<Decimal> =
+    cw_storage_plus::Item::new("unminted-stability-fees");
+
+// Debt that auctions could not raise, left as DIRA in circulation without a position backing it.
+// It is written off against the stability fees before they are minted to the treasury
+pub const BAD_DEBT: cw_storage_plus::Item<Decimal> = cw_storage_plus::Item::new("bad-debt");
 
 // Fee Switch Implementation , in Tier basis
 #[cw_serde]
@@ -67,12 +307,244 @@ impl FeeTier {
         pub fn rate(&self) -> Decimal {
         match self {
             FeeTier::Low => Decimal::permille(3),
-            FeeTier::Medium => Decimal :: permille(1_5),
-            FeeTier::High => Decimal::permille(0_5),
+            FeeTier::Medium => Decimal::permille(15),
+            FeeTier::High => Decimal::permille(5),
         }
     }
 }
 
 
-pub const FEE_SWITCH: Item<FeeConfig> = Item::new("fee_switch");
+// Stability pool, DIRA deposited here is burned to absorb liquidations and the
+// seized collateral is shared between depositors pro rata. Uses the product/sum
+// accounting from Liquity so that deposits and gains never have to be iterated over
+#[cw_serde]
+pub struct StabilityPoolState {
+    // DIRA currently held by the pool
+    pub total_deposits: Decimal,
+    // Running product P, by how much a deposit made at the start of the
+    // current epoch and scale has been reduced by liquidations
+    pub product: Decimal256,
+    // Incremented every time a liquidation empties the pool, which wipes out all deposits
+    pub epoch: u64,
+    // Incremented every time P gets too small and is scaled back up
+    pub scale: u64,
+}
+
+pub const STABILITY_POOL: Item<StabilityPoolState> = Item::new("stability-pool");
+
+// Running sum S of collateral gained per unit of DIRA deposited, per epoch, scale and collateral type
+pub const STABILITY_POOL_SUMS: cw_storage_plus::Map<(u64, u64, &str), Decimal256> =
+    cw_storage_plus::Map::new("stability-pool-sums");
+
+// A depositor's stake in the stability pool, along with the P, epoch and scale it was made at
+#[cw_serde]
+pub struct StabilityPoolDeposit {
+    pub initial_deposit: Decimal,
+    pub product: Decimal256,
+    pub epoch: u64,
+    pub scale: u64,
+}
+
+pub const STABILITY_POOL_DEPOSITS: cw_storage_plus::Map<&Addr, StabilityPoolDeposit> =
+    cw_storage_plus::Map::new("stability-pool-deposits");
+
+// Value of S for each collateral type when a depositor last deposited, withdrew or claimed
+pub const STABILITY_POOL_SUM_SNAPSHOTS: cw_storage_plus::Map<(&Addr, &str), Decimal256> =
+    cw_storage_plus::Map::new("stability-pool-sum-snapshots");
+
+// Admin changeable, how the price of an auction's collateral decays over time
+#[cw_serde]
+pub struct AuctionConfig {
+    // Multiplier on the collateral price that an auction starts at
+    pub starting_premium: Decimal,
+    // Seconds it would take for the auction price to decay linearly down to zero
+    pub price_decay_seconds: u64,
+    // Seconds after which an auction has run too long and has to be reset before bidding again
+    pub reset_after_seconds: u64,
+}
+
+// Collateral seized from a liquidated position, offered for DIRA at a decaying price
+#[cw_serde]
+pub struct Auction {
+    // Wallet that was liquidated, it gets back whatever collateral is left once the auction is over
+    pub wallet_address: Addr,
+    pub collateral_id: String,
+    // Collateral left to sell
+    pub collateral_amount: Decimal,
+    // DIRA left to raise, the repaid debt plus the liquidation penalty
+    pub dira_to_raise: Decimal,
+    // Part of the DIRA left to raise that is debt and gets burned, the rest goes to the treasury
+    pub debt_to_repay: Decimal,
+    pub starting_price: Decimal,
+    pub start_time: Timestamp,
+}
+
+pub const AUCTIONS: cw_storage_plus::Map<u64, Auction> = cw_storage_plus::Map::new("auctions");
+
+// Id given to the last auction that was started
+pub const AUCTION_COUNT: Item<u64> = Item::new("auction-count");
+
+// Savings vault, DIRA deposited here is exchanged for shares that grow in value
+// as the savings rate accrues
+#[cw_serde]
+pub struct SavingsVault {
+    pub total_shares: Decimal,
+    // DIRA a single share can be redeemed for
+    pub share_price: Decimal,
+    pub last_updated: Timestamp,
+}
+
+pub const SAVINGS_VAULT: Item<SavingsVault> = Item::new("savings-vault");
+
+// Savings vault shares held by each depositor
+pub const SAVINGS_SHARES: cw_storage_plus::Map<&Addr, Decimal> =
+    cw_storage_plus::Map::new("savings-shares");
+
+// A stablecoin that the peg stability module swaps with DIRA at its dirham price
+#[cw_serde]
+pub struct PsmAsset {
+    // How the stablecoin is transferred in and out of the contract
+    pub asset_type: CollateralAssetType,
+    // Number of decimals the stablecoin uses on chain
+    pub decimals: u32,
+    // Admin changeable, dirham value of a single stablecoin (one for AED stablecoins). This is
+    // the only rate swaps use, it is not read from the oracle or price feeders
+    pub price_in_dirham: Decimal,
+    // Admin changeable, share of the DIRA value charged when swapping the stablecoin in
+    pub fee_in: Decimal,
+    // Admin changeable, share of the DIRA value charged when swapping DIRA for the stablecoin
+    pub fee_out: Decimal,
+    // Admin changeable, most of the stablecoin the module will hold. None means there is no cap
+    pub reserve_cap: Option<Decimal>,
+}
+
+// Registry of the stablecoins accepted by the peg stability module, keyed by asset id
+// (the denom of a native token, or the contract address of a CW20 token)
+pub const PSM_ASSETS: cw_storage_plus::Map<&str, PsmAsset> = cw_storage_plus::Map::new("psm-assets");
+
+// When the price of each stablecoin in the peg stability module was last accepted
+pub const PSM_PRICE_ACCEPTED_AT: cw_storage_plus::Map<&str, Timestamp> =
+    cw_storage_plus::Map::new("psm-price-accepted-at");
+
+// Stablecoins held by the peg stability module, kept apart from the locked collateral
+pub const PSM_RESERVES: cw_storage_plus::Map<&str, Decimal> =
+    cw_storage_plus::Map::new("psm-reserves");
+
+// Positions with debt against each collateral type, ordered by their nominal collateral ratio
+// (collateral locked per unit of normalized debt) so that redemptions reach the riskiest
+// positions first. The ratio is stored as the atomics of a Decimal, which sort like the ratio
+pub const REDEMPTION_QUEUE: cw_storage_plus::Map<(&str, u128, &Addr), Empty> =
+    cw_storage_plus::Map::new("redemption-queue");
+
+// The nominal collateral ratio each position is currently filed under in REDEMPTION_QUEUE
+pub const REDEMPTION_QUEUE_KEYS: cw_storage_plus::Map<(&Addr, &str), u128> =
+    cw_storage_plus::Map::new("redemption-queue-keys");
+
+// Base rate of the redemption fee. It rises with every redemption and decays back over time
+#[cw_serde]
+pub struct RedemptionBaseRate {
+    pub base_rate: Decimal,
+    pub last_updated: Timestamp,
+}
+
+pub const REDEMPTION_BASE_RATE: Item<RedemptionBaseRate> = Item::new("redemption-base-rate");
+
+// Wallets allowed to submit collateral prices. Kept apart from the admins, so that no
+// single key decides the price everyone is liquidated at
+pub const PRICE_FEEDERS: Item<Vec<Addr>> = Item::new("price-feeders");
+
+// How many fresh feeder prices are needed for a collateral price, and how old they can get
+#[cw_serde]
+pub struct PriceFeedConfig {
+    pub quorum: u32,
+    pub max_price_age_seconds: u64,
+}
+
+// Price of a collateral type submitted by a price feeder
+#[cw_serde]
+pub struct FeederPrice {
+    pub price: Decimal,
+    pub submitted_at: Timestamp,
+}
+
+// Latest price submitted by each feeder, per collateral type
+pub const FEEDER_PRICES: cw_storage_plus::Map<(&str, &Addr), FeederPrice> =
+    cw_storage_plus::Map::new("feeder-prices");
+
+// Guards the admin set collateral prices against fat-fingered updates
+#[cw_serde]
+pub struct PriceGuardConfig {
+    // Largest move from the last accepted price allowed within the deviation window
+    pub max_price_deviation: Decimal,
+    // How long after a price is accepted the next one has to stay within the deviation.
+    // Zero turns the circuit breaker off
+    pub deviation_window_seconds: u64,
+    // How long new prices wait as the next price before they are used. Zero applies them at once
+    pub price_delay_seconds: u64,
+}
+
+// A collateral price waiting out the price delay before it is used
+#[cw_serde]
+pub struct NextCollateralPrice {
+    pub price: Decimal,
+    pub effective_at: Timestamp,
+}
+
+pub const NEXT_COLLATERAL_PRICES: cw_storage_plus::Map<&str, NextCollateralPrice> =
+    cw_storage_plus::Map::new("next-collateral-prices");
+
+// When the admin set price of each collateral type was last accepted
+pub const COLLATERAL_PRICE_ACCEPTED_AT: cw_storage_plus::Map<&str, Timestamp> =
+    cw_storage_plus::Map::new("collateral-price-accepted-at");
+
+// Cumulative prices of collateral types priced by a TWAP, keyed by collateral id and the
+// time in seconds they were observed at
+pub const TWAP_OBSERVATIONS: cw_storage_plus::Map<(&str, u64), Uint128> =
+    cw_storage_plus::Map::new("twap-observations");
+
+// Collateral prices as they were put to use, keyed by collateral id and the time in seconds
+// they took effect at. Only the newest entries of each collateral type are kept
+pub const PRICE_HISTORY: cw_storage_plus::Map<(&str, u64), Decimal> =
+    cw_storage_plus::Map::new("price-history");
+
+// Number of entries in the price history of each collateral type
+pub const PRICE_HISTORY_LENGTHS: cw_storage_plus::Map<&str, u32> =
+    cw_storage_plus::Map::new("price-history-lengths");
+
+// Layout of the first release, which accepted a single collateral type and kept its risk
+// parameters and price in their own items. Only read to migrate old instances
+pub const LEGACY_COLLATERAL_TOKEN_DENOM: Item<String> = Item::new("native-token-name");
+
+pub const LEGACY_LIQUIDATION_HEALTH: Item<Decimal> = Item::new("liquidation-health");
+
+pub const LEGACY_MINTABLE_HEALTH: Item<Decimal> = Item::new("mintable-health");
+
+pub const LEGACY_COLLATERAL_TOKEN_PRICE: Item<Decimal> = Item::new("collateral-price");
+
+pub const LEGACY_LOCKED_COLLATERAL: cw_storage_plus::Map<&Addr, Decimal> =
+    cw_storage_plus::Map::new("locked-collaterals");
+
+pub const LEGACY_MINTED_DIRA: cw_storage_plus::Map<&Addr, Decimal> =
+    cw_storage_plus::Map::new("minted-dira");
+
+// Contract parameters as they were kept before the config item, each in an item of its own.
+// Only read to migrate old instances
+pub const LEGACY_CW20_DIRA_CONTRACT_ADDRESS: Item<Addr> = Item::new("cw20-dira-contract-address");
+
+pub const LEGACY_FEE_SWITCH: Item<FeeConfig> = Item::new("fee_switch");
+
+pub const LEGACY_CLOSE_FACTOR: Item<Decimal> = Item::new("close-factor");
+
+pub const LEGACY_LIQUIDATION_BONUS: Item<Decimal> = Item::new("liquidation-bonus");
+
+pub const LEGACY_LIQUIDATION_PROTOCOL_SHARE: Item<Decimal> = Item::new("liquidation-protocol-share");
+
+pub const LEGACY_SAVINGS_RATE: Item<Decimal> = Item::new("savings-rate");
+
+pub const LEGACY_AUCTION_CONFIG: Item<AuctionConfig> = Item::new("auction-config");
+
+pub const LEGACY_PRICE_GUARD_CONFIG: Item<PriceGuardConfig> = Item::new("price-guard-config");
+
+pub const LEGACY_PRICE_FEED_CONFIG: Item<PriceFeedConfig> = Item::new("price-feed-config");
 
+pub const LEGACY_TIMELOCK_DELAY: Item<u64> = Item::new("timelock-delay");
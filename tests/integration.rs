@@ -1,12 +1,25 @@
-use cosmwasm_std::{coins, Addr, Decimal, Uint128};
-use cw20::MinterResponse;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{coin, coins, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Timestamp, Uint128};
+use cosmwasm_std::to_json_binary;
+use cw_storage_plus::Item;
+use cw20::{Cw20Coin, MinterResponse};
 use cw20_base::msg::{ExecuteMsg as Cw20ExecuteMsg, InstantiateMsg as Cw20InstantiateMsg};
 use cw_multi_test::{App, AppBuilder, Contract, ContractWrapper, Executor};
 use stable_dira::msg::{
-    AdminAddressesResponse, CW20DiraContractAddressResponse, CollateralPriceResponse,
-    CollateralResponse, CollateralTokenDenomResponse, ExecuteMsg as DiraExecuteMsg,
-    InstantiateMsg as DiraInstantiateMsg, LiquidationHealthResponse, MintableHealthResponse,
-    MintedDiraResponse, QueryMsg as StableDiraQueryMsg, StablecoinHealthResponse,
+    AdminAddressesResponse, AssetInfo, AuctionResponse, AuctionsResponse, BadDebtResponse, CW20DiraContractAddressResponse, CloseFactorResponse,
+    CollateralInfoResponse, CollateralPriceResponse,
+    CollateralHealthUpdate, CollateralResponse, CollateralsResponse, ConfigResponse, ConfigUpdate, CumulativePricesResponse, DebtCeilingUpdate, ExecuteMsg as DiraExecuteMsg,
+    InstantiateMsg as DiraInstantiateMsg, MigrateMsg as DiraMigrateMsg, LiquidationHealthResponse, MintableHealthResponse,
+    FeederPricesResponse, MintedDiraResponse, NextCollateralPriceResponse, OraclePriceResponse, OracleQueryMsg, OwnershipResponse, PauseStateResponse, PairQueryMsg, PositionsResponse, PriceHistoryEntry, PriceHistoryResponse, PsmAssetResponse, ShutdownResponse, PsmSwapSimulationResponse, QueryMsg as StableDiraQueryMsg, ReceiveMsg as DiraReceiveMsg,
+    QueuedChangeResponse, QueuedChangesResponse, RedemptionFeeResponse, RedemptionQueueResponse, RoleGrant, RolesResponse,
+    SavingsResponse, SavingsVaultResponse,
+    StabilityFeeResponse, StabilityFeeUpdate, StabilityPoolDepositResponse, StabilityPoolGainsResponse, StabilityPoolResponse,
+    StablecoinHealthResponse,
+};
+use stable_dira::state::{
+    CollateralAssetType, FeeConfig, FeeTier, ParameterChange, PauseFlags, PriceSource, Role, ADMIN_ADDRESSES,
+    LEGACY_COLLATERAL_TOKEN_DENOM, LEGACY_COLLATERAL_TOKEN_PRICE, LEGACY_CW20_DIRA_CONTRACT_ADDRESS, LEGACY_FEE_SWITCH,
+    LEGACY_LIQUIDATION_HEALTH, LEGACY_LOCKED_COLLATERAL, LEGACY_MINTABLE_HEALTH, LEGACY_MINTED_DIRA,
 };
 
 // Mock implementation for Dira stablecoin contract
@@ -15,6 +28,51 @@ fn dira_contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
         stable_dira::contract::execute,
         stable_dira::contract::instantiate,
         stable_dira::contract::query,
+    )
+    .with_migrate(stable_dira::contract::migrate);
+    Box::new(contract)
+}
+
+// Mock of the first release of the Dira contract, which accepted a single collateral type and
+// kept positions keyed by wallet only. Instantiating it writes that layout directly
+#[cw_serde]
+struct LegacyDiraInstantiateMsg {
+    contract_version: String,
+    cw20_dira_contract_address: Addr,
+    // Collateral locked and dira minted by each wallet
+    positions: Vec<(Addr, Decimal, Decimal)>,
+}
+
+fn legacy_dira_instantiate(deps: DepsMut, info: MessageInfo, msg: LegacyDiraInstantiateMsg) -> StdResult<Response> {
+    cw2::set_contract_version(deps.storage, "crates.io:cosmwasm-stable-dira", msg.contract_version)?;
+
+    ADMIN_ADDRESSES.save(deps.storage, &vec![info.sender])?;
+    LEGACY_COLLATERAL_TOKEN_DENOM.save(deps.storage, &"uatom".to_string())?;
+    LEGACY_LIQUIDATION_HEALTH.save(deps.storage, &Decimal::from_ratio(110u128, 100u128))?;
+    LEGACY_MINTABLE_HEALTH.save(deps.storage, &Decimal::from_ratio(130u128, 100u128))?;
+    LEGACY_COLLATERAL_TOKEN_PRICE.save(deps.storage, &Decimal::from_ratio(10u128, 1u128))?;
+    LEGACY_CW20_DIRA_CONTRACT_ADDRESS.save(deps.storage, &msg.cw20_dira_contract_address)?;
+    LEGACY_FEE_SWITCH.save(
+        deps.storage,
+        &FeeConfig {
+            enabled: false,
+            tier: FeeTier::Low,
+        },
+    )?;
+
+    for (wallet_address, collateral_locked, dira_minted) in msg.positions {
+        LEGACY_LOCKED_COLLATERAL.save(deps.storage, &wallet_address, &collateral_locked)?;
+        LEGACY_MINTED_DIRA.save(deps.storage, &wallet_address, &dira_minted)?;
+    }
+
+    Ok(Response::new())
+}
+
+fn legacy_dira_contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
+    let contract = ContractWrapper::new(
+        |_: DepsMut, _: Env, _: MessageInfo, _: cosmwasm_std::Empty| -> StdResult<Response> { Ok(Response::new()) },
+        |deps: DepsMut, _: Env, info: MessageInfo, msg: LegacyDiraInstantiateMsg| legacy_dira_instantiate(deps, info, msg),
+        |_: Deps, _: Env, _: cosmwasm_std::Empty| -> StdResult<Binary> { to_json_binary(&cosmwasm_std::Empty {}) },
     );
     Box::new(contract)
 }
@@ -29,6 +87,96 @@ fn cw20_contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
     Box::new(contract)
 }
 
+// Mock price oracle, answering OracleQueryMsg::Price with whatever price was last set
+#[cw_serde]
+struct MockOracleMsg {
+    price: Decimal,
+}
+
+const MOCK_ORACLE_PRICE: Item<Decimal> = Item::new("price");
+
+fn mock_oracle_set_price(deps: DepsMut, msg: MockOracleMsg) -> StdResult<Response> {
+    MOCK_ORACLE_PRICE.save(deps.storage, &msg.price)?;
+    Ok(Response::new())
+}
+
+fn mock_oracle_contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
+    let contract = ContractWrapper::new(
+        |deps: DepsMut, _: Env, _: MessageInfo, msg: MockOracleMsg| mock_oracle_set_price(deps, msg),
+        |deps: DepsMut, _: Env, _: MessageInfo, msg: MockOracleMsg| mock_oracle_set_price(deps, msg),
+        |deps: Deps, _: Env, msg: OracleQueryMsg| -> StdResult<Binary> {
+            match msg {
+                OracleQueryMsg::Price { .. } => to_json_binary(&OraclePriceResponse {
+                    price: MOCK_ORACLE_PRICE.load(deps.storage)?,
+                }),
+            }
+        },
+    );
+    Box::new(contract)
+}
+
+// Mock DEX pair quoting atom in dirham, keeping cumulative prices with 6 decimals of precision
+#[cw_serde]
+struct MockPairMsg {
+    price: Decimal,
+}
+
+#[cw_serde]
+struct MockPairState {
+    price: Decimal,
+    cumulative_price: Uint128,
+    last_updated: u64,
+}
+
+const MOCK_PAIR_STATE: Item<MockPairState> = Item::new("pair");
+
+fn mock_pair_cumulative_price(deps: Deps, env: &Env) -> StdResult<Uint128> {
+    let state = MOCK_PAIR_STATE.may_load(deps.storage)?;
+
+    Ok(state.map_or(Uint128::zero(), |state| {
+        let elapsed_seconds = env.block.time.seconds() - state.last_updated;
+        state.cumulative_price
+            + Uint128::new(1_000_000).mul_floor(state.price) * Uint128::from(elapsed_seconds)
+    }))
+}
+
+fn mock_pair_set_price(deps: DepsMut, env: Env, msg: MockPairMsg) -> StdResult<Response> {
+    let cumulative_price = mock_pair_cumulative_price(deps.as_ref(), &env)?;
+
+    MOCK_PAIR_STATE.save(
+        deps.storage,
+        &MockPairState {
+            price: msg.price,
+            cumulative_price,
+            last_updated: env.block.time.seconds(),
+        },
+    )?;
+    Ok(Response::new())
+}
+
+fn mock_pair_contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
+    let contract = ContractWrapper::new(
+        |deps: DepsMut, env: Env, _: MessageInfo, msg: MockPairMsg| mock_pair_set_price(deps, env, msg),
+        |deps: DepsMut, env: Env, _: MessageInfo, msg: MockPairMsg| mock_pair_set_price(deps, env, msg),
+        |deps: Deps, env: Env, msg: PairQueryMsg| -> StdResult<Binary> {
+            match msg {
+                PairQueryMsg::CumulativePrices {} => to_json_binary(&CumulativePricesResponse {
+                    cumulative_prices: vec![(
+                        AssetInfo::NativeToken {
+                            denom: "uatom".to_string(),
+                        },
+                        AssetInfo::NativeToken {
+                            denom: "uaed".to_string(),
+                        },
+                        mock_pair_cumulative_price(deps, &env)?,
+                    )],
+                }),
+            }
+        },
+    );
+    Box::new(contract)
+}
+
 // Generate Bech32 Address:
 // dbg!(bech32::encode::<bech32::Bech32>(
 //     bech32::Hrp::parse("cosmwasm").unwrap(),
@@ -48,7 +196,10 @@ fn setup_app() -> (App, Addr, Addr, Addr, Addr) {
             .init_balance(
                 storage,
                 &Addr::unchecked("cosmwasm1qypqxpq9qcrsszgszyfpx9q4zct3sxfqx5vwjh"),
-                coins(100_000_000_000_000u128, "uatom"),
+                vec![
+                    coin(100_000_000_000_000u128, "uatom"),
+                    coin(100_000_000_000_000u128, "uom"),
+                ],
             )
             .unwrap();
 
@@ -57,7 +208,10 @@ fn setup_app() -> (App, Addr, Addr, Addr, Addr) {
             .init_balance(
                 storage,
                 &Addr::unchecked("cosmwasm1qypqxpq9qcrsszgszyfpx9q4zct3sy3q8mmchv"),
-                coins(100_000_000_000_000u128, "uatom"),
+                vec![
+                    coin(100_000_000_000_000u128, "uatom"),
+                    coin(100_000_000_000_000u128, "uom"),
+                ],
             )
             .unwrap();
     });
@@ -144,6 +298,7 @@ fn test_admin_functions() {
 
     // Test setting collateral price
     let msg = DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
         collateral_price_in_dirham: Decimal::from_ratio(3309u128, 100u128),
     };
     let res = app.execute_contract(admin.clone(), dira_contract_addr.clone(), &msg, &[]);
@@ -154,6 +309,7 @@ fn test_admin_functions() {
 
     // Test setting mintable health
     let msg = DiraExecuteMsg::SetMintableHealth {
+        collateral_id: "uatom".to_string(),
         mintable_health: Decimal::percent(195),
     };
     let res = app.execute_contract(admin.clone(), dira_contract_addr.clone(), &msg, &[]);
@@ -164,6 +320,7 @@ fn test_admin_functions() {
 
     // Test setting liquidation health
     let msg = DiraExecuteMsg::SetLiquidationHealth {
+        collateral_id: "uatom".to_string(),
         liquidation_health: Decimal::percent(85),
     };
     let res = app.execute_contract(admin.clone(), dira_contract_addr.clone(), &msg, &[]);
@@ -171,6 +328,31 @@ fn test_admin_functions() {
 
     let res = app.execute_contract(non_admin.clone(), dira_contract_addr.clone(), &msg, &[]);
     assert!(res.is_err());
+
+    // Test setting the close factor
+    let msg = DiraExecuteMsg::SetCloseFactor {
+        close_factor: Decimal::percent(25),
+    };
+    let res = app.execute_contract(admin.clone(), dira_contract_addr.clone(), &msg, &[]);
+    assert!(res.is_ok());
+
+    let res = app.execute_contract(non_admin.clone(), dira_contract_addr.clone(), &msg, &[]);
+    assert!(res.is_err());
+
+    let msg = DiraExecuteMsg::SetCloseFactor {
+        close_factor: Decimal::percent(101),
+    };
+    let res = app.execute_contract(admin.clone(), dira_contract_addr.clone(), &msg, &[]);
+    assert!(res.is_err());
+
+    let res: CloseFactorResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract_addr.clone(),
+            &StableDiraQueryMsg::QueryCloseFactor {},
+        )
+        .unwrap();
+    assert_eq!(res.close_factor, Decimal::percent(25));
 }
 
 #[test]
@@ -179,13 +361,16 @@ fn test_lock_unlock_collateral() {
 
     // Set collateral price
     let msg = DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
         collateral_price_in_dirham: Decimal::from_ratio(3309u128, 100u128),
     };
     let res = app.execute_contract(admin.clone(), dira_contract_addr.clone(), &msg, &[]);
     assert!(res.is_ok());
 
     // Lock collateral
-    let msg = DiraExecuteMsg::LockCollateral {};
+    let msg = DiraExecuteMsg::LockCollateral {
+        collateral_id: "uatom".to_string(),
+    };
     let res = app.execute_contract(
         admin.clone(),
         dira_contract_addr.clone(),
@@ -196,6 +381,7 @@ fn test_lock_unlock_collateral() {
 
     // Unlock collateral
     let msg = DiraExecuteMsg::UnlockCollateral {
+        collateral_id: "uatom".to_string(),
         collateral_amount_to_unlock: Decimal::from_atomics(1204u128, 6).unwrap(),
     };
     let res = app.execute_contract(admin.clone(), dira_contract_addr.clone(), &msg, &[]);
@@ -203,6 +389,7 @@ fn test_lock_unlock_collateral() {
 
     // Attempt to unlock too much collateral (should fail)
     let msg = DiraExecuteMsg::UnlockCollateral {
+        collateral_id: "uatom".to_string(),
         collateral_amount_to_unlock: Decimal::from_atomics(1500u128, 6).unwrap(),
     };
     let res = app.execute_contract(admin.clone(), dira_contract_addr.clone(), &msg, &[]);
@@ -217,6 +404,7 @@ fn test_mint_burn_dira() {
 
     // Lock collateral from the admin user
     let set_collateral_price_msg = DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
         collateral_price_in_dirham: Decimal::from_ratio(3309u128, 100u128),
     };
     let res = app.execute_contract(
@@ -228,7 +416,9 @@ fn test_mint_burn_dira() {
     assert!(res.is_ok());
     dbg!("Set collateral price");
 
-    let lock_collateral_msg = DiraExecuteMsg::LockCollateral {};
+    let lock_collateral_msg = DiraExecuteMsg::LockCollateral {
+        collateral_id: "uatom".to_string(),
+    };
     let res = app.execute_contract(
         admin.clone(),
         dira_contract_addr.clone(),
@@ -252,6 +442,7 @@ fn test_mint_burn_dira() {
 
     // Mint DIRA for admin
     let mint_dira_msg = DiraExecuteMsg::MintDira {
+        collateral_id: "uatom".to_string(),
         dira_to_mint: Decimal::from_atomics(1_000u128, 6).unwrap(),
     };
     let res = app.execute_contract(
@@ -269,7 +460,6 @@ fn test_mint_burn_dira() {
     };
 
     let amount = Decimal::from_atomics(1_000u128, 6).unwrap();
-    let fee = helper_calculate_fee_tier_amount(amount);
     // This fee is now routed to the same admin currently , as this address is considered as treasury address
     let after_fee = amount;
     let expected_admin_mint = after_fee.atomics() / Uint128::from(u128::pow(10, 12));
@@ -281,29 +471,19 @@ fn test_mint_burn_dira() {
     assert_eq!(balance.balance, expected_admin_mint);
     dbg!("Admin's balance of DIRA:", balance.balance);
 
-    let increase_allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
-        spender: dira_contract_addr.to_string(),
-        amount: Uint128::from(500u128), // Approve 500 DIRA tokens
-        expires: None,                  // No expiration
-    };
-
-    let res = app.execute_contract(
-        admin.clone(),              // The user granting the approval
-        cw20_contract_addr.clone(), // The CW20 token contract
-        &increase_allowance_msg,    // The IncreaseAllowance message
-        &[],                        // No funds required
-    );
-    assert!(res.is_ok());
-    dbg!("Successfully granted allowance to Dira contract!");
-
-    // // Burn DIRA from admin
-    let burn_dira_msg = DiraExecuteMsg::BurnDira {
-        dira_to_burn: Decimal::from_atomics(500u128, 6).unwrap(),
+    // // Burn DIRA from admin by sending it to the Dira contract with a Repay hook
+    let repay_dira_msg = Cw20ExecuteMsg::Send {
+        contract: dira_contract_addr.to_string(),
+        amount: Uint128::from(500u128),
+        msg: to_json_binary(&DiraReceiveMsg::Repay {
+            collateral_id: "uatom".to_string(),
+        })
+        .unwrap(),
     };
     let res = app.execute_contract(
         admin.clone(),
-        dira_contract_addr.clone(),
-        &burn_dira_msg,
+        cw20_contract_addr.clone(),
+        &repay_dira_msg,
         &[],
     );
     assert!(res.is_ok());
@@ -318,13 +498,14 @@ fn test_mint_burn_dira() {
     // This fee is now routed to the same admin currently , as this address is considered as treasury address
     let fee_amount = helper_calculate_fee_tier_amount(burn);
     let fee_admin = (expected_admin_mint - ( burn.atomics() / Uint128::from(u128::pow(10, 12))) ) + fee_amount.atomics() / Uint128::from(u128::pow(10, 12));
-    assert_eq!(balance.balance, Uint128::from(fee_admin)+ Uint128::one());
+    assert_eq!(balance.balance, fee_admin);
     dbg!("Admin's balance of DIRA after burning:", balance.balance);
 
 
     // Fees for minting and buring will be deducted from non admin users
     // // Mint DIRA for non-admin
     let mint_dira_msg = DiraExecuteMsg::MintDira {
+        collateral_id: "uatom".to_string(),
         dira_to_mint: Decimal::from_atomics(500u128, 6).unwrap(),
     };
     let res = app.execute_contract(
@@ -343,8 +524,9 @@ fn test_mint_burn_dira() {
 
     let amount = Decimal::from_atomics(500u128, 6).unwrap();
     let fee = helper_calculate_fee_tier_amount(amount);
-    let after_fee = amount - fee;
-    let expected_non_admin_mint = after_fee.atomics() / Uint128::from(u128::pow(10, 12));
+    // The fee is rounded down to whole micro DIRA and the user receives the rest
+    let expected_non_admin_mint = amount.atomics() / Uint128::from(u128::pow(10, 12))
+        - fee.atomics() / Uint128::from(u128::pow(10, 12));
 
     let balance: cw20::BalanceResponse = app
         .wrap()
@@ -353,29 +535,19 @@ fn test_mint_burn_dira() {
     assert_eq!(balance.balance, expected_non_admin_mint);
     dbg!("Non-admin's balance of DIRA:", balance.balance);
 
-    let increase_allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
-        spender: dira_contract_addr.to_string(),
-        amount: Uint128::from(250u128), // Approve 500 DIRA tokens
-        expires: None,                  // No expiration
-    };
-
-    let res = app.execute_contract(
-        non_admin.clone(),          // The user granting the approval
-        cw20_contract_addr.clone(), // The CW20 token contract
-        &increase_allowance_msg,    // The IncreaseAllowance message
-        &[],                        // No funds required
-    );
-    assert!(res.is_ok());
-    dbg!("Successfully granted allowance to Dira contract!");
-
     // // Burn DIRA from non-admin
-    let burn_dira_msg = DiraExecuteMsg::BurnDira {
-        dira_to_burn: Decimal::from_atomics(250u128, 6).unwrap(),
+    let repay_dira_msg = Cw20ExecuteMsg::Send {
+        contract: dira_contract_addr.to_string(),
+        amount: Uint128::from(250u128),
+        msg: to_json_binary(&DiraReceiveMsg::Repay {
+            collateral_id: "uatom".to_string(),
+        })
+        .unwrap(),
     };
     let res = app.execute_contract(
         non_admin.clone(),
-        dira_contract_addr.clone(),
-        &burn_dira_msg,
+        cw20_contract_addr.clone(),
+        &repay_dira_msg,
         &[],
     );
     assert!(res.is_ok());
@@ -390,20 +562,107 @@ fn test_mint_burn_dira() {
         .wrap()
         .query_wasm_smart(cw20_contract_addr.clone(), &non_admin_balance_query)
         .unwrap();
-    assert_eq!(balance.balance, expected_balance_non_admin + Uint128::one());
+    assert_eq!(balance.balance, expected_balance_non_admin);
+
+    // Repaying more than was minted should fail. The admin still owes 501 micro DIRA, since
+    // only the 499 burned of the 500 sent came off the debt
+    let res = app.execute_contract(
+        admin.clone(),
+        cw20_contract_addr.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: dira_contract_addr.to_string(),
+            amount: Uint128::from(600u128),
+            msg: to_json_binary(&DiraReceiveMsg::Repay {
+                collateral_id: "uatom".to_string(),
+            })
+            .unwrap(),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    // The burned DIRA leaves the total supply, and the contract keeps none of what it received
+    let token_info: cw20::TokenInfoResponse = app
+        .wrap()
+        .query_wasm_smart(cw20_contract_addr.clone(), &cw20::Cw20QueryMsg::TokenInfo {})
+        .unwrap();
+    let admin_balance: cw20::BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(cw20_contract_addr.clone(), &balance_query)
+        .unwrap();
+    assert_eq!(token_info.total_supply, admin_balance.balance + balance.balance);
+
+    // The fee is paid on top of the debt repaid, so the supply still matches the total debt
+    let mut total_debt = Decimal::zero();
+    for wallet in [admin.clone(), non_admin.clone()] {
+        let res: MintedDiraResponse = app
+            .wrap()
+            .query_wasm_smart(
+                dira_contract_addr.clone(),
+                &StableDiraQueryMsg::QueryMintedDira {
+                    wallet_address_to_query: wallet,
+                    collateral_id: "uatom".to_string(),
+                },
+            )
+            .unwrap();
+        total_debt += res.dira_minted;
+    }
+    assert_eq!(Decimal::from_atomics(token_info.total_supply, 6).unwrap(), total_debt);
+    assert_eq!(total_debt, Decimal::from_atomics(501u128 + 250u128, 6).unwrap());
     dbg!(
         "Non-admin's balance of DIRA after burning:",
         balance.balance
     );
+
+    // With the fee switch disabled, DIRA can still be repaid and all of it comes off the debt
+    app.execute_contract(
+        admin.clone(),
+        dira_contract_addr.clone(),
+        &DiraExecuteMsg::DisableFeeSwitch {},
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        non_admin.clone(),
+        cw20_contract_addr.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: dira_contract_addr.to_string(),
+            amount: Uint128::from(100u128),
+            msg: to_json_binary(&DiraReceiveMsg::Repay {
+                collateral_id: "uatom".to_string(),
+            })
+            .unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let res: MintedDiraResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract_addr.clone(),
+            &StableDiraQueryMsg::QueryMintedDira {
+                wallet_address_to_query: non_admin.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.dira_minted, Decimal::from_atomics(150u128, 6).unwrap());
+    let res: cw20::BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(cw20_contract_addr.clone(), &non_admin_balance_query)
+        .unwrap();
+    assert_eq!(res.balance, balance.balance - Uint128::from(100u128));
 }
 
 #[test]
 fn test_liquidate_collateral() {
-    let (mut app, dira_contract_addr, _cw20_contract_addr, admin, user) = setup_app();
+    let (mut app, dira_contract_addr, cw20_contract_addr, admin, user) = setup_app();
 
     // 1. Setup the environment
     // Step 1.1: Set collateral price
     let set_collateral_price_msg = DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
         collateral_price_in_dirham: Decimal::from_ratio(3309u128, 100u128), // 33.09
     };
     let res = app.execute_contract(
@@ -416,7 +675,9 @@ fn test_liquidate_collateral() {
     dbg!("Set collateral price to 33.09");
 
     // Step 1.2: Lock collateral from both admin and user
-    let lock_collateral_msg = DiraExecuteMsg::LockCollateral {};
+    let lock_collateral_msg = DiraExecuteMsg::LockCollateral {
+        collateral_id: "uatom".to_string(),
+    };
 
     let res = app.execute_contract(
         admin.clone(),
@@ -438,6 +699,7 @@ fn test_liquidate_collateral() {
 
     // Step 1.3: Mint DIRA for both users
     let mint_dira_msg = DiraExecuteMsg::MintDira {
+        collateral_id: "uatom".to_string(),
         dira_to_mint: Decimal::from_ratio(1000000u128, 100000u128),
     };
 
@@ -462,6 +724,7 @@ fn test_liquidate_collateral() {
     // 2. Test liquidation due to price drop
     // Step 2.1: Drop collateral price
     let set_low_collateral_price_msg = DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
         collateral_price_in_dirham: Decimal::from_ratio(1000u128, 100u128), // Price drops to 10.00
     };
     let res = app.execute_contract(
@@ -473,9 +736,11 @@ fn test_liquidate_collateral() {
     assert!(res.is_ok());
     dbg!("Collateral price dropped to 10.00");
 
-    // Step 2.2: Attempt to liquidate admin from user account
+    // Step 2.2: Attempt to repay more than the close factor allows (50% of 10 DIRA)
     let liquidate_admin_msg = DiraExecuteMsg::LiquidateStablecoins {
         wallet_address_to_liquidate: admin.clone(),
+        collateral_id: "uatom".to_string(),
+        debt_to_repay: Decimal::from_ratio(6u128, 1u128),
     };
     let res = app.execute_contract(
         user.clone(),
@@ -483,12 +748,80 @@ fn test_liquidate_collateral() {
         &liquidate_admin_msg,
         &[],
     );
+    assert!(res.is_err());
+    dbg!("Liquidation above the close factor failed");
+
+    // Step 2.3: Partially liquidate admin from user account. The user sends their
+    // own DIRA along to repay the debt right away
+    let user_atom_balance_before = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+    let admin_atom_balance_before = app.wrap().query_balance(&admin, "uatom").unwrap().amount;
+
+    let res = app.execute_contract(
+        user.clone(),
+        cw20_contract_addr.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: dira_contract_addr.to_string(),
+            amount: Uint128::new(4_000_000),
+            msg: to_json_binary(&DiraReceiveMsg::Liquidate {
+                wallet_address_to_liquidate: admin.clone(),
+                collateral_id: "uatom".to_string(),
+            })
+            .unwrap(),
+        },
+        &[],
+    );
     assert!(res.is_ok());
     dbg!("Admin successfully liquidated by user");
 
+    // Step 2.4: Only part of the position is closed. 4 DIRA plus the 5% bonus
+    // at a price of 10.00 seizes 0.42 atom
+    let res: CollateralResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract_addr.clone(),
+            &StableDiraQueryMsg::QueryLockedCollateral {
+                wallet_address_to_query: admin.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.collateral_locked, Decimal::percent(58));
+
+    let res: MintedDiraResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract_addr.clone(),
+            &StableDiraQueryMsg::QueryMintedDira {
+                wallet_address_to_query: admin.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.dira_minted, Decimal::from_ratio(6u128, 1u128));
+
+    // Step 2.5: The user burned 4 DIRA and received the seized collateral minus the
+    // treasury's 10% share of the 0.02 atom bonus, which went to the admin as treasury
+    let balance: cw20::BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            cw20_contract_addr.clone(),
+            &cw20::Cw20QueryMsg::Balance {
+                address: user.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, Uint128::new(5_970_000));
+
+    let user_atom_balance = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+    assert_eq!(user_atom_balance - user_atom_balance_before, Uint128::new(418_000));
+
+    let admin_atom_balance = app.wrap().query_balance(&admin, "uatom").unwrap().amount;
+    assert_eq!(admin_atom_balance - admin_atom_balance_before, Uint128::new(2_000));
+
     // 3. Test liquidation of user from admin account
     // Step 3.1: Drop collateral price further
     let set_lower_collateral_price_msg = DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
         collateral_price_in_dirham: Decimal::from_ratio(500u128, 100u128), // Price drops to 5.00
     };
     let res = app.execute_contract(
@@ -500,9 +833,12 @@ fn test_liquidate_collateral() {
     assert!(res.is_ok());
     dbg!("Collateral price dropped to 5.00");
 
-    // Step 3.2: Attempt to liquidate user from admin account
+    // Step 3.2: Liquidate user from admin account. The stability pool is empty,
+    // so the seized collateral is put up for auction instead
     let liquidate_user_msg = DiraExecuteMsg::LiquidateStablecoins {
         wallet_address_to_liquidate: user.clone(),
+        collateral_id: "uatom".to_string(),
+        debt_to_repay: Decimal::from_ratio(4u128, 1u128),
     };
     let res = app.execute_contract(
         admin.clone(),
@@ -513,9 +849,31 @@ fn test_liquidate_collateral() {
     assert!(res.is_ok());
     dbg!("User successfully liquidated by admin");
 
+    let res: AuctionsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract_addr.clone(),
+            &StableDiraQueryMsg::QueryActiveAuctions {},
+        )
+        .unwrap();
+    assert_eq!(res.auctions.len(), 1);
+    assert_eq!(res.auctions[0].wallet_address, user);
+    assert_eq!(res.auctions[0].collateral_amount, Decimal::one());
+
     // 4. Edge Case: Attempt liquidation when health is above threshold
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract_addr.clone(),
+        &set_collateral_price_msg,
+        &[],
+    );
+    assert!(res.is_ok());
+    dbg!("Collateral price recovered to 33.09");
+
     let invalid_liquidation_msg = DiraExecuteMsg::LiquidateStablecoins {
         wallet_address_to_liquidate: admin.clone(),
+        collateral_id: "uatom".to_string(),
+        debt_to_repay: Decimal::one(),
     };
     let res = app.execute_contract(
         user.clone(),
@@ -529,6 +887,8 @@ fn test_liquidate_collateral() {
     // 5. Edge Case: Liquidation of a wallet with no minted DIRA
     let invalid_liquidation_msg = DiraExecuteMsg::LiquidateStablecoins {
         wallet_address_to_liquidate: Addr::unchecked("cosmos1no_minterxxxxxxxxxxxxxx"),
+        collateral_id: "uatom".to_string(),
+        debt_to_repay: Decimal::one(),
     };
     let res = app.execute_contract(
         admin.clone(),
@@ -542,6 +902,8 @@ fn test_liquidate_collateral() {
     // 6. Edge Case: Liquidation attempt on a non-existing user
     let non_existing_user_msg = DiraExecuteMsg::LiquidateStablecoins {
         wallet_address_to_liquidate: Addr::unchecked("cosmos1nonexistentxxxxxxxxxxx"),
+        collateral_id: "uatom".to_string(),
+        debt_to_repay: Decimal::one(),
     };
     let res = app.execute_contract(
         admin.clone(),
@@ -554,94 +916,3974 @@ fn test_liquidate_collateral() {
 }
 
 #[test]
-fn test_query_functions() {
-    let (mut app, dira_contract, _cw20_contract, admin, user) = setup_app();
+fn test_liquidate_with_received_dira() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
 
-    // Update initial state - Set collateral price
-    let set_price_msg = DiraExecuteMsg::SetCollateralPriceInDirham {
-        collateral_price_in_dirham: Decimal::from_ratio(2500u128, 100u128), // 25.00
+    let set_price = |price: Decimal| DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
+        collateral_price_in_dirham: price,
     };
-    app.execute_contract(admin.clone(), dira_contract.clone(), &set_price_msg, &[])
-        .unwrap();
-
-    // Lock collateral for both admin and user
-    let lock_collateral_msg = DiraExecuteMsg::LockCollateral {};
-    dbg!(app.execute_contract(
+    app.execute_contract(
         admin.clone(),
         dira_contract.clone(),
-        &lock_collateral_msg,
-        &coins(100_000_000_000u128, "uatom"),
-    ))
+        &set_price(Decimal::from_ratio(3309u128, 100u128)),
+        &[],
+    )
     .unwrap();
+
+    for wallet in [&admin, &user] {
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::LockCollateral {
+                collateral_id: "uatom".to_string(),
+            },
+            &coins(1_000_000, "uatom"),
+        )
+        .unwrap();
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::MintDira {
+                collateral_id: "uatom".to_string(),
+                dira_to_mint: Decimal::from_ratio(10u128, 1u128),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
     app.execute_contract(
-        user.clone(),
+        admin.clone(),
         dira_contract.clone(),
-        &lock_collateral_msg,
-        &coins(100_000_000_000u128, "uatom"),
+        &set_price(Decimal::from_ratio(10u128, 1u128)),
+        &[],
     )
     .unwrap();
 
-    // Mint DIRA for both admin and user
-    let mint_msg = DiraExecuteMsg::MintDira {
-        dira_to_mint: Decimal::from_ratio(5000u128, 100u128), // 50 DIRA
+    let query_supply = |app: &App| -> Uint128 {
+        let token_info: cw20::TokenInfoResponse = app
+            .wrap()
+            .query_wasm_smart(cw20_contract.clone(), &cw20::Cw20QueryMsg::TokenInfo {})
+            .unwrap();
+        token_info.total_supply
     };
-    app.execute_contract(admin.clone(), dira_contract.clone(), &mint_msg, &[])
-        .unwrap();
-    app.execute_contract(user.clone(), dira_contract.clone(), &mint_msg, &[])
-        .unwrap();
-
-    // Query locked collateral
-    let query_locked = StableDiraQueryMsg::QueryLockedCollateral {
-        wallet_address_to_query: admin.clone(),
+    let query_total_debt = |app: &App| -> Decimal {
+        let res: CollateralInfoResponse = app
+            .wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryCollateralInfo {
+                    collateral_id: "uatom".to_string(),
+                },
+            )
+            .unwrap();
+        res.total_dira_minted
     };
-    let res: CollateralResponse = app
-        .wrap()
-        .query_wasm_smart(dira_contract.clone(), &query_locked)
-        .unwrap();
+
+    let supply_before = query_supply(&app);
+    let total_debt_before = query_total_debt(&app);
+
+    // Liquidate admin by sending 3 DIRA, no allowance needed
+    let liquidate_msg = to_json_binary(&DiraReceiveMsg::Liquidate {
+        wallet_address_to_liquidate: admin.clone(),
+        collateral_id: "uatom".to_string(),
+    })
+    .unwrap();
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: dira_contract.to_string(),
+            amount: Uint128::new(3_000_000),
+            msg: liquidate_msg.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // The DIRA supply and the recorded debt go down by the same amount
+    assert_eq!(supply_before - query_supply(&app), Uint128::new(3_000_000));
     assert_eq!(
-        res.collateral_locked,
-        Decimal::from_ratio(100_000_000_000u128, 1000000u128)
+        total_debt_before - query_total_debt(&app),
+        Decimal::from_ratio(3u128, 1u128)
     );
-    dbg!("Admin's locked collateral:", res.collateral_locked);
-
-    // Query minted DIRA
-    let query_minted = StableDiraQueryMsg::QueryMintedDira {
-        wallet_address_to_query: user.clone(),
-    };
-    let res: MintedDiraResponse = app
-        .wrap()
-        .query_wasm_smart(dira_contract.clone(), &query_minted)
-        .unwrap();
-    assert_eq!(res.dira_minted, Decimal::from_ratio(4985u128, 100u128));
-    dbg!("User's minted DIRA:", res.dira_minted);
 
-    // Query stablecoin health
-    let query_health = StableDiraQueryMsg::QueryStablecoinHealth {
-        stablecoin_minter_address_to_query: user.clone(),
-    };
-    let res: StablecoinHealthResponse = app
+    let balance: cw20::BalanceResponse = app
         .wrap()
-        .query_wasm_smart(dira_contract.clone(), &query_health)
+        .query_wasm_smart(
+            cw20_contract.clone(),
+            &cw20::Cw20QueryMsg::Balance {
+                address: dira_contract.to_string(),
+            },
+        )
         .unwrap();
-    dbg!("User's stablecoin health:", res.health);
+    assert!(balance.balance.is_zero());
 
-    // Query collateral price
-    let res: CollateralPriceResponse = app
+    let res: MintedDiraResponse = app
         .wrap()
         .query_wasm_smart(
             dira_contract.clone(),
-            &StableDiraQueryMsg::QueryCollateralPrice {},
+            &StableDiraQueryMsg::QueryMintedDira {
+                wallet_address_to_query: admin.clone(),
+                collateral_id: "uatom".to_string(),
+            },
         )
         .unwrap();
-    assert_eq!(res.collateral_price, Decimal::from_ratio(2500u128, 100u128));
-    dbg!("Collateral price:", res.collateral_price);
+    assert_eq!(res.dira_minted, Decimal::from_ratio(7u128, 1u128));
 
-    // Query liquidation health
-    let res: LiquidationHealthResponse = app
-        .wrap()
+    // Sending more DIRA than the close factor allows is rejected and nothing is burned
+    let res = app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: dira_contract.to_string(),
+            amount: Uint128::new(4_000_000),
+            msg: liquidate_msg,
+        },
+        &[],
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_stability_pool() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    let set_price = |price: Decimal| DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
+        collateral_price_in_dirham: price,
+    };
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price(Decimal::from_ratio(3309u128, 100u128)),
+        &[],
+    )
+    .unwrap();
+
+    for wallet in [&admin, &user] {
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::LockCollateral {
+                collateral_id: "uatom".to_string(),
+            },
+            &coins(1_000_000, "uatom"),
+        )
+        .unwrap();
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::MintDira {
+                collateral_id: "uatom".to_string(),
+                dira_to_mint: Decimal::from_ratio(10u128, 1u128),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    // User deposits 6 DIRA and admin deposits 4 DIRA
+    for (wallet, amount) in [(&user, 6_000_000u128), (&admin, 4_000_000u128)] {
+        app.execute_contract(
+            wallet.clone(),
+            cw20_contract.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: dira_contract.to_string(),
+                amount: Uint128::new(amount),
+                msg: to_json_binary(&DiraReceiveMsg::DepositStabilityPool {}).unwrap(),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    let res: StabilityPoolResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryStabilityPool {})
+        .unwrap();
+    assert_eq!(res.total_deposits, Decimal::from_ratio(10u128, 1u128));
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price(Decimal::from_ratio(10u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+
+    // No allowance is given, the pool covers the debt and absorbs the liquidation
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LiquidateStablecoins {
+            wallet_address_to_liquidate: admin.clone(),
+            collateral_id: "uatom".to_string(),
+            debt_to_repay: Decimal::from_ratio(4u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let res: StabilityPoolResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryStabilityPool {})
+        .unwrap();
+    assert_eq!(res.total_deposits, Decimal::from_ratio(6u128, 1u128));
+
+    let query_deposit = |app: &App, depositor: &Addr| -> StabilityPoolDepositResponse {
+        app.wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryStabilityPoolDeposit {
+                    depositor: depositor.clone(),
+                },
+            )
+            .unwrap()
+    };
+    let query_gains = |app: &App, depositor: &Addr| -> StabilityPoolGainsResponse {
+        app.wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryStabilityPoolGains {
+                    depositor: depositor.clone(),
+                },
+            )
+            .unwrap()
+    };
+
+    // The 4 DIRA of debt is taken out of both deposits pro rata
+    assert_eq!(
+        query_deposit(&app, &user).compounded_deposit,
+        Decimal::from_ratio(36u128, 10u128)
+    );
+    assert_eq!(
+        query_deposit(&app, &admin).compounded_deposit,
+        Decimal::from_ratio(24u128, 10u128)
+    );
+
+    // 0.42 atom is seized, 0.002 of it goes to the treasury and the rest is shared out
+    let user_gains = query_gains(&app, &user).collateral_gains;
+    let admin_gains = query_gains(&app, &admin).collateral_gains;
+    assert_eq!(user_gains.len(), 1);
+    assert_eq!(user_gains[0].collateral_id, "uatom");
+    assert_eq!(user_gains[0].amount, Decimal::from_ratio(2508u128, 10_000u128));
+    assert_eq!(admin_gains[0].amount, Decimal::from_ratio(1672u128, 10_000u128));
+
+    let balance_before = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::ClaimStabilityPoolGains {},
+        &[],
+    )
+    .unwrap();
+    let balance_after = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+    assert_eq!(balance_after - balance_before, Uint128::new(250_800));
+    assert!(query_gains(&app, &user).collateral_gains.is_empty());
+
+    // Withdrawing more than what is left of the deposit fails
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::WithdrawFromStabilityPool {
+            dira_to_withdraw: Decimal::from_ratio(4u128, 1u128),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    let query_dira_balance = |app: &App, address: &Addr| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                cw20_contract.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+
+    let dira_before = query_dira_balance(&app, &user);
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::WithdrawFromStabilityPool {
+            dira_to_withdraw: Decimal::from_ratio(36u128, 10u128),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        query_dira_balance(&app, &user) - dira_before,
+        Uint128::new(3_600_000)
+    );
+    assert!(query_deposit(&app, &user).compounded_deposit.is_zero());
+}
+
+#[test]
+fn test_collateral_auction() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    let set_price = |price: Decimal| DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
+        collateral_price_in_dirham: price,
+    };
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price(Decimal::from_ratio(3309u128, 100u128)),
+        &[],
+    )
+    .unwrap();
+
+    for wallet in [&admin, &user] {
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::LockCollateral {
+                collateral_id: "uatom".to_string(),
+            },
+            &coins(1_000_000, "uatom"),
+        )
+        .unwrap();
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::MintDira {
+                collateral_id: "uatom".to_string(),
+                dira_to_mint: Decimal::from_ratio(10u128, 1u128),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    // Only admins can change the auction config, and auctions have to be reset
+    // before their price decays to zero
+    let auction_config_msg = |reset_after_seconds: u64| DiraExecuteMsg::SetAuctionConfig {
+        starting_premium: Decimal::percent(120),
+        price_decay_seconds: 3600,
+        reset_after_seconds,
+    };
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &auction_config_msg(1800),
+        &[],
+    );
+    assert!(res.is_err());
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &auction_config_msg(3600),
+        &[],
+    );
+    assert!(res.is_err());
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &auction_config_msg(1800),
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price(Decimal::from_ratio(10u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+
+    let query_supply = |app: &App| -> Uint128 {
+        let token_info: cw20::TokenInfoResponse = app
+            .wrap()
+            .query_wasm_smart(cw20_contract.clone(), &cw20::Cw20QueryMsg::TokenInfo {})
+            .unwrap();
+        token_info.total_supply
+    };
+    let query_dira_balance = |app: &App, address: &Addr| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                cw20_contract.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+    let query_auction = |app: &App, auction_id: u64| -> AuctionResponse {
+        app.wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryAuction { auction_id },
+            )
+            .unwrap()
+    };
+    let bid_msg = |auction_id: u64, max_collateral: Decimal, amount: u128| Cw20ExecuteMsg::Send {
+        contract: dira_contract.to_string(),
+        amount: Uint128::new(amount),
+        msg: to_json_binary(&DiraReceiveMsg::BidAuction {
+            auction_id,
+            max_collateral,
+        })
+        .unwrap(),
+    };
+
+    // The stability pool is empty, so liquidating admin starts an auction. 4.2 DIRA has to
+    // be raised, and 0.504 atom is seized to be sold starting at 12.00
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LiquidateStablecoins {
+            wallet_address_to_liquidate: admin.clone(),
+            collateral_id: "uatom".to_string(),
+            debt_to_repay: Decimal::from_ratio(4u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let auction = query_auction(&app, 1);
+    assert_eq!(auction.wallet_address, admin);
+    assert_eq!(auction.collateral_amount, Decimal::permille(504));
+    assert_eq!(auction.dira_to_raise, Decimal::from_ratio(42u128, 10u128));
+    assert_eq!(auction.starting_price, Decimal::from_ratio(12u128, 1u128));
+    assert_eq!(auction.current_price, Decimal::from_ratio(12u128, 1u128));
+
+    let res: CollateralResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryLockedCollateral {
+                wallet_address_to_query: admin.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.collateral_locked, Decimal::permille(496));
+
+    // A quarter of the way through the decay the price is down to 9.00
+    app.update_block(|block| block.time = block.time.plus_seconds(900));
+    assert_eq!(query_auction(&app, 1).current_price, Decimal::from_ratio(9u128, 1u128));
+
+    let supply_before = query_supply(&app);
+    let user_dira_before = query_dira_balance(&app, &user);
+    let admin_dira_before = query_dira_balance(&app, &admin);
+    let user_atom_before = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+
+    // Buying 0.1 atom costs 0.9 DIRA, the rest of the 2 DIRA sent is refunded
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &bid_msg(1, Decimal::permille(100), 2_000_000),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        user_dira_before - query_dira_balance(&app, &user),
+        Uint128::new(900_000)
+    );
+    assert_eq!(
+        query_auction(&app, 1).dira_to_raise,
+        Decimal::from_ratio(33u128, 10u128)
+    );
+
+    // The next bid raises everything that is left. 3.1 DIRA of it is debt and
+    // burned, and the 0.2 DIRA penalty goes to the admin as treasury
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &bid_msg(1, Decimal::one(), 5_000_000),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        user_dira_before - query_dira_balance(&app, &user),
+        Uint128::new(4_200_000)
+    );
+    assert_eq!(supply_before - query_supply(&app), Uint128::new(4_000_000));
+    assert_eq!(
+        query_dira_balance(&app, &admin) - admin_dira_before,
+        Uint128::new(200_000)
+    );
+
+    let user_atom_after = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+    assert_eq!(user_atom_after - user_atom_before, Uint128::new(466_666));
+
+    // The auction is over and the collateral it did not sell is back with admin
+    let res: AuctionsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryActiveAuctions {},
+        )
+        .unwrap();
+    assert!(res.auctions.is_empty());
+
+    let res: CollateralResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryLockedCollateral {
+                wallet_address_to_query: admin.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert!(res.collateral_locked > Decimal::permille(533));
+    assert!(res.collateral_locked < Decimal::permille(534));
+
+    let res = app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &bid_msg(1, Decimal::one(), 1_000_000),
+        &[],
+    );
+    assert!(res.is_err());
+
+    // An auction that runs too long cannot be bid on until it is reset
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LiquidateStablecoins {
+            wallet_address_to_liquidate: admin.clone(),
+            collateral_id: "uatom".to_string(),
+            debt_to_repay: Decimal::from_ratio(2u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let reset_msg = DiraExecuteMsg::ResetAuction { auction_id: 2 };
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &reset_msg, &[]);
+    assert!(res.is_err());
+
+    app.update_block(|block| block.time = block.time.plus_seconds(1800));
+    let auction = query_auction(&app, 2);
+    assert!(auction.needs_reset);
+    assert_eq!(auction.current_price, Decimal::from_ratio(6u128, 1u128));
+
+    let res = app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &bid_msg(2, Decimal::one(), 1_000_000),
+        &[],
+    );
+    assert!(res.is_err());
+
+    app.execute_contract(user.clone(), dira_contract.clone(), &reset_msg, &[])
+        .unwrap();
+    let auction = query_auction(&app, 2);
+    assert!(!auction.needs_reset);
+    assert_eq!(auction.current_price, Decimal::from_ratio(12u128, 1u128));
+
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &bid_msg(2, Decimal::one(), 1_000_000),
+        &[],
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_auction_bad_debt() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    let set_price = |price: Decimal| DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
+        collateral_price_in_dirham: price,
+    };
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price(Decimal::from_ratio(3309u128, 100u128)),
+        &[],
+    )
+    .unwrap();
+
+    for wallet in [&admin, &user] {
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::LockCollateral {
+                collateral_id: "uatom".to_string(),
+            },
+            &coins(1_000_000, "uatom"),
+        )
+        .unwrap();
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::MintDira {
+                collateral_id: "uatom".to_string(),
+                dira_to_mint: Decimal::from_ratio(10u128, 1u128),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetAuctionConfig {
+            starting_premium: Decimal::percent(120),
+            price_decay_seconds: 3600,
+            reset_after_seconds: 1800,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let query_bad_debt = |app: &App| -> Decimal {
+        let res: BadDebtResponse = app
+            .wrap()
+            .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryBadDebt {})
+            .unwrap();
+        res.bad_debt
+    };
+    let query_dira_balance = |app: &App, address: &Addr| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                cw20_contract.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+    let liquidate_msg = |debt_to_repay: u128| DiraExecuteMsg::LiquidateStablecoins {
+        wallet_address_to_liquidate: admin.clone(),
+        collateral_id: "uatom".to_string(),
+        debt_to_repay: Decimal::from_ratio(debt_to_repay, 1u128),
+    };
+
+    // Liquidating 4 DIRA of the admin's debt puts 0.504 atom up for auction
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price(Decimal::from_ratio(10u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(user.clone(), dira_contract.clone(), &liquidate_msg(4), &[])
+        .unwrap();
+    assert!(query_bad_debt(&app).is_zero());
+
+    // After the price crashes to 1.00 the auction is reset to start at 1.20, and all of its
+    // collateral sells for 0.6048 DIRA. The other 3.3952 DIRA of debt is bad debt
+    app.execute_contract(admin.clone(), dira_contract.clone(), &set_price(Decimal::one()), &[])
+        .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(1800));
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::ResetAuction { auction_id: 1 },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: dira_contract.to_string(),
+            amount: Uint128::new(1_000_000),
+            msg: to_json_binary(&DiraReceiveMsg::BidAuction {
+                auction_id: 1,
+                max_collateral: Decimal::one(),
+            })
+            .unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_bad_debt(&app), Decimal::from_ratio(33952u128, 10_000u128));
+
+    // A year at 25% on the 16 DIRA still owed accrues 4 DIRA of fees. Dripping writes the bad
+    // debt off against them and mints only the rest to the admin as treasury
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetStabilityFee {
+            collateral_id: "uatom".to_string(),
+            stability_fee: Decimal::percent(25),
+        },
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(365 * 24 * 60 * 60));
+
+    let treasury_balance_before = query_dira_balance(&app, &admin);
+    app.execute_contract(user.clone(), dira_contract.clone(), &DiraExecuteMsg::Drip {}, &[])
+        .unwrap();
+    assert_eq!(
+        query_dira_balance(&app, &admin) - treasury_balance_before,
+        Uint128::new(604_800)
+    );
+    assert!(query_bad_debt(&app).is_zero());
+
+    // Shutting down settles a running auction. Its 2 DIRA of debt is part of the snapshot and
+    // backed by 0.2 atom of the 0.252 atom for sale, the other 0.052 atom goes back to the admin
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price(Decimal::from_ratio(10u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(user.clone(), dira_contract.clone(), &liquidate_msg(2), &[])
+        .unwrap();
+    app.execute_contract(admin.clone(), dira_contract.clone(), &DiraExecuteMsg::Shutdown {}, &[])
+        .unwrap();
+
+    let shutdown: ShutdownResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryShutdown {})
+        .unwrap();
+    let shutdown_collateral = &shutdown.collaterals[0].shutdown_collateral;
+    assert_eq!(shutdown_collateral.total_debt, Decimal::from_ratio(20u128, 1u128));
+    assert_eq!(shutdown_collateral.settled_debt, Decimal::from_ratio(2u128, 1u128));
+    assert_eq!(shutdown_collateral.backing, Decimal::permille(200));
+
+    let res: AuctionsResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryActiveAuctions {})
+        .unwrap();
+    assert!(res.auctions.is_empty());
+
+    let res: CollateralResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryLockedCollateral {
+                wallet_address_to_query: admin.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.collateral_locked, Decimal::permille(296));
+}
+
+#[test]
+fn test_stability_fee() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id: "uatom".to_string(),
+            collateral_price_in_dirham: Decimal::from_ratio(3309u128, 100u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LockCollateral {
+            collateral_id: "uatom".to_string(),
+        },
+        &coins(1_000_000, "uatom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::MintDira {
+            collateral_id: "uatom".to_string(),
+            dira_to_mint: Decimal::from_ratio(10u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // Only admins can set the stability fee
+    let set_stability_fee_msg = DiraExecuteMsg::SetStabilityFee {
+        collateral_id: "uatom".to_string(),
+        stability_fee: Decimal::percent(10),
+    };
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &set_stability_fee_msg,
+        &[],
+    );
+    assert!(res.is_err());
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_stability_fee_msg,
+        &[],
+    )
+    .unwrap();
+
+    let query_minted_dira = |app: &App| -> MintedDiraResponse {
+        app.wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryMintedDira {
+                    wallet_address_to_query: user.clone(),
+                    collateral_id: "uatom".to_string(),
+                },
+            )
+            .unwrap()
+    };
+    let query_dira_balance = |app: &App, address: &Addr| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                cw20_contract.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+
+    // Half a year at 10% a year adds 5% to the 10 DIRA owed
+    app.update_block(|block| block.time = block.time.plus_seconds(365 * 24 * 60 * 60 / 2));
+
+    let res: StabilityFeeResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryStabilityFee {
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.stability_fee, Decimal::percent(10));
+    assert_eq!(res.rate, Decimal::percent(105));
+
+    let res = query_minted_dira(&app);
+    assert_eq!(res.dira_minted, Decimal::from_ratio(105u128, 10u128));
+    assert_eq!(
+        res.accrued_stability_fee,
+        Decimal::from_ratio(5u128, 10u128)
+    );
+
+    let res: StablecoinHealthResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryStablecoinHealth {
+                stablecoin_minter_address_to_query: user.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.health,
+        Decimal::from_ratio(3309u128, 100u128) / Decimal::from_ratio(105u128, 10u128)
+    );
+    assert_eq!(
+        res.accrued_stability_fee,
+        Decimal::from_ratio(5u128, 10u128)
+    );
+
+    // Anyone can drip, the accrued fees are minted to the admin as treasury
+    let treasury_balance_before = query_dira_balance(&app, &admin);
+    app.execute_contract(user.clone(), dira_contract.clone(), &DiraExecuteMsg::Drip {}, &[])
+        .unwrap();
+    assert_eq!(
+        query_dira_balance(&app, &admin) - treasury_balance_before,
+        Uint128::new(500_000)
+    );
+
+    // Dripping again in the same block has nothing left to mint
+    let treasury_balance_before = query_dira_balance(&app, &admin);
+    app.execute_contract(user.clone(), dira_contract.clone(), &DiraExecuteMsg::Drip {}, &[])
+        .unwrap();
+    assert_eq!(query_dira_balance(&app, &admin), treasury_balance_before);
+
+    // Repayments pay off the accrued fee before the principal. The 0.3% fee is paid on top,
+    // so 0.501504 DIRA burns exactly the 0.5 DIRA accrued
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: dira_contract.to_string(),
+            amount: Uint128::new(501_504),
+            msg: to_json_binary(&DiraReceiveMsg::Repay {
+                collateral_id: "uatom".to_string(),
+            })
+            .unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let res = query_minted_dira(&app);
+    assert_eq!(res.dira_minted, Decimal::from_ratio(10u128, 1u128));
+    assert!(res.accrued_stability_fee.is_zero());
+
+    let res: CollateralInfoResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryCollateralInfo {
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.total_dira_minted,
+        Decimal::from_ratio(10u128, 1u128)
+    );
+    assert_eq!(res.collateral_info.stability_fee, Decimal::percent(10));
+}
+
+#[test]
+fn test_savings_vault() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id: "uatom".to_string(),
+            collateral_price_in_dirham: Decimal::from_ratio(3309u128, 100u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    for wallet in [&admin, &user] {
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::LockCollateral {
+                collateral_id: "uatom".to_string(),
+            },
+            &coins(1_000_000, "uatom"),
+        )
+        .unwrap();
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::MintDira {
+                collateral_id: "uatom".to_string(),
+                dira_to_mint: Decimal::from_ratio(10u128, 1u128),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetStabilityFee {
+            collateral_id: "uatom".to_string(),
+            stability_fee: Decimal::percent(10),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // Only admins can set the savings rate
+    let set_savings_rate_msg = DiraExecuteMsg::SetSavingsRate {
+        savings_rate: Decimal::percent(4),
+    };
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &set_savings_rate_msg,
+        &[],
+    );
+    assert!(res.is_err());
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_savings_rate_msg,
+        &[],
+    )
+    .unwrap();
+
+    let query_savings = |app: &App| -> SavingsResponse {
+        app.wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QuerySavings {
+                    depositor: user.clone(),
+                },
+            )
+            .unwrap()
+    };
+    let query_dira_balance = |app: &App, address: &Addr| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                cw20_contract.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+    let deposit_savings_msg = |amount: u128| Cw20ExecuteMsg::Send {
+        contract: dira_contract.to_string(),
+        amount: Uint128::new(amount),
+        msg: to_json_binary(&DiraReceiveMsg::DepositSavings {}).unwrap(),
+    };
+
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &deposit_savings_msg(5_000_000),
+        &[],
+    )
+    .unwrap();
+
+    let res = query_savings(&app);
+    assert_eq!(res.shares, Decimal::from_ratio(5u128, 1u128));
+    assert_eq!(res.redeemable_dira, Decimal::from_ratio(5u128, 1u128));
+
+    // After a year the 5 DIRA saved at 4% has earned 0.2 DIRA, well within the
+    // 2 DIRA of stability fees paid on the 20 DIRA borrowed
+    app.update_block(|block| block.time = block.time.plus_seconds(365 * 24 * 60 * 60));
+
+    let res: SavingsVaultResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QuerySavingsVault {})
+        .unwrap();
+    assert_eq!(res.savings_rate, Decimal::percent(4));
+    assert_eq!(res.share_price, Decimal::percent(104));
+    assert_eq!(res.total_dira_saved, Decimal::from_ratio(52u128, 10u128));
+    assert_eq!(
+        query_savings(&app).redeemable_dira,
+        Decimal::from_ratio(52u128, 10u128)
+    );
+
+    // Dripping pays the savers first and mints the rest of the fees to the treasury
+    let treasury_balance_before = query_dira_balance(&app, &admin);
+    app.execute_contract(user.clone(), dira_contract.clone(), &DiraExecuteMsg::Drip {}, &[])
+        .unwrap();
+    assert_eq!(
+        query_dira_balance(&app, &admin) - treasury_balance_before,
+        Uint128::new(1_800_000)
+    );
+    assert_eq!(
+        query_dira_balance(&app, &dira_contract),
+        Uint128::new(5_200_000)
+    );
+
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::WithdrawSavings {
+            dira_to_withdraw: Decimal::from_ratio(53u128, 10u128),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    let user_balance_before = query_dira_balance(&app, &user);
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::WithdrawSavings {
+            dira_to_withdraw: Decimal::from_ratio(52u128, 10u128),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        query_dira_balance(&app, &user) - user_balance_before,
+        Uint128::new(5_200_000)
+    );
+    assert!(query_savings(&app).shares.is_zero());
+
+    // Without stability fees coming in there is nothing to pay savers with
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetStabilityFee {
+            collateral_id: "uatom".to_string(),
+            stability_fee: Decimal::zero(),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(user.clone(), dira_contract.clone(), &DiraExecuteMsg::Drip {}, &[])
+        .unwrap();
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &deposit_savings_msg(1_000_000),
+        &[],
+    )
+    .unwrap();
+
+    app.update_block(|block| block.time = block.time.plus_seconds(365 * 24 * 60 * 60));
+
+    let res = query_savings(&app);
+    assert!(res.redeemable_dira <= Decimal::one());
+    assert!(res.redeemable_dira > Decimal::permille(999));
+}
+
+#[test]
+fn test_peg_stability_module() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    // Only admins can whitelist stablecoins, with fees of at most one
+    let register_psm_asset_msg = |fee_in: Decimal| DiraExecuteMsg::RegisterPsmAsset {
+        asset_id: "uom".to_string(),
+        asset_type: CollateralAssetType::Native,
+        decimals: 6,
+        price_in_dirham: Decimal::one(),
+        fee_in,
+        fee_out: Decimal::percent(2),
+        reserve_cap: Some(Decimal::from_ratio(100u128, 1u128)),
+    };
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &register_psm_asset_msg(Decimal::percent(1)),
+        &[],
+    );
+    assert!(res.is_err());
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &register_psm_asset_msg(Decimal::percent(101)),
+        &[],
+    );
+    assert!(res.is_err());
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &register_psm_asset_msg(Decimal::percent(1)),
+        &[],
+    )
+    .unwrap();
+
+    let query_dira_balance = |app: &App, address: &Addr| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                cw20_contract.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+    let query_reserves = |app: &App| -> Decimal {
+        let res: PsmAssetResponse = app
+            .wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryPsmAsset {
+                    asset_id: "uom".to_string(),
+                },
+            )
+            .unwrap();
+        res.reserves
+    };
+
+    let res: PsmSwapSimulationResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QuerySimulateSwapStablecoinForDira {
+                asset_id: "uom".to_string(),
+                stablecoin_amount: Decimal::from_ratio(50u128, 1u128),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.amount_out, Decimal::from_ratio(495u128, 10u128));
+    assert_eq!(res.fee, Decimal::from_ratio(5u128, 10u128));
+
+    // Swapping 50 stablecoins in mints 49.5 DIRA to the user and the 0.5 DIRA fee to the treasury
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SwapStablecoinForDira {
+            asset_id: "uom".to_string(),
+        },
+        &coins(50_000_000, "uom"),
+    )
+    .unwrap();
+    assert_eq!(query_dira_balance(&app, &user), Uint128::new(49_500_000));
+    assert_eq!(query_dira_balance(&app, &admin), Uint128::new(500_000));
+    assert_eq!(query_reserves(&app), Decimal::from_ratio(50u128, 1u128));
+
+    // Reserves cannot grow past their cap
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SwapStablecoinForDira {
+            asset_id: "uom".to_string(),
+        },
+        &coins(60_000_000, "uom"),
+    );
+    assert!(res.is_err());
+
+    // Swapping 10 DIRA back burns 9.8 DIRA, pays out 9.8 stablecoins and sends the 0.2 DIRA fee to the treasury
+    let res: PsmSwapSimulationResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QuerySimulateSwapDiraForStablecoin {
+                asset_id: "uom".to_string(),
+                dira_amount: Decimal::from_ratio(10u128, 1u128),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.amount_out, Decimal::from_ratio(98u128, 10u128));
+    assert_eq!(res.fee, Decimal::from_ratio(2u128, 10u128));
+
+    let swap_dira_for_stablecoin_msg = |amount: u128| Cw20ExecuteMsg::Send {
+        contract: dira_contract.to_string(),
+        amount: Uint128::new(amount),
+        msg: to_json_binary(&DiraReceiveMsg::SwapDiraForStablecoin {
+            asset_id: "uom".to_string(),
+        })
+        .unwrap(),
+    };
+
+    let stablecoin_balance_before = app.wrap().query_balance(&user, "uom").unwrap().amount;
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &swap_dira_for_stablecoin_msg(10_000_000),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_balance(&user, "uom").unwrap().amount - stablecoin_balance_before,
+        Uint128::new(9_800_000)
+    );
+    assert_eq!(query_dira_balance(&app, &admin), Uint128::new(700_000));
+    assert_eq!(query_reserves(&app), Decimal::from_ratio(402u128, 10u128));
+
+    // The reserves still back every DIRA minted through the module
+    let res: cw20::TokenInfoResponse = app
+        .wrap()
+        .query_wasm_smart(cw20_contract.clone(), &cw20::Cw20QueryMsg::TokenInfo {})
+        .unwrap();
+    assert_eq!(res.total_supply, Uint128::new(40_200_000));
+
+    // Stablecoin prices are guarded like collateral prices and stop with price updates
+    let set_psm_asset_price_msg = DiraExecuteMsg::SetPsmAssetPrice {
+        asset_id: "uom".to_string(),
+        price_in_dirham: Decimal::percent(10),
+    };
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetPriceGuardConfig {
+            max_price_deviation: Decimal::percent(10),
+            deviation_window_seconds: 3600,
+            price_delay_seconds: 0,
+        },
+        &[],
+    )
+    .unwrap();
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &set_psm_asset_price_msg, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "Price moves more than 0.1 away from the last accepted price within the deviation window"
+    );
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetPsmAssetPrice {
+            asset_id: "uom".to_string(),
+            price_in_dirham: Decimal::zero(),
+        },
+        &[],
+    );
+    assert_eq!(res.unwrap_err().root_cause().to_string(), "Price cannot be zero");
+
+    app.update_block(|block| block.time = block.time.plus_seconds(3600));
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::Pause {
+            minting: false,
+            unlocking: false,
+            liquidations: false,
+            price_updates: true,
+            duration_seconds: 60,
+        },
+        &[],
+    )
+    .unwrap();
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &set_psm_asset_price_msg, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "Contract is paused for price_updates"
+    );
+    app.execute_contract(admin.clone(), dira_contract.clone(), &DiraExecuteMsg::Unpause {}, &[])
+        .unwrap();
+
+    // At a lower price the same DIRA asks for more stablecoins than the module holds
+    app.execute_contract(admin.clone(), dira_contract.clone(), &set_psm_asset_price_msg, &[])
+        .unwrap();
+    let res = app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &swap_dira_for_stablecoin_msg(10_000_000),
+        &[],
+    );
+    assert!(res.is_err());
+
+    // Stablecoins that were never whitelisted cannot be swapped
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SwapStablecoinForDira {
+            asset_id: "uatom".to_string(),
+        },
+        &coins(1_000_000, "uatom"),
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_redemptions() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id: "uatom".to_string(),
+            collateral_price_in_dirham: Decimal::from_ratio(10u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // The admin's position is the riskier one, with 1 atom behind 2 DIRA of debt,
+    // against 6 atom behind 10 DIRA for the user
+    for (wallet, collateral, dira_to_mint) in [(&admin, 1_000_000, 2u128), (&user, 6_000_000, 10u128)] {
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::LockCollateral {
+                collateral_id: "uatom".to_string(),
+            },
+            &coins(collateral, "uatom"),
+        )
+        .unwrap();
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::MintDira {
+                collateral_id: "uatom".to_string(),
+                dira_to_mint: Decimal::from_ratio(dira_to_mint, 1u128),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    let query_redemption_queue = |app: &App| -> RedemptionQueueResponse {
+        app.wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryRedemptionQueue {
+                    collateral_id: "uatom".to_string(),
+                    limit: None,
+                },
+            )
+            .unwrap()
+    };
+    let query_redemption_fee = |app: &App| -> RedemptionFeeResponse {
+        app.wrap()
+            .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryRedemptionFee {})
+            .unwrap()
+    };
+    let redeem_msg = |amount: u128| Cw20ExecuteMsg::Send {
+        contract: dira_contract.to_string(),
+        amount: Uint128::new(amount),
+        msg: to_json_binary(&DiraReceiveMsg::Redeem {
+            collateral_id: "uatom".to_string(),
+        })
+        .unwrap(),
+    };
+
+    let res = query_redemption_queue(&app);
+    assert_eq!(res.positions.len(), 2);
+    assert_eq!(res.positions[0].wallet_address, admin);
+    assert_eq!(res.positions[0].dira_owed, Decimal::from_ratio(2u128, 1u128));
+    assert_eq!(res.positions[1].wallet_address, user);
+
+    let res = query_redemption_fee(&app);
+    assert!(res.base_rate.is_zero());
+    assert_eq!(res.redemption_fee, Decimal::permille(5));
+
+    // Redeeming 3 of the 12 DIRA in circulation clears the admin's 2 DIRA of debt and takes
+    // the other 1 DIRA from the user's position, for 0.3 atom. The base rate rises to 12.5%,
+    // so 13% of the collateral is kept as a fee for the treasury
+    let user_atom_before = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+    let admin_atom_before = app.wrap().query_balance(&admin, "uatom").unwrap().amount;
+    app.execute_contract(user.clone(), cw20_contract.clone(), &redeem_msg(3_000_000), &[])
+        .unwrap();
+    assert_eq!(
+        app.wrap().query_balance(&user, "uatom").unwrap().amount - user_atom_before,
+        Uint128::new(261_000)
+    );
+    assert_eq!(
+        app.wrap().query_balance(&admin, "uatom").unwrap().amount - admin_atom_before,
+        Uint128::new(39_000)
+    );
+
+    let res: MintedDiraResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryMintedDira {
+                wallet_address_to_query: admin.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert!(res.dira_minted.is_zero());
+
+    let res: CollateralResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryLockedCollateral {
+                wallet_address_to_query: admin.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.collateral_locked, Decimal::from_ratio(8u128, 10u128));
+
+    // Positions without debt leave the queue
+    let res = query_redemption_queue(&app);
+    assert_eq!(res.positions.len(), 1);
+    assert_eq!(res.positions[0].wallet_address, user);
+    assert_eq!(res.positions[0].dira_owed, Decimal::from_ratio(9u128, 1u128));
+    assert_eq!(
+        res.positions[0].collateral_locked,
+        Decimal::from_ratio(59u128, 10u128)
+    );
+
+    let res = query_redemption_fee(&app);
+    assert_eq!(res.base_rate, Decimal::permille(125));
+    assert_eq!(res.redemption_fee, Decimal::permille(130));
+
+    // The base rate halves every 12 hours
+    app.update_block(|block| block.time = block.time.plus_seconds(12 * 60 * 60));
+
+    let res = query_redemption_fee(&app);
+    assert!(res.base_rate > Decimal::from_ratio(624u128, 10000u128));
+    assert!(res.base_rate < Decimal::from_ratio(626u128, 10000u128));
+
+    // Positions below the liquidation health are left for liquidators
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id: "uatom".to_string(),
+            collateral_price_in_dirham: Decimal::one(),
+        },
+        &[],
+    )
+    .unwrap();
+    let res = app.execute_contract(user.clone(), cw20_contract.clone(), &redeem_msg(1_000_000), &[]);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_redemption_past_underwater_positions() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    let set_price = |price: Decimal| DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
+        collateral_price_in_dirham: price,
+    };
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price(Decimal::from_ratio(10u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+
+    // More positions than a redemption redeems from hold 1 atom behind 5 DIRA each, ahead of
+    // the user's 10 atom behind 10 DIRA in the redemption queue
+    let mut positions = (0..51)
+        .map(|index| (app.api().addr_make(&format!("minter{index}")), 1_000_000u128, 5u128))
+        .collect::<Vec<_>>();
+    positions.push((user.clone(), 10_000_000, 10));
+
+    for (wallet, collateral, dira_to_mint) in &positions {
+        if *wallet != user {
+            app.send_tokens(admin.clone(), wallet.clone(), &coins(*collateral, "uatom"))
+                .unwrap();
+        }
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::LockCollateral {
+                collateral_id: "uatom".to_string(),
+            },
+            &coins(*collateral, "uatom"),
+        )
+        .unwrap();
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::MintDira {
+                collateral_id: "uatom".to_string(),
+                dira_to_mint: Decimal::from_ratio(*dira_to_mint, 1u128),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    // At a price of 5.00 every position ahead of the user's falls below the liquidation health.
+    // They are left for liquidators, and the redemption reaches the user's position behind them
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price(Decimal::from_ratio(5u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: dira_contract.to_string(),
+            amount: Uint128::new(1_000_000),
+            msg: to_json_binary(&DiraReceiveMsg::Redeem {
+                collateral_id: "uatom".to_string(),
+            })
+            .unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let res: MintedDiraResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryMintedDira {
+                wallet_address_to_query: user.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.dira_minted, Decimal::from_ratio(9u128, 1u128));
+
+    let res: MintedDiraResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryMintedDira {
+                wallet_address_to_query: positions[0].0.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.dira_minted, Decimal::from_ratio(5u128, 1u128));
+
+    // The redemption queue query lists at most 50 positions, whatever limit is asked for
+    let res: RedemptionQueueResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryRedemptionQueue {
+                collateral_id: "uatom".to_string(),
+                limit: Some(1000),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.positions.len(), 50);
+}
+
+#[test]
+fn test_collateral_price_oracle() {
+    let (mut app, dira_contract, _cw20_contract, admin, user) = setup_app();
+
+    let oracle_code_id = app.store_code(mock_oracle_contract());
+    let oracle_contract = app
+        .instantiate_contract(
+            oracle_code_id,
+            admin.clone(),
+            &MockOracleMsg {
+                price: Decimal::from_ratio(20u128, 1u128),
+            },
+            &[],
+            "Mock Price Oracle",
+            None,
+        )
+        .unwrap();
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id: "uatom".to_string(),
+            collateral_price_in_dirham: Decimal::from_ratio(10u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let query_collateral_price = |app: &App| -> Decimal {
+        let res: CollateralPriceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryCollateralPrice {
+                    collateral_id: "uatom".to_string(),
+                },
+            )
+            .unwrap();
+        res.collateral_price
+    };
+    assert_eq!(query_collateral_price(&app), Decimal::from_ratio(10u128, 1u128));
+
+    // Only admins can point a collateral type at an oracle, and it has to answer price queries
+    let set_oracle_msg = |oracle_address: &Addr| DiraExecuteMsg::SetCollateralPriceSource {
+        collateral_id: "uatom".to_string(),
+        price_source: PriceSource::Oracle {
+            oracle_address: oracle_address.clone(),
+        },
+    };
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &set_oracle_msg(&oracle_contract),
+        &[],
+    );
+    assert!(res.is_err());
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &set_oracle_msg(&user), &[]);
+    assert!(res.is_err());
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_oracle_msg(&oracle_contract),
+        &[],
+    )
+    .unwrap();
+
+    let res: CollateralInfoResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryCollateralInfo {
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.collateral_info.price_source,
+        PriceSource::Oracle {
+            oracle_address: oracle_contract.clone(),
+        }
+    );
+    assert_eq!(query_collateral_price(&app), Decimal::from_ratio(20u128, 1u128));
+
+    // At the oracle price of 20, 1 atom can back 15 DIRA, which the admin price of 10 would not allow
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LockCollateral {
+            collateral_id: "uatom".to_string(),
+        },
+        &coins(1_000_000, "uatom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::MintDira {
+            collateral_id: "uatom".to_string(),
+            dira_to_mint: Decimal::from_ratio(15u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // The oracle is read at execution time, so a price drop makes the position liquidatable
+    app.execute_contract(
+        admin.clone(),
+        oracle_contract.clone(),
+        &MockOracleMsg {
+            price: Decimal::from_ratio(15u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+    let res: StablecoinHealthResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryStablecoinHealth {
+                stablecoin_minter_address_to_query: user.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert!(res.health < Decimal::from_ratio(110u128, 100u128));
+
+    // Going back to the manual price uses the admin set price again
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceSource {
+            collateral_id: "uatom".to_string(),
+            price_source: PriceSource::Manual,
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_collateral_price(&app), Decimal::from_ratio(10u128, 1u128));
+}
+
+#[test]
+fn test_price_feeders() {
+    let (mut app, dira_contract, _cw20_contract, admin, user) = setup_app();
+    let feeder = app.api().addr_make("feeder");
+
+    let submit_price_msg = |price: u128| DiraExecuteMsg::SubmitPrice {
+        collateral_id: "uatom".to_string(),
+        price: Decimal::from_ratio(price, 1u128),
+    };
+    let query_collateral_price = |app: &App| -> StdResult<Decimal> {
+        let res: CollateralPriceResponse = app.wrap().query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryCollateralPrice {
+                collateral_id: "uatom".to_string(),
+            },
+        )?;
+        Ok(res.collateral_price)
+    };
+    let mint_msg = |dira_to_mint: u128| DiraExecuteMsg::MintDira {
+        collateral_id: "uatom".to_string(),
+        dira_to_mint: Decimal::from_ratio(dira_to_mint, 1u128),
+    };
+
+    // Feeders are separate from admins, and only admins can add them
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &submit_price_msg(30), &[]);
+    assert!(res.is_err());
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::AddPriceFeeder {
+            feeder_address: user.clone(),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    for feeder_address in [&admin, &user, &feeder] {
+        app.execute_contract(
+            admin.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::AddPriceFeeder {
+                feeder_address: feeder_address.clone(),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    // A collateral type can only be priced by the feeders once they have submitted prices
+    let set_feeders_price_source_msg = DiraExecuteMsg::SetCollateralPriceSource {
+        collateral_id: "uatom".to_string(),
+        price_source: PriceSource::Feeders,
+    };
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_feeders_price_source_msg,
+        &[],
+    );
+    assert!(res.is_err());
+
+    let res = app.execute_contract(feeder.clone(), dira_contract.clone(), &submit_price_msg(0), &[]);
+    assert_eq!(res.unwrap_err().root_cause().to_string(), "Price cannot be zero");
+
+    for (feeder_address, price) in [(&admin, 30), (&user, 34), (&feeder, 32)] {
+        app.execute_contract(
+            feeder_address.clone(),
+            dira_contract.clone(),
+            &submit_price_msg(price),
+            &[],
+        )
+        .unwrap();
+    }
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_feeders_price_source_msg,
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_collateral_price(&app).unwrap(), Decimal::from_ratio(32u128, 1u128));
+
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetPriceFeedConfig {
+            quorum: 0,
+            max_price_age_seconds: 600,
+        },
+        &[],
+    );
+    assert!(res.is_err());
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetPriceFeedConfig {
+            quorum: 2,
+            max_price_age_seconds: 600,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LockCollateral {
+            collateral_id: "uatom".to_string(),
+        },
+        &coins(1_000_000, "uatom"),
+    )
+    .unwrap();
+    app.execute_contract(user.clone(), dira_contract.clone(), &mint_msg(20), &[])
+        .unwrap();
+
+    // Once the prices go stale, a single fresh price is not enough to mint or unlock against
+    app.update_block(|block| block.time = block.time.plus_seconds(601));
+    app.execute_contract(feeder.clone(), dira_contract.clone(), &submit_price_msg(31), &[])
+        .unwrap();
+
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &mint_msg(1), &[]);
+    assert!(res.unwrap_err().root_cause().to_string().contains("fresh prices"));
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UnlockCollateral {
+            collateral_id: "uatom".to_string(),
+            collateral_amount_to_unlock: Decimal::percent(1),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+    assert!(query_collateral_price(&app).is_err());
+
+    // With two fresh prices the median is their average
+    app.execute_contract(user.clone(), dira_contract.clone(), &submit_price_msg(35), &[])
+        .unwrap();
+    assert_eq!(query_collateral_price(&app).unwrap(), Decimal::from_ratio(33u128, 1u128));
+    app.execute_contract(user.clone(), dira_contract.clone(), &mint_msg(1), &[])
+        .unwrap();
+
+    // Prices from removed feeders are no longer used
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RemovePriceFeeder {
+            feeder_address: user.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    assert!(query_collateral_price(&app).is_err());
+
+    let res: FeederPricesResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryFeederPrices {
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.prices.len(), 2);
+    assert_eq!(res.prices[0].feeder_address, admin);
+    assert!(res.prices[0].stale);
+    assert_eq!(res.prices[1].feeder_address, feeder);
+    assert!(!res.prices[1].stale);
+}
+
+#[test]
+fn test_price_guard() {
+    let (mut app, dira_contract, _cw20_contract, admin, user) = setup_app();
+
+    let set_price_msg = |price: Decimal| DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
+        collateral_price_in_dirham: price,
+    };
+    let query_collateral_price = |app: &App| -> Decimal {
+        let res: CollateralPriceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryCollateralPrice {
+                    collateral_id: "uatom".to_string(),
+                },
+            )
+            .unwrap();
+        res.collateral_price
+    };
+    let query_next_collateral_price = |app: &App| -> NextCollateralPriceResponse {
+        app.wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryNextCollateralPrice {
+                    collateral_id: "uatom".to_string(),
+                },
+            )
+            .unwrap()
+    };
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price_msg(Decimal::from_ratio(10u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+
+    // Only admins can guard prices
+    let set_price_guard_config_msg = DiraExecuteMsg::SetPriceGuardConfig {
+        max_price_deviation: Decimal::percent(10),
+        deviation_window_seconds: 3600,
+        price_delay_seconds: 3600,
+    };
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &set_price_guard_config_msg,
+        &[],
+    );
+    assert!(res.is_err());
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price_guard_config_msg,
+        &[],
+    )
+    .unwrap();
+
+    // A price ten times too high is rejected within the deviation window
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price_msg(Decimal::from_ratio(100u128, 1u128)),
+        &[],
+    );
+    assert!(res.is_err());
+
+    // A zero price is never accepted, whatever the deviation window
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &set_price_msg(Decimal::zero()), &[]);
+    assert_eq!(res.unwrap_err().root_cause().to_string(), "Price cannot be zero");
+
+    // A small move waits an hour as the next price before it is used
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price_msg(Decimal::from_ratio(105u128, 10u128)),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_collateral_price(&app), Decimal::from_ratio(10u128, 1u128));
+
+    let queued_at = app.block_info().time;
+    let res = query_next_collateral_price(&app);
+    assert_eq!(res.current_price, Some(Decimal::from_ratio(10u128, 1u128)));
+    assert_eq!(res.next_price, Some(Decimal::from_ratio(105u128, 10u128)));
+    assert_eq!(res.next_price_effective_at, Some(queued_at.plus_seconds(3600)));
+
+    // The deviation is measured against the queued price, not the one in use
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price_msg(Decimal::from_ratio(94u128, 10u128)),
+        &[],
+    );
+    assert!(res.is_err());
+
+    app.update_block(|block| block.time = block.time.plus_seconds(3600));
+
+    assert_eq!(query_collateral_price(&app), Decimal::from_ratio(105u128, 10u128));
+    let res = query_next_collateral_price(&app);
+    assert_eq!(res.current_price, Some(Decimal::from_ratio(105u128, 10u128)));
+    assert_eq!(res.next_price, None);
+
+    // Once the deviation window has passed, larger moves are accepted but still delayed
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price_msg(Decimal::from_ratio(20u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_collateral_price(&app), Decimal::from_ratio(105u128, 10u128));
+    assert_eq!(
+        query_next_collateral_price(&app).next_price,
+        Some(Decimal::from_ratio(20u128, 1u128))
+    );
+}
+
+#[test]
+fn test_twap_price() {
+    let (mut app, dira_contract, _cw20_contract, admin, user) = setup_app();
+
+    let pair_code_id = app.store_code(mock_pair_contract());
+    let pair_contract = app
+        .instantiate_contract(
+            pair_code_id,
+            admin.clone(),
+            &MockPairMsg {
+                price: Decimal::from_ratio(30u128, 1u128),
+            },
+            &[],
+            "Mock ATOM/AED Pair",
+            None,
+        )
+        .unwrap();
+
+    let set_twap_price_source_msg = |window_seconds: u64| DiraExecuteMsg::SetCollateralPriceSource {
+        collateral_id: "uatom".to_string(),
+        price_source: PriceSource::Twap {
+            pair_address: pair_contract.clone(),
+            window_seconds,
+        },
+    };
+    let query_collateral_price = |app: &App| -> StdResult<Decimal> {
+        let res: CollateralPriceResponse = app.wrap().query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryCollateralPrice {
+                collateral_id: "uatom".to_string(),
+            },
+        )?;
+        Ok(res.collateral_price)
+    };
+    let record_observation_msg = DiraExecuteMsg::RecordPriceObservation {
+        collateral_id: "uatom".to_string(),
+    };
+
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &set_twap_price_source_msg(0), &[]);
+    assert!(res.is_err());
+    app.execute_contract(admin.clone(), dira_contract.clone(), &set_twap_price_source_msg(3600), &[])
+        .unwrap();
+
+    // There is no price until a whole window has been observed
+    assert!(query_collateral_price(&app).is_err());
+
+    // Half the window at 30 and half at 40 averages out to 35
+    app.update_block(|block| block.time = block.time.plus_seconds(1800));
+    app.execute_contract(
+        admin.clone(),
+        pair_contract.clone(),
+        &MockPairMsg {
+            price: Decimal::from_ratio(40u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(1800));
+    assert_eq!(query_collateral_price(&app).unwrap(), Decimal::from_ratio(35u128, 1u128));
+
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LockCollateral {
+            collateral_id: "uatom".to_string(),
+        },
+        &coins(1_000_000, "uatom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::MintDira {
+            collateral_id: "uatom".to_string(),
+            dira_to_mint: Decimal::from_ratio(25u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // Anyone can record observations, moving the TWAP on to the latest window
+    app.execute_contract(user.clone(), dira_contract.clone(), &record_observation_msg, &[])
+        .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(3600));
+    assert_eq!(query_collateral_price(&app).unwrap(), Decimal::from_ratio(40u128, 1u128));
+
+    // Without fresh observations the TWAP goes stale
+    app.update_block(|block| block.time = block.time.plus_seconds(3601));
+    assert!(query_collateral_price(&app).is_err());
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::MintDira {
+            collateral_id: "uatom".to_string(),
+            dira_to_mint: Decimal::one(),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    // Only collateral types priced by a TWAP have observations to record
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceSource {
+            collateral_id: "uatom".to_string(),
+            price_source: PriceSource::Manual,
+        },
+        &[],
+    )
+    .unwrap();
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &record_observation_msg, &[]);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_price_history() {
+    let (mut app, dira_contract, _cw20_contract, admin, _user) = setup_app();
+
+    // Prices are recorded to the second
+    let start_time = Timestamp::from_seconds(app.block_info().time.seconds());
+    let set_price_msg = |price: Decimal| DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
+        collateral_price_in_dirham: price,
+    };
+    let query_price_history =
+        |app: &App, start_after: Option<Timestamp>, limit: Option<u32>| -> Vec<PriceHistoryEntry> {
+            let res: PriceHistoryResponse = app
+                .wrap()
+                .query_wasm_smart(
+                    dira_contract.clone(),
+                    &StableDiraQueryMsg::QueryPriceHistory {
+                        collateral_id: "uatom".to_string(),
+                        start_after,
+                        limit,
+                    },
+                )
+                .unwrap();
+            res.prices
+        };
+    let query_price_at = |app: &App, time: Timestamp| -> StdResult<PriceHistoryEntry> {
+        app.wrap().query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryPriceAt {
+                collateral_id: "uatom".to_string(),
+                time,
+            },
+        )
+    };
+
+    // Nothing is on record before the first price
+    assert!(query_price_at(&app, start_time).is_err());
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price_msg(Decimal::from_ratio(10u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price_msg(Decimal::from_ratio(11u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+
+    // Delayed prices only make it into the history once they take effect
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetPriceGuardConfig {
+            max_price_deviation: Decimal::percent(10),
+            deviation_window_seconds: 0,
+            price_delay_seconds: 60,
+        },
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price_msg(Decimal::from_ratio(12u128, 1u128)),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_price_history(&app, None, None).len(), 2);
+
+    // A next price replaced before it took effect drops out of the history
+    app.update_block(|block| block.time = block.time.plus_seconds(10));
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &set_price_msg(Decimal::from_ratio(121u128, 10u128)),
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(90));
+
+    assert_eq!(
+        query_price_history(&app, None, None),
+        vec![
+            PriceHistoryEntry {
+                time: start_time,
+                price: Decimal::from_ratio(10u128, 1u128),
+            },
+            PriceHistoryEntry {
+                time: start_time.plus_seconds(100),
+                price: Decimal::from_ratio(11u128, 1u128),
+            },
+            PriceHistoryEntry {
+                time: start_time.plus_seconds(270),
+                price: Decimal::from_ratio(121u128, 10u128),
+            },
+        ]
+    );
+
+    // The history can be paged through
+    let first_page = query_price_history(&app, None, Some(2));
+    assert_eq!(first_page.len(), 2);
+    let second_page = query_price_history(&app, Some(first_page[1].time), Some(2));
+    assert_eq!(second_page.len(), 1);
+    assert_eq!(second_page[0].price, Decimal::from_ratio(121u128, 10u128));
+
+    // The price in use at a given time is the last one recorded before it
+    let price_at = query_price_at(&app, start_time.plus_seconds(150)).unwrap();
+    assert_eq!(price_at.time, start_time.plus_seconds(100));
+    assert_eq!(price_at.price, Decimal::from_ratio(11u128, 1u128));
+    let price_at = query_price_at(&app, start_time.plus_seconds(265)).unwrap();
+    assert_eq!(price_at.price, Decimal::from_ratio(11u128, 1u128));
+    let price_at = query_price_at(&app, start_time.plus_seconds(270)).unwrap();
+    assert_eq!(price_at.price, Decimal::from_ratio(121u128, 10u128));
+}
+
+#[test]
+fn test_admin_management() {
+    let (mut app, dira_contract, _cw20_contract, admin, user) = setup_app();
+
+    let new_admin = app.api().addr_make("new_admin");
+    let query_admins = |app: &App| -> Vec<Addr> {
+        let res: AdminAddressesResponse = app
+            .wrap()
+            .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryAdminAddresses {})
+            .unwrap();
+        res.admin_addresses
+    };
+    let query_ownership = |app: &App| -> OwnershipResponse {
+        app.wrap()
+            .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryOwnership {})
+            .unwrap()
+    };
+
+    // The last admin cannot be removed
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RemoveAdmin {
+            admin_address: admin.clone(),
+        },
+        &[],
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "The last admin cannot be removed"
+    );
+
+    // Only admins can add admins
+    let add_admin_msg = DiraExecuteMsg::AddAdmin {
+        admin_address: new_admin.clone(),
+    };
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &add_admin_msg, &[]);
+    assert!(res.is_err());
+
+    let res = app
+        .execute_contract(admin.clone(), dira_contract.clone(), &add_admin_msg, &[])
+        .unwrap();
+    assert!(res.events.iter().any(|event| event.ty == "wasm-admin_added"));
+    assert_eq!(query_admins(&app), vec![admin.clone(), new_admin.clone()]);
+
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &add_admin_msg, &[]);
+    assert!(res.is_err());
+
+    // Admins can remove each other, but not the owner
+    let res = app.execute_contract(
+        new_admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RemoveAdmin {
+            admin_address: admin.clone(),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RemoveAdmin {
+            admin_address: new_admin.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_admins(&app), vec![admin.clone()]);
+
+    // Only the owner can propose an ownership transfer
+    let propose_msg = DiraExecuteMsg::ProposeOwnershipTransfer {
+        new_owner: user.clone(),
+        expires_in_seconds: 3600,
+    };
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &propose_msg, &[]);
+    assert!(res.is_err());
+
+    // Proposals expire within thirty days
+    for expires_in_seconds in [0, 30 * 24 * 60 * 60 + 1, u64::MAX] {
+        let res = app.execute_contract(
+            admin.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::ProposeOwnershipTransfer {
+                new_owner: user.clone(),
+                expires_in_seconds,
+            },
+            &[],
+        );
+        assert_eq!(
+            res.unwrap_err().root_cause().to_string(),
+            "Ownership transfer expiry has to be higher than zero and at most 2592000 seconds"
+        );
+    }
+
+    app.execute_contract(admin.clone(), dira_contract.clone(), &propose_msg, &[])
+        .unwrap();
+    assert_eq!(query_ownership(&app).pending_owner, Some(user.clone()));
+
+    // Expired proposals cannot be accepted
+    app.update_block(|block| block.time = block.time.plus_seconds(3600));
+    assert_eq!(query_ownership(&app).pending_owner, None);
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::AcceptOwnershipTransfer {},
+        &[],
+    );
+    assert!(res.is_err());
+
+    // Only the proposed owner can accept
+    app.execute_contract(admin.clone(), dira_contract.clone(), &propose_msg, &[])
+        .unwrap();
+    let res = app.execute_contract(
+        new_admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::AcceptOwnershipTransfer {},
+        &[],
+    );
+    assert!(res.is_err());
+
+    let res = app
+        .execute_contract(
+            user.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::AcceptOwnershipTransfer {},
+            &[],
+        )
+        .unwrap();
+    assert!(res.events.iter().any(|event| event.ty == "wasm-ownership_transferred"));
+
+    // The previous owner stays on as an admin
+    let ownership = query_ownership(&app);
+    assert_eq!(ownership.owner, user.clone());
+    assert_eq!(ownership.pending_owner, None);
+    assert_eq!(query_admins(&app), vec![user.clone(), admin.clone()]);
+
+    // Cancelling needs a pending transfer, and only the owner can cancel
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::CancelOwnershipTransfer {},
+        &[],
+    );
+    assert!(res.is_err());
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::ProposeOwnershipTransfer {
+            new_owner: new_admin.clone(),
+            expires_in_seconds: 3600,
+        },
+        &[],
+    )
+    .unwrap();
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::CancelOwnershipTransfer {},
+        &[],
+    );
+    assert!(res.is_err());
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::CancelOwnershipTransfer {},
+        &[],
+    )
+    .unwrap();
+    let res = app.execute_contract(
+        new_admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::AcceptOwnershipTransfer {},
+        &[],
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_roles() {
+    let (mut app, dira_contract, _cw20_contract, admin, user) = setup_app();
+
+    let price_key = app.api().addr_make("price_key");
+    let new_admin = app.api().addr_make("new_admin");
+    let query_roles = |app: &App, address: &Addr| -> Vec<Role> {
+        let res: RolesResponse = app
+            .wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryRoles {
+                    address: Some(address.clone()),
+                },
+            )
+            .unwrap();
+        res.roles.into_iter().map(|role_grant| role_grant.role).collect()
+    };
+    let set_price_msg = DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
+        collateral_price_in_dirham: Decimal::from_ratio(10u128, 1u128),
+    };
+    let set_liquidation_health_msg = DiraExecuteMsg::SetLiquidationHealth {
+        collateral_id: "uatom".to_string(),
+        liquidation_health: Decimal::from_ratio(11u128, 10u128),
+    };
+    let grant_price_feeder_msg = DiraExecuteMsg::GrantRole {
+        role: Role::PriceFeeder,
+        address: price_key.clone(),
+    };
+
+    // The instantiator is the owner and holds every role
+    assert_eq!(query_roles(&app, &admin), Role::ALL.to_vec());
+
+    // Only the owner can grant roles
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &grant_price_feeder_msg, &[]);
+    assert!(res.is_err());
+    app.execute_contract(admin.clone(), dira_contract.clone(), &grant_price_feeder_msg, &[])
+        .unwrap();
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &grant_price_feeder_msg, &[]);
+    assert!(res.is_err());
+
+    let res: RolesResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryRoles { address: None },
+        )
+        .unwrap();
+    assert!(res.roles.contains(&RoleGrant {
+        role: Role::PriceFeeder,
+        address: price_key.clone(),
+    }));
+    assert_eq!(res.roles.len(), Role::ALL.len() + 1);
+
+    // The price key can set prices but has no power over risk parameters
+    app.execute_contract(price_key.clone(), dira_contract.clone(), &set_price_msg, &[])
+        .unwrap();
+    let res = app.execute_contract(
+        price_key.clone(),
+        dira_contract.clone(),
+        &set_liquidation_health_msg,
+        &[],
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "The user does not have the risk_manager role needed to perform this action"
+    );
+    let res = app.execute_contract(
+        price_key.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::GrantRole {
+            role: Role::RiskManager,
+            address: price_key.clone(),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    // Revoked roles can no longer be used
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RevokeRole {
+            role: Role::PriceFeeder,
+            address: price_key.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    assert!(query_roles(&app, &price_key).is_empty());
+    let res = app.execute_contract(price_key.clone(), dira_contract.clone(), &set_price_msg, &[]);
+    assert!(res.is_err());
+
+    // The owner keeps the Owner role until ownership is transferred
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RevokeRole {
+            role: Role::Owner,
+            address: admin.clone(),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    // Admins hold every role but the Owner role, and lose them all once removed
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::AddAdmin {
+            admin_address: new_admin.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        query_roles(&app, &new_admin),
+        vec![Role::PriceFeeder, Role::RiskManager, Role::FeeManager, Role::Pauser]
+    );
+    app.execute_contract(new_admin.clone(), dira_contract.clone(), &set_liquidation_health_msg, &[])
+        .unwrap();
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RemoveAdmin {
+            admin_address: new_admin.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    assert!(query_roles(&app, &new_admin).is_empty());
+}
+
+#[test]
+fn test_timelocked_parameter_changes() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    let set_liquidation_health_msg = DiraExecuteMsg::SetLiquidationHealth {
+        collateral_id: "uatom".to_string(),
+        liquidation_health: Decimal::percent(120),
+    };
+    let query_liquidation_health = |app: &App| -> Decimal {
+        let res: LiquidationHealthResponse = app
+            .wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryLiquidationHealth {
+                    collateral_id: "uatom".to_string(),
+                },
+            )
+            .unwrap();
+        res.liquidation_health
+    };
+    let query_queued_changes = |app: &App| -> QueuedChangesResponse {
+        app.wrap()
+            .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryQueuedChanges {})
+            .unwrap()
+    };
+
+    // Parameter changes are queued instead of applied
+    app.execute_contract(admin.clone(), dira_contract.clone(), &set_liquidation_health_msg, &[])
+        .unwrap();
+    assert_eq!(query_liquidation_health(&app), Decimal::percent(110));
+
+    let queued_changes = query_queued_changes(&app);
+    assert_eq!(queued_changes.timelock_delay_seconds, 2 * 24 * 60 * 60);
+    assert_eq!(queued_changes.changes.len(), 1);
+    assert_eq!(
+        queued_changes.changes[0].change,
+        ParameterChange::LiquidationHealth {
+            collateral_id: "uatom".to_string(),
+            liquidation_health: Decimal::percent(120),
+        }
+    );
+    assert_eq!(
+        queued_changes.changes[0].eta,
+        app.block_info().time.plus_seconds(2 * 24 * 60 * 60)
+    );
+
+    // They cannot be applied during the delay, and only admins can cancel them
+    let execute_queued_msg = DiraExecuteMsg::ExecuteQueued { id: 1 };
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &execute_queued_msg, &[]);
+    assert!(res.is_err());
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::CancelQueued { id: 1 },
+        &[],
+    );
+    assert!(res.is_err());
+
+    // Once the delay has passed anyone can apply them
+    app.update_block(|block| block.time = block.time.plus_seconds(2 * 24 * 60 * 60));
+    app.execute_contract(user.clone(), dira_contract.clone(), &execute_queued_msg, &[])
+        .unwrap();
+    assert_eq!(query_liquidation_health(&app), Decimal::percent(120));
+    assert!(query_queued_changes(&app).changes.is_empty());
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &execute_queued_msg, &[]);
+    assert!(res.is_err());
+
+    // Invalid changes cannot be queued
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetMintableHealth {
+            collateral_id: "uatom".to_string(),
+            mintable_health: Decimal::percent(115),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    // Liquidation health cannot be raised above the 150% mintable health either
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetLiquidationHealth {
+            collateral_id: "uatom".to_string(),
+            liquidation_health: Decimal::percent(160),
+        },
+        &[],
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "Mintable Health cannot be set to be lower than Liquidation Health"
+    );
+    assert!(query_queued_changes(&app).changes.is_empty());
+
+    // Cancelled changes are never applied
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCW20DiraContractAddress {
+            cw20_dira_contract_address: cw20_contract.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    let res: QueuedChangeResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryQueuedChange { id: 2 },
+        )
+        .unwrap();
+    assert_eq!(res.proposer, admin.clone());
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::CancelQueued { id: 2 },
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(2 * 24 * 60 * 60));
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::ExecuteQueued { id: 2 },
+        &[],
+    );
+    assert!(res.is_err());
+
+    // The timelock delay is changed through the queue as well, and only between an hour and 30 days
+    for timelock_delay_seconds in [0, 59 * 60, 30 * 24 * 60 * 60 + 1, u64::MAX] {
+        let res = app.execute_contract(
+            admin.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::SetTimelockDelay {
+                timelock_delay_seconds,
+            },
+            &[],
+        );
+        assert_eq!(
+            res.unwrap_err().root_cause().to_string(),
+            "Timelock delay has to be between 3600 and 2592000 seconds"
+        );
+    }
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetTimelockDelay {
+            timelock_delay_seconds: 3600,
+        },
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(2 * 24 * 60 * 60));
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::ExecuteQueued { id: 3 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_queued_changes(&app).timelock_delay_seconds, 3600);
+
+    // Changes left in the queue for too long expire
+    app.execute_contract(admin.clone(), dira_contract.clone(), &set_liquidation_health_msg, &[])
+        .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(3600 + 14 * 24 * 60 * 60));
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::ExecuteQueued { id: 4 },
+        &[],
+    );
+    assert!(res.unwrap_err().root_cause().to_string().contains("expired"));
+}
+
+#[test]
+fn test_pause() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    let set_price_msg = DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
+        collateral_price_in_dirham: Decimal::from_ratio(10u128, 1u128),
+    };
+    let lock_msg = DiraExecuteMsg::LockCollateral {
+        collateral_id: "uatom".to_string(),
+    };
+    let mint_msg = DiraExecuteMsg::MintDira {
+        collateral_id: "uatom".to_string(),
+        dira_to_mint: Decimal::one(),
+    };
+    let unlock_msg = DiraExecuteMsg::UnlockCollateral {
+        collateral_id: "uatom".to_string(),
+        collateral_amount_to_unlock: Decimal::percent(10),
+    };
+    let repay_msg = Cw20ExecuteMsg::Send {
+        contract: dira_contract.to_string(),
+        amount: Uint128::from(500_000u128),
+        msg: to_json_binary(&DiraReceiveMsg::Repay {
+            collateral_id: "uatom".to_string(),
+        })
+        .unwrap(),
+    };
+    let pause_msg = |minting: bool, price_updates: bool, duration_seconds: u64| DiraExecuteMsg::Pause {
+        minting,
+        unlocking: minting,
+        liquidations: false,
+        price_updates,
+        duration_seconds,
+    };
+    let query_pause_state = |app: &App| -> PauseStateResponse {
+        app.wrap()
+            .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryPauseState {})
+            .unwrap()
+    };
+
+    app.execute_contract(admin.clone(), dira_contract.clone(), &set_price_msg, &[])
+        .unwrap();
+    app.execute_contract(user.clone(), dira_contract.clone(), &lock_msg, &coins(1_000_000, "uatom"))
+        .unwrap();
+    app.execute_contract(user.clone(), dira_contract.clone(), &mint_msg, &[])
+        .unwrap();
+
+    // Only the guardian can pause, and only for a limited time
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &pause_msg(true, false, 3600), &[]);
+    assert!(res.is_err());
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &pause_msg(true, false, 0), &[]);
+    assert!(res.is_err());
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &pause_msg(true, false, 8 * 24 * 60 * 60),
+        &[],
+    );
+    assert!(res.is_err());
+
+    app.execute_contract(admin.clone(), dira_contract.clone(), &pause_msg(true, false, 3600), &[])
+        .unwrap();
+    let pause_state = query_pause_state(&app);
+    assert!(pause_state.flags.minting && pause_state.flags.unlocking);
+    assert_eq!(pause_state.paused_until, Some(app.block_info().time.plus_seconds(3600)));
+
+    // Minting and unlocking are paused, locking and repaying never are
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &mint_msg, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "Contract is paused for minting"
+    );
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &unlock_msg, &[]);
+    assert!(res.is_err());
+    app.execute_contract(user.clone(), dira_contract.clone(), &lock_msg, &coins(1_000_000, "uatom"))
+        .unwrap();
+    app.execute_contract(user.clone(), cw20_contract.clone(), &repay_msg, &[])
+        .unwrap();
+    app.execute_contract(admin.clone(), dira_contract.clone(), &set_price_msg, &[])
+        .unwrap();
+
+    // Pausing again adds operations without pushing the deadline out
+    app.update_block(|block| block.time = block.time.plus_seconds(1800));
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &pause_msg(false, true, 7 * 24 * 60 * 60),
+        &[],
+    )
+    .unwrap();
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &set_price_msg, &[]);
+    assert!(res.is_err());
+    assert_eq!(
+        query_pause_state(&app).paused_until,
+        Some(app.block_info().time.plus_seconds(1800))
+    );
+
+    // Everything is unpaused automatically at the deadline
+    app.update_block(|block| block.time = block.time.plus_seconds(1800));
+    assert_eq!(
+        query_pause_state(&app),
+        PauseStateResponse {
+            flags: PauseFlags::default(),
+            paused_until: None,
+        }
+    );
+    app.execute_contract(user.clone(), dira_contract.clone(), &mint_msg, &[])
+        .unwrap();
+    app.execute_contract(user.clone(), dira_contract.clone(), &unlock_msg, &[])
+        .unwrap();
+
+    // The contract cannot be paused again right away
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &pause_msg(true, false, 3600), &[]);
+    assert!(res.is_err());
+    app.update_block(|block| block.time = block.time.plus_seconds(2 * 24 * 60 * 60));
+    app.execute_contract(admin.clone(), dira_contract.clone(), &pause_msg(true, false, 3600), &[])
+        .unwrap();
+
+    // Pausing minting also stops the peg stability module and Drip from minting DIRA
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RegisterPsmAsset {
+            asset_id: "uom".to_string(),
+            asset_type: CollateralAssetType::Native,
+            decimals: 6,
+            price_in_dirham: Decimal::one(),
+            fee_in: Decimal::percent(1),
+            fee_out: Decimal::percent(1),
+            reserve_cap: None,
+        },
+        &[],
+    )
+    .unwrap();
+    let swap_in_msg = DiraExecuteMsg::SwapStablecoinForDira {
+        asset_id: "uom".to_string(),
+    };
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &swap_in_msg, &coins(1_000_000, "uom"));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "Contract is paused for minting"
+    );
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &DiraExecuteMsg::Drip {}, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "Contract is paused for minting"
+    );
+
+    // The guardian can unpause early, which starts the cooldown right away
+    app.execute_contract(admin.clone(), dira_contract.clone(), &DiraExecuteMsg::Unpause {}, &[])
+        .unwrap();
+    app.execute_contract(user.clone(), dira_contract.clone(), &mint_msg, &[])
+        .unwrap();
+    app.execute_contract(user.clone(), dira_contract.clone(), &swap_in_msg, &coins(1_000_000, "uom"))
+        .unwrap();
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &pause_msg(true, false, 3600), &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        format!(
+            "The contract was paused too recently, it can be paused again at {}",
+            app.block_info().time.plus_seconds(2 * 24 * 60 * 60)
+        )
+    );
+    app.execute_contract(user.clone(), dira_contract.clone(), &mint_msg, &[])
+        .unwrap();
+}
+
+#[test]
+fn test_global_shutdown() {
+    let (mut app, dira_contract, cw20_contract, admin, user) = setup_app();
+
+    let uatom = "uatom".to_string();
+    let query_locked = |app: &App, wallet: &Addr| -> Decimal {
+        let res: CollateralResponse = app
+            .wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryLockedCollateral {
+                    wallet_address_to_query: wallet.clone(),
+                    collateral_id: "uatom".to_string(),
+                },
+            )
+            .unwrap();
+        res.collateral_locked
+    };
+    let query_dira_balance = |app: &App, wallet: &Addr| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                cw20_contract.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: wallet.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+    let settle_msg = |wallet: &Addr| DiraExecuteMsg::SettlePosition {
+        wallet_address: wallet.clone(),
+        collateral_id: "uatom".to_string(),
+    };
+    let cash_out_msg = |amount: u128| Cw20ExecuteMsg::Send {
+        contract: dira_contract.to_string(),
+        amount: Uint128::new(amount),
+        msg: to_json_binary(&DiraReceiveMsg::CashOut {}).unwrap(),
+    };
+
+    // The user mints 5 DIRA and the admin 2 DIRA, each against 1 atom at a price of 10.00
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id: uatom.clone(),
+            collateral_price_in_dirham: Decimal::from_ratio(10u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+    for (wallet, dira_to_mint) in [(&user, 5u128), (&admin, 2u128)] {
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::LockCollateral {
+                collateral_id: uatom.clone(),
+            },
+            &coins(1_000_000, "uatom"),
+        )
+        .unwrap();
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::MintDira {
+                collateral_id: uatom.clone(),
+                dira_to_mint: Decimal::from_ratio(dira_to_mint, 1u128),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    // Nothing can be settled or cashed out before shutdown, and only the owner can shut down
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &settle_msg(&user), &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "The contract has not been shut down"
+    );
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &DiraExecuteMsg::Shutdown {}, &[]);
+    assert!(res.is_err());
+
+    app.execute_contract(admin.clone(), dira_contract.clone(), &DiraExecuteMsg::Shutdown {}, &[])
+        .unwrap();
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &DiraExecuteMsg::Shutdown {}, &[]);
+    assert!(res.is_err());
+
+    let shutdown: ShutdownResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryShutdown {})
+        .unwrap();
+    assert_eq!(shutdown.shutdown_at, Some(app.block_info().time));
+    assert_eq!(
+        shutdown.cash_out_opens_at,
+        Some(app.block_info().time.plus_seconds(7 * 24 * 60 * 60))
+    );
+    assert_eq!(shutdown.dira_supply, Some(Decimal::from_ratio(7u128, 1u128)));
+    assert_eq!(shutdown.collaterals.len(), 1);
+    assert_eq!(
+        shutdown.collaterals[0].shutdown_collateral.price,
+        Decimal::from_ratio(10u128, 1u128)
+    );
+    assert_eq!(
+        shutdown.collaterals[0].shutdown_collateral.total_debt,
+        Decimal::from_ratio(7u128, 1u128)
+    );
+
+    // Minting and unlocking stop for good, and later price updates no longer move the price
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::MintDira {
+            collateral_id: uatom.clone(),
+            dira_to_mint: Decimal::one(),
+        },
+        &[],
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "The contract has been shut down"
+    );
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UnlockCollateral {
+            collateral_id: uatom.clone(),
+            collateral_amount_to_unlock: Decimal::percent(10),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id: uatom.clone(),
+            collateral_price_in_dirham: Decimal::from_ratio(20u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // Nothing can change the DIRA supply or the collateral locked either, other than
+    // settling positions and cashing out
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LockCollateral {
+            collateral_id: uatom.clone(),
+        },
+        &coins(1_000_000, "uatom"),
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "The contract has been shut down"
+    );
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &DiraExecuteMsg::Drip {}, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "The contract has been shut down"
+    );
+    for receive_msg in [
+        DiraReceiveMsg::Repay {
+            collateral_id: uatom.clone(),
+        },
+        DiraReceiveMsg::BidAuction {
+            auction_id: 1,
+            max_collateral: Decimal::one(),
+        },
+    ] {
+        let res = app.execute_contract(
+            user.clone(),
+            cw20_contract.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: dira_contract.to_string(),
+                amount: Uint128::new(1_000_000),
+                msg: to_json_binary(&receive_msg).unwrap(),
+            },
+            &[],
+        );
+        assert_eq!(
+            res.unwrap_err().root_cause().to_string(),
+            "The contract has been shut down"
+        );
+    }
+
+    // Anyone can settle the user's position. 5 DIRA of debt at the frozen price of 10.00
+    // nets 0.5 atom, and the other 0.5 atom goes back to the user
+    let user_atom_balance_before = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+    app.execute_contract(admin.clone(), dira_contract.clone(), &settle_msg(&user), &[])
+        .unwrap();
+    let user_atom_balance = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+    assert_eq!(user_atom_balance - user_atom_balance_before, Uint128::new(500_000));
+    assert_eq!(query_locked(&app, &user), Decimal::zero());
+
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &settle_msg(&user), &[]);
+    assert!(res.is_err());
+
+    // Cashing out waits for the settlement period while positions are left unsettled
+    let res = app.execute_contract(user.clone(), cw20_contract.clone(), &cash_out_msg(1_000_000), &[]);
+    assert!(res
+        .unwrap_err()
+        .root_cause()
+        .to_string()
+        .starts_with("Cashing out opens at"));
+
+    // Once the settlement period is over, the 0.5 atom netted so far backs the 7 DIRA in
+    // circulation. The user's 4.985 DIRA cash out for 0.356071 atom, rounded down
+    app.update_block(|block| block.time = block.time.plus_seconds(7 * 24 * 60 * 60));
+
+    let user_atom_balance_before = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+    let user_dira_balance = query_dira_balance(&app, &user);
+    app.execute_contract(
+        user.clone(),
+        cw20_contract.clone(),
+        &cash_out_msg(user_dira_balance.u128()),
+        &[],
+    )
+    .unwrap();
+    let user_atom_balance = app.wrap().query_balance(&user, "uatom").unwrap().amount;
+    assert_eq!(user_atom_balance - user_atom_balance_before, Uint128::new(356_071));
+    assert_eq!(query_dira_balance(&app, &user), Uint128::zero());
+
+    // Settling the admin's position afterwards adds its 0.2 atom to the backing left for the
+    // 2.015 DIRA not cashed out yet, and the admin cashes all of them out for all of it
+    app.execute_contract(user.clone(), dira_contract.clone(), &settle_msg(&admin), &[])
+        .unwrap();
+    assert_eq!(query_locked(&app, &admin), Decimal::zero());
+
+    let shutdown: ShutdownResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryShutdown {})
+        .unwrap();
+    assert_eq!(shutdown.dira_cashed_out, Some(Decimal::from_ratio(4985u128, 1000u128)));
+
+    let admin_atom_balance_before = app.wrap().query_balance(&admin, "uatom").unwrap().amount;
+    let admin_dira_balance = query_dira_balance(&app, &admin);
+    app.execute_contract(
+        admin.clone(),
+        cw20_contract.clone(),
+        &cash_out_msg(admin_dira_balance.u128()),
+        &[],
+    )
+    .unwrap();
+    let admin_atom_balance = app.wrap().query_balance(&admin, "uatom").unwrap().amount;
+    assert_eq!(admin_atom_balance - admin_atom_balance_before, Uint128::new(343_928));
+
+    let shutdown: ShutdownResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryShutdown {})
+        .unwrap();
+    assert_eq!(
+        shutdown.collaterals[0].shutdown_collateral.settled_debt,
+        Decimal::from_ratio(7u128, 1u128)
+    );
+    assert_eq!(shutdown.collaterals[0].shutdown_collateral.backing, Decimal::zero());
+    assert_eq!(shutdown.dira_cashed_out, Some(Decimal::from_ratio(7u128, 1u128)));
+}
+
+#[test]
+fn test_shutdown_without_live_prices() {
+    let (mut app, dira_contract, _, admin, user) = setup_app();
+
+    let query_shutdown = |app: &App| -> ShutdownResponse {
+        app.wrap()
+            .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryShutdown {})
+            .unwrap()
+    };
+
+    let submit_price_msg = DiraExecuteMsg::SubmitPrice {
+        collateral_id: "uatom".to_string(),
+        price: Decimal::from_ratio(10u128, 1u128),
+    };
+
+    // The user mints 5 DIRA against 1 atom priced at 10.00 by a single feeder
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::AddPriceFeeder {
+            feeder_address: admin.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(admin.clone(), dira_contract.clone(), &submit_price_msg, &[])
+        .unwrap();
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceSource {
+            collateral_id: "uatom".to_string(),
+            price_source: PriceSource::Feeders,
+        },
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(60));
+    app.execute_contract(admin.clone(), dira_contract.clone(), &submit_price_msg, &[])
+        .unwrap();
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LockCollateral {
+            collateral_id: "uatom".to_string(),
+        },
+        &coins(1_000_000, "uatom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::MintDira {
+            collateral_id: "uatom".to_string(),
+            dira_to_mint: Decimal::from_ratio(5u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // A second collateral type is locked but never priced
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RegisterCollateral {
+            collateral_id: "uom".to_string(),
+            asset_type: CollateralAssetType::Native,
+            liquidation_health: Decimal::percent(120),
+            mintable_health: Decimal::percent(150),
+            debt_ceiling: None,
+            decimals: 6,
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LockCollateral {
+            collateral_id: "uom".to_string(),
+        },
+        &coins(1_000_000, "uom"),
+    )
+    .unwrap();
+
+    // Once the feeder's price goes stale, shutdown freezes the atom at its last recorded price
+    // and the unpriced collateral type without debt at zero
+    app.update_block(|block| block.time = block.time.plus_seconds(2 * 60 * 60));
+    app.execute_contract(admin.clone(), dira_contract.clone(), &DiraExecuteMsg::Shutdown {}, &[])
+        .unwrap();
+
+    let shutdown = query_shutdown(&app);
+    assert_eq!(shutdown.collaterals.len(), 2);
+    assert_eq!(shutdown.collaterals[0].collateral_id, "uatom");
+    assert_eq!(
+        shutdown.collaterals[0].shutdown_collateral.price,
+        Decimal::from_ratio(10u128, 1u128)
+    );
+    assert_eq!(shutdown.collaterals[1].collateral_id, "uom");
+    assert_eq!(shutdown.collaterals[1].shutdown_collateral.price, Decimal::zero());
+    assert_eq!(shutdown.collaterals[1].shutdown_collateral.total_debt, Decimal::zero());
+
+    // The unpriced collateral is handed back in full when its position is settled
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SettlePosition {
+            wallet_address: user.clone(),
+            collateral_id: "uom".to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_balance(user.clone(), "uom").unwrap().amount,
+        Uint128::new(100_000_000_000_000)
+    );
+}
+
+#[test]
+fn test_migrate() {
+    let (mut app, dira_contract, _, admin, user) = setup_app();
+
+    // A DIRA token minted by an instance of the first release, which holds 2 atom of collateral
+    // backing the user's 3 DIRA and the admin's 2 DIRA
+    let cw20_code_id = app.store_code(cw20_contract());
+    let cw20_contract = app
+        .instantiate_contract(
+            cw20_code_id,
+            admin.clone(),
+            &Cw20InstantiateMsg {
+                name: "Dira".to_string(),
+                symbol: "DIRA".to_string(),
+                decimals: 6,
+                initial_balances: vec![
+                    Cw20Coin {
+                        address: user.to_string(),
+                        amount: Uint128::new(3_000_000),
+                    },
+                    Cw20Coin {
+                        address: admin.to_string(),
+                        amount: Uint128::new(2_000_000),
+                    },
+                ],
+                mint: Some(MinterResponse {
+                    minter: admin.to_string(),
+                    cap: None,
+                }),
+                marketing: None,
+            },
+            &[],
+            "CW20 Dira Token",
+            None,
+        )
+        .unwrap();
+
+    let legacy_code_id = app.store_code(legacy_dira_contract());
+    let instantiate_legacy = |app: &mut App, contract_version: &str| -> Addr {
+        app.instantiate_contract(
+            legacy_code_id,
+            admin.clone(),
+            &LegacyDiraInstantiateMsg {
+                contract_version: contract_version.to_string(),
+                cw20_dira_contract_address: cw20_contract.clone(),
+                positions: vec![
+                    (user.clone(), Decimal::one(), Decimal::from_ratio(3u128, 1u128)),
+                    (admin.clone(), Decimal::one(), Decimal::from_ratio(2u128, 1u128)),
+                ],
+            },
+            &coins(2_000_000, "uatom"),
+            "Dira Stablecoin",
+            Some(admin.to_string()),
+        )
+        .unwrap()
+    };
+    let legacy_contract = instantiate_legacy(&mut app, "0.1.0");
+    app.execute_contract(
+        admin.clone(),
+        cw20_contract.clone(),
+        &Cw20ExecuteMsg::UpdateMinter {
+            new_minter: Some(legacy_contract.to_string()),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // Upgrade the instance to the code of this version
+    let dira_code_id = app.contract_data(&dira_contract).unwrap().code_id;
+    app.migrate_contract(admin.clone(), legacy_contract.clone(), &DiraMigrateMsg {}, dira_code_id)
+        .unwrap();
+
+    // Positions are kept, now under the collateral type the first release accepted
+    let res: CollateralResponse = app
+        .wrap()
+        .query_wasm_smart(
+            legacy_contract.clone(),
+            &StableDiraQueryMsg::QueryLockedCollateral {
+                wallet_address_to_query: user.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.collateral_locked, Decimal::one());
+
+    let query_minted = |app: &App| -> Decimal {
+        let res: MintedDiraResponse = app
+            .wrap()
+            .query_wasm_smart(
+                legacy_contract.clone(),
+                &StableDiraQueryMsg::QueryMintedDira {
+                    wallet_address_to_query: user.clone(),
+                    collateral_id: "uatom".to_string(),
+                },
+            )
+            .unwrap();
+        res.dira_minted
+    };
+    assert_eq!(query_minted(&app), Decimal::from_ratio(3u128, 1u128));
+
+    let res: CollateralPriceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            legacy_contract.clone(),
+            &StableDiraQueryMsg::QueryCollateralPrice {
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.collateral_price, Decimal::from_ratio(10u128, 1u128));
+
+    let res: LiquidationHealthResponse = app
+        .wrap()
+        .query_wasm_smart(
+            legacy_contract.clone(),
+            &StableDiraQueryMsg::QueryLiquidationHealth {
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.liquidation_health, Decimal::from_ratio(110u128, 100u128));
+
+    // The first release's owner holds every role, and its layout is cleaned up
+    let res: OwnershipResponse = app
+        .wrap()
+        .query_wasm_smart(legacy_contract.clone(), &StableDiraQueryMsg::QueryOwnership {})
+        .unwrap();
+    assert_eq!(res.owner, admin);
+    let res: RolesResponse = app
+        .wrap()
+        .query_wasm_smart(
+            legacy_contract.clone(),
+            &StableDiraQueryMsg::QueryRoles {
+                address: Some(admin.clone()),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.roles.len(), Role::ALL.len());
+    assert!(app
+        .wrap()
+        .query_wasm_raw(legacy_contract.clone(), b"native-token-name".to_vec())
+        .unwrap()
+        .is_none());
+
+    // Its parameters are moved into the config item, the rest get their defaults
+    let config: ConfigResponse = app
+        .wrap()
+        .query_wasm_smart(legacy_contract.clone(), &StableDiraQueryMsg::QueryConfig {})
+        .unwrap();
+    assert_eq!(config.cw20_dira_contract_address, Some(cw20_contract.clone()));
+    assert!(!config.fee_switch.enabled);
+    assert_eq!(config.close_factor, Decimal::percent(50));
+    assert_eq!(config.timelock_delay_seconds, 2 * 24 * 60 * 60);
+    for legacy_key in ["cw20-dira-contract-address", "fee_switch"] {
+        assert!(app
+            .wrap()
+            .query_wasm_raw(legacy_contract.clone(), legacy_key.as_bytes().to_vec())
+            .unwrap()
+            .is_none());
+    }
+
+    // The user keeps minting against the migrated position
+    app.execute_contract(
+        user.clone(),
+        legacy_contract.clone(),
+        &DiraExecuteMsg::MintDira {
+            collateral_id: "uatom".to_string(),
+            dira_to_mint: Decimal::one(),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_minted(&app), Decimal::from_ratio(4u128, 1u128));
+
+    // Migrating to the same version again leaves the state as it is
+    app.migrate_contract(admin.clone(), legacy_contract.clone(), &DiraMigrateMsg {}, dira_code_id)
+        .unwrap();
+    assert_eq!(query_minted(&app), Decimal::from_ratio(4u128, 1u128));
+
+    // Instances of a newer version cannot be migrated back
+    let newer_contract = instantiate_legacy(&mut app, "9.0.0");
+    let res = app.migrate_contract(admin.clone(), newer_contract, &DiraMigrateMsg {}, dira_code_id);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "Cannot migrate from version 9.0.0 to older version 0.2.0"
+    );
+}
+
+#[test]
+fn test_update_config() {
+    let (mut app, dira_contract, _, admin, user) = setup_app();
+
+    let query_config = |app: &App| -> ConfigResponse {
+        app.wrap()
+            .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryConfig {})
+            .unwrap()
+    };
+
+    let config = query_config(&app);
+    assert_eq!(config.owner, admin);
+    assert_eq!(config.close_factor, Decimal::percent(50));
+    assert!(config.fee_switch.enabled);
+    assert_eq!(config.timelock_delay_seconds, 2 * 24 * 60 * 60);
+    assert_eq!(config.collaterals.len(), 1);
+    assert_eq!(
+        config.collaterals[0].collateral_info.liquidation_health,
+        Decimal::from_ratio(110u128, 100u128)
+    );
+
+    // Updates need the role of every parameter changed, and at least one parameter
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::default()),
+        &[],
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "No config parameters to update"
+    );
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::GrantRole {
+            role: Role::FeeManager,
+            address: user.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::new(ConfigUpdate {
+            fee_switch_enabled: Some(false),
+            close_factor: Some(Decimal::percent(75)),
+            ..ConfigUpdate::default()
+        })),
+        &[],
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "The user does not have the risk_manager role needed to perform this action"
+    );
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::new(ConfigUpdate {
+            fee_switch_enabled: Some(false),
+            ..ConfigUpdate::default()
+        })),
+        &[],
+    )
+    .unwrap();
+    assert!(!query_config(&app).fee_switch.enabled);
+
+    // A single invalid parameter rejects the whole update
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::new(ConfigUpdate {
+            close_factor: Some(Decimal::percent(75)),
+            liquidation_bonus: Some(Decimal::one()),
+            ..ConfigUpdate::default()
+        })),
+        &[],
+    );
+    assert!(res.is_err());
+    assert_eq!(query_config(&app).close_factor, Decimal::percent(50));
+
+    // Healths are checked against each other as they will be after the change. Raising the
+    // liquidation health above the current mintable health works when both move together
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::new(ConfigUpdate {
+            collateral_health: Some(CollateralHealthUpdate {
+                collateral_id: "uatom".to_string(),
+                liquidation_health: Some(Decimal::from_ratio(200u128, 100u128)),
+                mintable_health: Some(Decimal::from_ratio(150u128, 100u128)),
+            }),
+            ..ConfigUpdate::default()
+        })),
+        &[],
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "Mintable Health cannot be set to be lower than Liquidation Health"
+    );
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::new(ConfigUpdate {
+            collateral_health: Some(CollateralHealthUpdate {
+                collateral_id: "uatom".to_string(),
+                liquidation_health: Some(Decimal::from_ratio(140u128, 100u128)),
+                mintable_health: Some(Decimal::from_ratio(160u128, 100u128)),
+            }),
+            close_factor: Some(Decimal::percent(75)),
+            liquidation_bonus: Some(Decimal::percent(8)),
+            ..ConfigUpdate::default()
+        })),
+        &[],
+    )
+    .unwrap();
+
+    // The rest applies right away, the health change waits out the timelock
+    let config = query_config(&app);
+    assert_eq!(config.close_factor, Decimal::percent(75));
+    assert_eq!(config.liquidation_bonus, Decimal::percent(8));
+    assert_eq!(
+        config.collaterals[0].collateral_info.liquidation_health,
+        Decimal::from_ratio(110u128, 100u128)
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(2 * 24 * 60 * 60));
+    app.execute_contract(user.clone(), dira_contract.clone(), &DiraExecuteMsg::ExecuteQueued { id: 1 }, &[])
+        .unwrap();
+
+    let config = query_config(&app);
+    assert_eq!(
+        config.collaterals[0].collateral_info.liquidation_health,
+        Decimal::from_ratio(140u128, 100u128)
+    );
+    assert_eq!(
+        config.collaterals[0].collateral_info.mintable_health,
+        Decimal::from_ratio(160u128, 100u128)
+    );
+
+    // Debt ceilings and stability fees apply right away, each under its own role
+    let debt_ceiling_and_stability_fee = ConfigUpdate {
+        debt_ceiling: Some(DebtCeilingUpdate {
+            collateral_id: "uatom".to_string(),
+            debt_ceiling: Some(Decimal::from_ratio(1_000u128, 1u128)),
+        }),
+        stability_fee: Some(StabilityFeeUpdate {
+            collateral_id: "uatom".to_string(),
+            stability_fee: Decimal::percent(3),
+        }),
+        ..ConfigUpdate::default()
+    };
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::new(debt_ceiling_and_stability_fee.clone())),
+        &[],
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "The user does not have the risk_manager role needed to perform this action"
+    );
+
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::new(ConfigUpdate {
+            debt_ceiling: Some(DebtCeilingUpdate {
+                collateral_id: "uosmo".to_string(),
+                debt_ceiling: None,
+            }),
+            ..ConfigUpdate::default()
+        })),
+        &[],
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "Collateral type uosmo is not registered"
+    );
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::new(debt_ceiling_and_stability_fee)),
+        &[],
+    )
+    .unwrap();
+
+    let config = query_config(&app);
+    assert_eq!(
+        config.collaterals[0].collateral_info.debt_ceiling,
+        Some(Decimal::from_ratio(1_000u128, 1u128))
+    );
+    assert_eq!(config.collaterals[0].collateral_info.stability_fee, Decimal::percent(3));
+
+    // The timelock delay and the CW20 DIRA contract need the owner and are queued like their setters
+    let cw20_code_id = app.store_code(cw20_contract());
+    let new_cw20_contract = app
+        .instantiate_contract(
+            cw20_code_id,
+            admin.clone(),
+            &Cw20InstantiateMsg {
+                name: "Dira".to_string(),
+                symbol: "DIRA".to_string(),
+                decimals: 6,
+                initial_balances: vec![],
+                mint: Some(MinterResponse {
+                    minter: dira_contract.to_string(),
+                    cap: None,
+                }),
+                marketing: None,
+            },
+            &[],
+            "CW20 Dira Token",
+            None,
+        )
+        .unwrap();
+    let timelock_delay_and_cw20 = ConfigUpdate {
+        timelock_delay_seconds: Some(24 * 60 * 60),
+        cw20_dira_contract_address: Some(new_cw20_contract.clone()),
+        ..ConfigUpdate::default()
+    };
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::GrantRole {
+            role: Role::RiskManager,
+            address: user.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::new(timelock_delay_and_cw20.clone())),
+        &[],
+    );
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        "The user does not have the owner role needed to perform this action"
+    );
+
+    let cw20_dira_contract_address = config.cw20_dira_contract_address.clone();
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UpdateConfig(Box::new(timelock_delay_and_cw20)),
+        &[],
+    )
+    .unwrap();
+
+    let config = query_config(&app);
+    assert_eq!(config.timelock_delay_seconds, 2 * 24 * 60 * 60);
+    assert_eq!(config.cw20_dira_contract_address, cw20_dira_contract_address);
+
+    let queued_changes: QueuedChangesResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryQueuedChanges {})
+        .unwrap();
+    assert_eq!(
+        queued_changes
+            .changes
+            .iter()
+            .map(|queued_change| queued_change.change.clone())
+            .collect::<Vec<_>>(),
+        vec![
+            ParameterChange::TimelockDelay {
+                timelock_delay_seconds: 24 * 60 * 60,
+            },
+            ParameterChange::Cw20DiraContractAddress {
+                cw20_dira_contract_address: new_cw20_contract,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_query_all_positions() {
+    let (mut app, dira_contract, _, admin, user) = setup_app();
+
+    let query_all_positions = |app: &App,
+                               start_after: Option<(Addr, String)>,
+                               limit: Option<u32>,
+                               below_health: Option<Decimal>|
+     -> PositionsResponse {
+        app.wrap()
+            .query_wasm_smart(
+                dira_contract.clone(),
+                &StableDiraQueryMsg::QueryAllPositions {
+                    start_after,
+                    limit,
+                    below_health,
+                },
+            )
+            .unwrap()
+    };
+
+    assert!(query_all_positions(&app, None, None, None).positions.is_empty());
+
+    // The user mints 5 DIRA and the admin 7 DIRA, each against 1 atom at a price of 10.00
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id: "uatom".to_string(),
+            collateral_price_in_dirham: Decimal::from_ratio(10u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+    for (wallet, dira_to_mint) in [(&user, 5u128), (&admin, 7u128)] {
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::LockCollateral {
+                collateral_id: "uatom".to_string(),
+            },
+            &coins(1_000_000, "uatom"),
+        )
+        .unwrap();
+        app.execute_contract(
+            wallet.clone(),
+            dira_contract.clone(),
+            &DiraExecuteMsg::MintDira {
+                collateral_id: "uatom".to_string(),
+                dira_to_mint: Decimal::from_ratio(dira_to_mint, 1u128),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    // Positions are listed by wallet address, the admin's address sorts first
+    let admin_dira_owed = Decimal::from_ratio(7u128, 1u128);
+    let user_dira_owed = Decimal::from_ratio(5u128, 1u128);
+
+    let res = query_all_positions(&app, None, None, None);
+    assert_eq!(res.positions.len(), 2);
+    assert_eq!(res.last_position, None);
+    assert_eq!(res.positions[0].wallet_address, admin);
+    assert_eq!(res.positions[0].collateral_locked, Decimal::one());
+    assert_eq!(res.positions[0].dira_owed, admin_dira_owed);
+    assert_eq!(
+        res.positions[0].health,
+        Some(Decimal::from_ratio(10u128, 1u128) / admin_dira_owed)
+    );
+    assert_eq!(res.positions[1].wallet_address, user);
+    assert_eq!(
+        res.positions[1].health,
+        Some(Decimal::from_ratio(10u128, 1u128) / user_dira_owed)
+    );
+
+    // Paging one position at a time
+    let res = query_all_positions(&app, None, Some(1), None);
+    assert_eq!(res.positions.len(), 1);
+    assert_eq!(res.last_position, Some((admin.clone(), "uatom".to_string())));
+
+    let res = query_all_positions(&app, res.last_position, Some(1), None);
+    assert_eq!(res.positions[0].wallet_address, user);
+    assert_eq!(res.last_position, Some((user.clone(), "uatom".to_string())));
+
+    let res = query_all_positions(&app, res.last_position, Some(1), None);
+    assert!(res.positions.is_empty());
+    assert_eq!(res.last_position, None);
+
+    // Keepers only look for positions close to liquidation
+    let res = query_all_positions(&app, None, None, Some(Decimal::from_ratio(150u128, 100u128)));
+    assert_eq!(res.positions.len(), 1);
+    assert_eq!(res.positions[0].wallet_address, admin);
+
+    let res = query_all_positions(&app, None, None, Some(Decimal::one()));
+    assert!(res.positions.is_empty());
+}
+
+#[test]
+fn test_query_functions() {
+    let (mut app, dira_contract, _cw20_contract, admin, user) = setup_app();
+
+    // Update initial state - Set collateral price
+    let set_price_msg = DiraExecuteMsg::SetCollateralPriceInDirham {
+        collateral_id: "uatom".to_string(),
+        collateral_price_in_dirham: Decimal::from_ratio(2500u128, 100u128), // 25.00
+    };
+    app.execute_contract(admin.clone(), dira_contract.clone(), &set_price_msg, &[])
+        .unwrap();
+
+    // Lock collateral for both admin and user
+    let lock_collateral_msg = DiraExecuteMsg::LockCollateral {
+        collateral_id: "uatom".to_string(),
+    };
+    dbg!(app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &lock_collateral_msg,
+        &coins(100_000_000_000u128, "uatom"),
+    ))
+    .unwrap();
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &lock_collateral_msg,
+        &coins(100_000_000_000u128, "uatom"),
+    )
+    .unwrap();
+
+    // Mint DIRA for both admin and user
+    let mint_msg = DiraExecuteMsg::MintDira {
+        collateral_id: "uatom".to_string(),
+        dira_to_mint: Decimal::from_ratio(5000u128, 100u128), // 50 DIRA
+    };
+    app.execute_contract(admin.clone(), dira_contract.clone(), &mint_msg, &[])
+        .unwrap();
+    app.execute_contract(user.clone(), dira_contract.clone(), &mint_msg, &[])
+        .unwrap();
+
+    // Query locked collateral
+    let query_locked = StableDiraQueryMsg::QueryLockedCollateral {
+        wallet_address_to_query: admin.clone(),
+        collateral_id: "uatom".to_string(),
+    };
+    let res: CollateralResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &query_locked)
+        .unwrap();
+    assert_eq!(
+        res.collateral_locked,
+        Decimal::from_ratio(100_000_000_000u128, 1000000u128)
+    );
+    dbg!("Admin's locked collateral:", res.collateral_locked);
+
+    // Query minted DIRA
+    let query_minted = StableDiraQueryMsg::QueryMintedDira {
+        wallet_address_to_query: user.clone(),
+        collateral_id: "uatom".to_string(),
+    };
+    let res: MintedDiraResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &query_minted)
+        .unwrap();
+    assert_eq!(res.dira_minted, Decimal::from_ratio(50u128, 1u128));
+    dbg!("User's minted DIRA:", res.dira_minted);
+
+    // Query stablecoin health
+    let query_health = StableDiraQueryMsg::QueryStablecoinHealth {
+        stablecoin_minter_address_to_query: user.clone(),
+        collateral_id: "uatom".to_string(),
+    };
+    let res: StablecoinHealthResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &query_health)
+        .unwrap();
+    dbg!("User's stablecoin health:", res.health);
+
+    // Query collateral price
+    let res: CollateralPriceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryCollateralPrice {
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.collateral_price, Decimal::from_ratio(2500u128, 100u128));
+    dbg!("Collateral price:", res.collateral_price);
+
+    // Query liquidation health
+    let res: LiquidationHealthResponse = app
+        .wrap()
         .query_wasm_smart(
             dira_contract.clone(),
-            &StableDiraQueryMsg::QueryLiquidationHealth {},
+            &StableDiraQueryMsg::QueryLiquidationHealth {
+                collateral_id: "uatom".to_string(),
+            },
         )
         .unwrap();
     dbg!("Liquidation health threshold:", res.liquidation_health);
@@ -651,7 +4893,9 @@ fn test_query_functions() {
         .wrap()
         .query_wasm_smart(
             dira_contract.clone(),
-            &StableDiraQueryMsg::QueryMintableHealth {},
+            &StableDiraQueryMsg::QueryMintableHealth {
+                collateral_id: "uatom".to_string(),
+            },
         )
         .unwrap();
     dbg!("Mintable health threshold:", res.mintable_health);
@@ -666,16 +4910,17 @@ fn test_query_functions() {
         .unwrap();
     dbg!("Admin addresses:", res.admin_addresses);
 
-    // Query collateral token denom
-    let res: CollateralTokenDenomResponse = app
+    // Query registered collateral types
+    let res: CollateralsResponse = app
         .wrap()
         .query_wasm_smart(
             dira_contract.clone(),
-            &StableDiraQueryMsg::QueryCollateralTokenDenom {},
+            &StableDiraQueryMsg::QueryCollaterals {},
         )
         .unwrap();
-    assert_eq!(res.collateral_token_denom, "uatom");
-    dbg!("Collateral token denom:", res.collateral_token_denom);
+    assert_eq!(res.collaterals.len(), 1);
+    assert_eq!(res.collaterals[0].collateral_id, "uatom");
+    dbg!("Registered collateral types:", res.collaterals);
 
     // Query CW20 DIRA contract address
     let res: CW20DiraContractAddressResponse = app
@@ -691,19 +4936,299 @@ fn test_query_functions() {
     );
 }
 
+#[test]
+fn test_multiple_collateral_types() {
+    let (mut app, dira_contract, _cw20_contract, admin, user) = setup_app();
+
+    // Register OM as a second collateral type with a debt ceiling of 100 DIRA
+    let register_msg = DiraExecuteMsg::RegisterCollateral {
+        collateral_id: "uom".to_string(),
+        asset_type: CollateralAssetType::Native,
+        liquidation_health: Decimal::percent(120),
+        mintable_health: Decimal::percent(150),
+        debt_ceiling: Some(Decimal::from_ratio(100u128, 1u128)),
+        decimals: 6,
+    };
+    let res = app.execute_contract(user.clone(), dira_contract.clone(), &register_msg, &[]);
+    assert!(res.is_err());
+
+    app.execute_contract(admin.clone(), dira_contract.clone(), &register_msg, &[])
+        .unwrap();
+
+    // Registering the same collateral twice should fail
+    let res = app.execute_contract(admin.clone(), dira_contract.clone(), &register_msg, &[]);
+    assert!(res.is_err());
+
+    // Mintable health cannot be lower than liquidation health
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RegisterCollateral {
+            collateral_id: "ustatom".to_string(),
+            asset_type: CollateralAssetType::Native,
+            liquidation_health: Decimal::percent(150),
+            mintable_health: Decimal::percent(120),
+            debt_ceiling: None,
+            decimals: 6,
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id: "uom".to_string(),
+            collateral_price_in_dirham: Decimal::from_ratio(10u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // Locking OM under the ATOM collateral type should fail
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LockCollateral {
+            collateral_id: "uatom".to_string(),
+        },
+        &coins(100_000_000, "uom"),
+    );
+    assert!(res.is_err());
+
+    // Lock 100 OM, worth 1000 dirham
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LockCollateral {
+            collateral_id: "uom".to_string(),
+        },
+        &coins(100_000_000, "uom"),
+    )
+    .unwrap();
+
+    // Minting more than the debt ceiling should fail even with enough collateral
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::MintDira {
+            collateral_id: "uom".to_string(),
+            dira_to_mint: Decimal::from_ratio(200u128, 1u128),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::MintDira {
+            collateral_id: "uom".to_string(),
+            dira_to_mint: Decimal::from_ratio(50u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // The position is tracked per collateral type
+    let res: CollateralResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryLockedCollateral {
+                wallet_address_to_query: user.clone(),
+                collateral_id: "uom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.collateral_locked, Decimal::from_ratio(100u128, 1u128));
+
+    let res: CollateralResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryLockedCollateral {
+                wallet_address_to_query: user.clone(),
+                collateral_id: "uatom".to_string(),
+            },
+        )
+        .unwrap();
+    assert!(res.collateral_locked.is_zero());
+
+    let res: MintableHealthResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryMintableHealth {
+                collateral_id: "uom".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.mintable_health, Decimal::percent(150));
+
+    let res: CollateralsResponse = app
+        .wrap()
+        .query_wasm_smart(dira_contract.clone(), &StableDiraQueryMsg::QueryCollaterals {})
+        .unwrap();
+    assert_eq!(res.collaterals.len(), 2);
+    let om = res
+        .collaterals
+        .iter()
+        .find(|collateral| collateral.collateral_id == "uom")
+        .unwrap();
+    assert_eq!(om.total_dira_minted, Decimal::from_ratio(50u128, 1u128));
+}
+
+#[test]
+fn test_cw20_collateral() {
+    let (mut app, dira_contract, _cw20_contract, admin, user) = setup_app();
+
+    // Deploy a CW20 token that only exists as a CW20, such as an LP share
+    let cw20_code_id = app.store_code(cw20_contract());
+    let lp_token = app
+        .instantiate_contract(
+            cw20_code_id,
+            admin.clone(),
+            &Cw20InstantiateMsg {
+                name: "LP Share".to_string(),
+                symbol: "LPS".to_string(),
+                decimals: 6,
+                initial_balances: vec![Cw20Coin {
+                    address: user.to_string(),
+                    amount: Uint128::new(1_000_000_000),
+                }],
+                mint: None,
+                marketing: None,
+            },
+            &[],
+            "LP Share Token",
+            None,
+        )
+        .unwrap();
+
+    // A CW20 collateral must point to an actual CW20 contract
+    let res = app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RegisterCollateral {
+            collateral_id: user.to_string(),
+            asset_type: CollateralAssetType::Cw20,
+            liquidation_health: Decimal::percent(120),
+            mintable_health: Decimal::percent(150),
+            debt_ceiling: None,
+            decimals: 6,
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::RegisterCollateral {
+            collateral_id: lp_token.to_string(),
+            asset_type: CollateralAssetType::Cw20,
+            liquidation_health: Decimal::percent(120),
+            mintable_health: Decimal::percent(150),
+            debt_ceiling: None,
+            decimals: 6,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        admin.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::SetCollateralPriceInDirham {
+            collateral_id: lp_token.to_string(),
+            collateral_price_in_dirham: Decimal::from_ratio(2u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // CW20 collateral cannot be locked through the native LockCollateral message
+    let res = app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::LockCollateral {
+            collateral_id: lp_token.to_string(),
+        },
+        &[],
+    );
+    assert!(res.is_err());
+
+    // Lock 500 LP shares through the Receive hook
+    app.execute_contract(
+        user.clone(),
+        lp_token.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: dira_contract.to_string(),
+            amount: Uint128::new(500_000_000),
+            msg: to_json_binary(&DiraReceiveMsg::LockCollateral {}).unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let res: CollateralResponse = app
+        .wrap()
+        .query_wasm_smart(
+            dira_contract.clone(),
+            &StableDiraQueryMsg::QueryLockedCollateral {
+                wallet_address_to_query: user.clone(),
+                collateral_id: lp_token.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.collateral_locked, Decimal::from_ratio(500u128, 1u128));
+
+    // Unlocking returns the collateral through a CW20 transfer
+    app.execute_contract(
+        user.clone(),
+        dira_contract.clone(),
+        &DiraExecuteMsg::UnlockCollateral {
+            collateral_id: lp_token.to_string(),
+            collateral_amount_to_unlock: Decimal::from_ratio(200u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let balance: cw20::BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            lp_token.clone(),
+            &cw20::Cw20QueryMsg::Balance {
+                address: user.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, Uint128::new(700_000_000));
+
+    let balance: cw20::BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            lp_token.clone(),
+            &cw20::Cw20QueryMsg::Balance {
+                address: dira_contract.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, Uint128::new(300_000_000));
+}
+
 fn helper_calculate_fee_tier_amount(amount: Decimal) -> Decimal {
     if amount < Decimal::from_ratio(999u128, 1u128) {
 
         amount * Decimal::permille(3)
     } else if amount < Decimal::from_ratio(10_000u128, 1u128) {
 
-        amount * Decimal::permille(1_5)
+        amount * Decimal::permille(15)
     } else {
 
-        amount * Decimal::permille(0_5)
+        amount * Decimal::permille(5)
     }
 }
-
-fn to_cw20_amount(decimal: Decimal, decimals: u32) -> Uint128 {
-    decimal.atomics() / Uint128::from(10u128.pow(12 - decimals))
-}